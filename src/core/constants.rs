@@ -12,12 +12,21 @@ pub(crate) mod epub_settings {
 
     pub(crate) const MIN_PARAGRAPH_SPACING: f64 = 0.0;
     pub(crate) const MAX_PARAGRAPH_SPACING: f64 = 50.0;
+
+    // the line height is relative to the natural height of the lines of the font
+    pub(crate) const DEFAULT_LINE_HEIGHT: f64 = 1.0;
+    pub(crate) const MIN_LINE_HEIGHT: f64 = 1.0;
+    pub(crate) const MAX_LINE_HEIGHT: f64 = 2.5;
+
+    // in em, the font size of the paragraph
+    pub(crate) const MAX_FIRST_LINE_INDENT: f64 = 4.0;
 }
 
 pub(crate) mod commands {
     use druid::{FileInfo, Selector};
 
-    use crate::{widgets::{epub_page::sidebar::PanelButton, PromptOption}, data::{Recent, PagePosition}, PageType};
+    use crate::{widgets::{epub_page::sidebar::PanelButton, PromptOption}, data::{Recent, PagePosition, HighlightColor, LibraryResult}, PageType};
+    use crate::widgets::epub_page::pagination::PaginationKey;
 
     pub const MODIFY_EPUB_PATH: Selector<FileInfo> = Selector::new("epub_reader.modify-epub");
 
@@ -25,6 +34,9 @@ pub(crate) mod commands {
     pub const OPEN_REVERSE_OCR_1: druid::Selector<druid::FileInfo> = druid::Selector::new("epub_reader.open-reverse-ocr-1");
     pub const OPEN_REVERSE_OCR_2: druid::Selector<druid::FileInfo> = druid::Selector::new("epub_reader.open-reverse-ocr-2");
 
+    pub const EXPORT_ANNOTATIONS: Selector<FileInfo> = Selector::new("epub_reader.export-annotations");
+    pub const IMPORT_ANNOTATIONS: Selector<FileInfo> = Selector::new("epub_reader.import-annotations");
+
 
 
     pub const INTERNAL_COMMAND: Selector<InternalUICommand> =
@@ -33,10 +45,26 @@ pub(crate) mod commands {
     #[derive(Debug)]
     pub enum InternalUICommand {
         SwitchTab(PanelButton),
+        ClosePanel,
+        // open the Search panel and move the focus to its input
+        FocusSearch,
+        // move the focus to the input of a panel
+        FocusInput,
+        // move the focus to the page, so it receives the keys
+        FocusPage,
+        // a key pressed on the page, performing the action of the keymap
+        PageKeyDown(druid::KeyEvent),
+        OpenKeymapEditor,
         GoToMenu,
         OpenOCRDialog,
 
         OpenRecent(Recent),
+        // number of a search in the library and the results of a book,
+        // sent as soon as the book is searched
+        LibrarySearchResults((usize, druid::im::Vector<LibraryResult>)),
+        // number of a search in the library, sent when all the books are searched
+        LibrarySearchCompleted(usize),
+        OpenLibraryResult(LibraryResult),
         RemoveBook(String),
         UpdateBookInfo(String), 
 
@@ -52,9 +80,26 @@ pub(crate) mod commands {
         RequestReverseOCR((String, String)),
         OCRSearchCompleted(PagePosition),
         ReverseOCRCompleted(usize),
+        // path of the book, directory of its full-text index and settings of the indexed text
+        SearchIndexReady((String, std::path::PathBuf, crate::search::IndexSettings)),
+        // pages of a chapter counted in background: the pagination, the chapter and its pages
+        ChapterPaginated((PaginationKey, usize, usize)),
 
         EpubGoToPos(PagePosition),
+        // the last page of the current chapter
+        EpubGoToChapterEnd,
         EpubNavigate(bool),
+        // true for the next result of the search, false for the previous one
+        EpubGoToSearchResult(bool),
+        EpubOpenLink(String),
+        EpubOpenNote(String),
+        EpubGoBack,
+        AddBookmark,
+        CopySelection,
+        HighlightSelection(HighlightColor),
+        AnnotateSelection,
+        ExportAnnotations,
+        ImportAnnotations,
 
         UINavigate(PageType),
 
@@ -63,4 +108,6 @@ pub(crate) mod commands {
 }
 
 
-pub const APP_NAME: &str = "Rust Epub Reader";
\ No newline at end of file
+pub const APP_NAME: &str = "Rust Epub Reader";
+// the name of the directories of the application, like the one of the configuration
+pub const APP_DIR_NAME: &str = "rust-epub-reader";
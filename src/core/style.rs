@@ -1,13 +1,29 @@
-use druid::{Color, piet::ColorParseError};
+use druid::{Color, Env, Key};
 
-pub const LINK_COLOR: druid::Color = druid::Color::rgb8(0, 0, 0xEE);
+use crate::data::epub::theme::Theme;
 
-pub const PRIMARY_DARK : Result<Color, druid::piet::ColorParseError> = Color::from_hex_str("#3C4047");
-pub const PRIMARY_LIGHT : Result<Color, druid::piet::ColorParseError> = Color::from_hex_str("#637391");
+// colors of the theme chosen in the settings of the book, see apply_theme
+pub const BACKGROUND: Key<Color> = Key::new("epub_reader.theme.background");
+pub const TEXT: Key<Color> = Key::new("epub_reader.theme.text");
+pub const LINK: Key<Color> = Key::new("epub_reader.theme.link");
+pub const HIGHLIGHT: Key<Color> = Key::new("epub_reader.theme.highlight");
+pub const CHROME: Key<Color> = Key::new("epub_reader.theme.chrome");
+pub const CHROME_LIGHT: Key<Color> = Key::new("epub_reader.theme.chrome_light");
+pub const CHROME_TEXT: Key<Color> = Key::new("epub_reader.theme.chrome_text");
 
-pub fn get_color_unchecked(color: Result<Color, ColorParseError>) -> Color {
-    match color {
-        Ok(color) => color,
-        Err(_) => Color::rgb8(0, 0, 0),
-    }
+/**
+ * Set the colors of a theme in the environment of the widgets.
+ * The labels and the text boxes of druid follow the colors of the chrome.
+ */
+pub fn apply_theme(env: &mut Env, theme: &Theme) {
+    env.set(BACKGROUND, theme.background_color());
+    env.set(TEXT, theme.text_color());
+    env.set(LINK, theme.link_color());
+    env.set(HIGHLIGHT, theme.highlight_color());
+    env.set(CHROME, theme.chrome_color());
+    env.set(CHROME_LIGHT, theme.chrome_light_color());
+    env.set(CHROME_TEXT, theme.chrome_text_color());
+
+    env.set(druid::theme::WINDOW_BACKGROUND_COLOR, theme.chrome_color());
+    env.set(druid::theme::TEXT_COLOR, theme.chrome_text_color());
 }
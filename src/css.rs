@@ -0,0 +1,820 @@
+use std::collections::HashMap;
+
+use druid::{piet::TextAlignment, Color, Data, FontFamily, FontStyle, FontWeight, KeyOrValue};
+use serde::{Deserialize, Serialize};
+
+/**
+ * Module for parsing the stylesheets of a book and computing the style of
+ * the elements of a chapter.
+ *
+ * Only a subset of CSS is supported: selectors made of tags, classes and ids
+ * combined with the descendant combinator, and the properties that can be
+ * mapped on druid text attributes or on the layout of the paragraphs.
+ * Unsupported rules and properties are ignored.
+ */
+
+// named font sizes, relative to the default font size
+const FONT_SIZE_KEYWORDS: [(&str, f64); 9] = [
+    ("xx-small", 0.6),
+    ("x-small", 0.75),
+    ("small", 0.89),
+    ("medium", 1.),
+    ("large", 1.2),
+    ("x-large", 1.5),
+    ("xx-large", 2.),
+    ("smaller", 0.83),
+    ("larger", 1.2),
+];
+
+const COLOR_KEYWORDS: [(&str, Color); 10] = [
+    ("black", Color::BLACK),
+    ("white", Color::WHITE),
+    ("gray", Color::GRAY),
+    ("grey", Color::GRAY),
+    ("silver", Color::SILVER),
+    ("red", Color::RED),
+    ("green", Color::GREEN),
+    ("blue", Color::BLUE),
+    ("navy", Color::NAVY),
+    ("maroon", Color::MAROON),
+];
+
+/**
+ * A length as written in a stylesheet.
+ * Pixels are relative to a default font size of 16px, so the lengths of
+ * the book follow the font size chosen by the user.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    Em(f64),
+    Percent(f64),
+    Px(f64),
+}
+
+impl Length {
+    const CSS_DEFAULT_FONT_SIZE: f64 = 16.;
+
+    fn parse(value: &str) -> Option<Length> {
+        let value = value.trim();
+        let split = value
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+            .unwrap_or(value.len());
+        let (number, unit) = value.split_at(split);
+        let number: f64 = number.parse().ok()?;
+
+        match unit {
+            "em" | "rem" => Some(Length::Em(number)),
+            "%" => Some(Length::Percent(number)),
+            "px" => Some(Length::Px(number)),
+            "pt" => Some(Length::Px(number * 4. / 3.)),
+            "ex" => Some(Length::Em(number / 2.)),
+            "" if number == 0. => Some(Length::Px(0.)),
+            _ => None,
+        }
+    }
+
+    /**
+     * Convert the length in pixels.
+     *
+     * @param font_size: the font size the length is relative to
+     * @param default_font_size: the font size chosen by the user
+     */
+    pub fn to_px(&self, font_size: f64, default_font_size: f64) -> f64 {
+        match self {
+            Length::Em(em) => em * font_size,
+            Length::Percent(percent) => percent / 100. * font_size,
+            Length::Px(px) => px / Self::CSS_DEFAULT_FONT_SIZE * default_font_size,
+        }
+    }
+}
+
+// also chosen by the reader, overriding the alignment of the paragraphs of the book
+#[derive(Clone, Copy, Debug, Data, PartialEq, Serialize, Deserialize)]
+pub enum TextAlign {
+    Start,
+    End,
+    Center,
+    Justified,
+}
+
+impl Default for TextAlign {
+    fn default() -> Self {
+        TextAlign::Start
+    }
+}
+
+impl From<TextAlign> for TextAlignment {
+    fn from(align: TextAlign) -> Self {
+        match align {
+            TextAlign::Start => TextAlignment::Start,
+            TextAlign::End => TextAlignment::End,
+            TextAlign::Center => TextAlignment::Center,
+            TextAlign::Justified => TextAlignment::Justified,
+        }
+    }
+}
+
+/**
+ * The properties declared for an element.
+ * Properties not declared are None, and are inherited or defaulted.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Style {
+    pub font_family: Option<FontFamily>,
+    // the families named before the generic one, lowercase, in order of preference;
+    // they replace the generic family when the book embeds them
+    pub font_names: Option<Vec<String>>,
+    pub font_weight: Option<FontWeight>,
+    pub font_style: Option<FontStyle>,
+    pub font_size: Option<Length>,
+    // a key of the theme for the colors chosen by the reader, like the one of the links
+    pub color: Option<KeyOrValue<Color>>,
+    pub underline: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub text_align: Option<TextAlign>,
+    pub text_indent: Option<Length>,
+    // true when white spaces and line breaks are preserved
+    pub white_space: Option<bool>,
+    pub margin_top: Option<Length>,
+    pub margin_bottom: Option<Length>,
+    pub margin_left: Option<Length>,
+    pub margin_right: Option<Length>,
+}
+
+impl Style {
+    /**
+     * Parse the declarations of a rule, or of a style attribute.
+     *
+     * @param declarations: the declarations, like "font-weight: bold; margin: 0"
+     */
+    pub fn parse(declarations: &str) -> Style {
+        let mut style = Style::default();
+        for declaration in declarations.split(';') {
+            if let Some((property, value)) = declaration.split_once(':') {
+                let value = value.replace("!important", "");
+                style.set_property(&property.trim().to_lowercase(), value.trim());
+            }
+        }
+        style
+    }
+
+    fn set_property(&mut self, property: &str, value: &str) {
+        let lowercase_value = value.to_lowercase();
+        let value = lowercase_value.as_str();
+        match property {
+            // the names listed after a generic family are never used
+            "font-family" => {
+                let mut names = Vec::new();
+                self.font_family = None;
+                for family in value.split(',') {
+                    self.font_family = match unquote(family) {
+                        "serif" => Some(FontFamily::SERIF),
+                        "sans-serif" => Some(FontFamily::SANS_SERIF),
+                        "monospace" => Some(FontFamily::MONOSPACE),
+                        "system-ui" => Some(FontFamily::SYSTEM_UI),
+                        name => {
+                            names.push(name.to_string());
+                            continue;
+                        }
+                    };
+                    break;
+                }
+                self.font_names = Some(names);
+            }
+            "font-weight" => {
+                self.font_weight = match value {
+                    "bold" | "bolder" => Some(FontWeight::BOLD),
+                    "normal" | "lighter" => Some(FontWeight::REGULAR),
+                    _ => value.parse::<u16>().ok().map(FontWeight::new),
+                }
+            }
+            "font-style" => {
+                self.font_style = match value {
+                    "italic" | "oblique" => Some(FontStyle::Italic),
+                    "normal" => Some(FontStyle::Regular),
+                    _ => None,
+                }
+            }
+            "font-size" => {
+                self.font_size = FONT_SIZE_KEYWORDS
+                    .iter()
+                    .find(|(keyword, _)| *keyword == value)
+                    .map(|(_, em)| Length::Em(*em))
+                    .or_else(|| Length::parse(value))
+            }
+            "color" => self.color = parse_color(value).map(KeyOrValue::from),
+            "text-decoration" | "text-decoration-line" => {
+                self.underline = Some(value.contains("underline"));
+                self.strikethrough = Some(value.contains("line-through"));
+            }
+            "text-align" => {
+                self.text_align = match value {
+                    "left" | "start" => Some(TextAlign::Start),
+                    "right" | "end" => Some(TextAlign::End),
+                    "center" => Some(TextAlign::Center),
+                    "justify" => Some(TextAlign::Justified),
+                    _ => None,
+                }
+            }
+            "text-indent" => self.text_indent = Length::parse(value),
+            "white-space" => {
+                self.white_space = match value {
+                    "pre" | "pre-wrap" | "pre-line" | "break-spaces" => Some(true),
+                    "normal" | "nowrap" => Some(false),
+                    _ => None,
+                }
+            }
+            "margin-top" => self.margin_top = Length::parse(value),
+            "margin-bottom" => self.margin_bottom = Length::parse(value),
+            "margin-left" => self.margin_left = Length::parse(value),
+            "margin-right" => self.margin_right = Length::parse(value),
+            "margin" => {
+                let values: Vec<Option<Length>> =
+                    value.split_whitespace().map(Length::parse).collect();
+                // top, right, bottom, left, as the css shorthand
+                let (top, right, bottom, left) = match values.as_slice() {
+                    [all] => (*all, *all, *all, *all),
+                    [vertical, horizontal] => (*vertical, *horizontal, *vertical, *horizontal),
+                    [top, horizontal, bottom] => (*top, *horizontal, *bottom, *horizontal),
+                    [top, right, bottom, left, ..] => (*top, *right, *bottom, *left),
+                    [] => return,
+                };
+                self.margin_top = top;
+                self.margin_right = right;
+                self.margin_bottom = bottom;
+                self.margin_left = left;
+            }
+            _ => {}
+        }
+    }
+
+    /**
+     * Override the properties of this style with the ones declared in other.
+     */
+    pub fn merge(&mut self, other: &Style) {
+        fn merge_field<T: Clone>(field: &mut Option<T>, other: &Option<T>) {
+            if other.is_some() {
+                *field = other.clone();
+            }
+        }
+        merge_field(&mut self.font_family, &other.font_family);
+        merge_field(&mut self.font_names, &other.font_names);
+        merge_field(&mut self.font_weight, &other.font_weight);
+        merge_field(&mut self.font_style, &other.font_style);
+        merge_field(&mut self.font_size, &other.font_size);
+        merge_field(&mut self.color, &other.color);
+        merge_field(&mut self.underline, &other.underline);
+        merge_field(&mut self.strikethrough, &other.strikethrough);
+        merge_field(&mut self.text_align, &other.text_align);
+        merge_field(&mut self.text_indent, &other.text_indent);
+        merge_field(&mut self.white_space, &other.white_space);
+        merge_field(&mut self.margin_top, &other.margin_top);
+        merge_field(&mut self.margin_bottom, &other.margin_bottom);
+        merge_field(&mut self.margin_left, &other.margin_left);
+        merge_field(&mut self.margin_right, &other.margin_right);
+    }
+
+    /**
+     * Use the first font named by font-family which is embedded in the book.
+     *
+     * @param fonts: the family of each embedded font, by the lowercase name
+     *               the styles of the book use for it
+     */
+    pub fn resolve_font_family(&mut self, fonts: &HashMap<String, String>) {
+        let family = self
+            .font_names
+            .iter()
+            .flatten()
+            .find_map(|name| fonts.get(name));
+        if let Some(family) = family {
+            self.font_family = Some(FontFamily::new_unchecked(family.as_str()));
+        }
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    if value.starts_with('#') {
+        return Color::from_hex_str(value).ok();
+    }
+    if let Some(components) = value
+        .strip_prefix("rgb(")
+        .or_else(|| value.strip_prefix("rgba("))
+        .and_then(|v| v.strip_suffix(')'))
+    {
+        let components: Vec<u8> = components
+            .split(',')
+            .take(3)
+            .filter_map(|c| c.trim().parse::<f64>().ok())
+            .map(|c| c.clamp(0., 255.) as u8)
+            .collect();
+        if let [r, g, b] = components.as_slice() {
+            return Some(Color::rgb8(*r, *g, *b));
+        }
+        return None;
+    }
+    COLOR_KEYWORDS
+        .iter()
+        .find(|(keyword, _)| *keyword == value)
+        .map(|(_, color)| *color)
+}
+
+/**
+ * The style of an element once inheritance and defaults are applied.
+ * Lengths are already converted in pixels.
+ */
+#[derive(Clone, Debug)]
+pub struct ComputedStyle {
+    pub font_family: Option<FontFamily>,
+    pub font_size: f64,
+    pub font_weight: FontWeight,
+    pub font_style: FontStyle,
+    pub color: Option<KeyOrValue<Color>>,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub text_align: TextAlign,
+    pub text_indent: f64,
+    pub preserve_white_space: bool,
+    pub block: BlockStyle,
+}
+
+impl ComputedStyle {
+    pub fn new(font_size: f64) -> Self {
+        ComputedStyle {
+            font_family: None,
+            font_size,
+            font_weight: FontWeight::REGULAR,
+            font_style: FontStyle::Regular,
+            color: None,
+            underline: false,
+            strikethrough: false,
+            text_align: TextAlign::Start,
+            text_indent: 0.,
+            preserve_white_space: false,
+            block: BlockStyle::default(),
+        }
+    }
+
+    /**
+     * Compute the style of a child element.
+     * Text properties are inherited. Horizontal margins add up with the ones
+     * of the ancestors, so nested blocks are indented; vertical margins do not.
+     *
+     * @param style: the properties declared for the child
+     * @param default_font_size: the font size chosen by the user
+     */
+    pub fn child(&self, style: &Style, default_font_size: f64) -> ComputedStyle {
+        let font_size = style
+            .font_size
+            .map(|size| size.to_px(self.font_size, default_font_size))
+            .unwrap_or(self.font_size);
+        let to_px = |length: &Option<Length>| {
+            length
+                .map(|l| l.to_px(font_size, default_font_size))
+                .unwrap_or(0.)
+        };
+
+        ComputedStyle {
+            font_family: style
+                .font_family
+                .clone()
+                .or_else(|| self.font_family.clone()),
+            font_size,
+            font_weight: style.font_weight.unwrap_or(self.font_weight),
+            font_style: style.font_style.unwrap_or(self.font_style),
+            color: style.color.clone().or_else(|| self.color.clone()),
+            underline: style.underline.unwrap_or(self.underline),
+            strikethrough: style.strikethrough.unwrap_or(self.strikethrough),
+            text_align: style.text_align.unwrap_or(self.text_align),
+            text_indent: style
+                .text_indent
+                .map(|l| l.to_px(font_size, default_font_size))
+                .unwrap_or(self.text_indent),
+            preserve_white_space: style.white_space.unwrap_or(self.preserve_white_space),
+            block: BlockStyle {
+                text_align: style.text_align.unwrap_or(self.text_align),
+                margin_top: to_px(&style.margin_top),
+                margin_bottom: to_px(&style.margin_bottom),
+                margin_left: self.block.margin_left + to_px(&style.margin_left),
+                margin_right: self.block.margin_right + to_px(&style.margin_right),
+                text_indent: 0.,
+            },
+        }
+    }
+
+    /**
+     * Apply the text properties to a range of a RichText.
+     */
+    pub fn apply(&self, attrs: &mut druid::text::AttributesAdder) {
+        attrs.size(self.font_size).weight(self.font_weight);
+        if let Some(family) = &self.font_family {
+            attrs.font_family(family.clone());
+        }
+        if self.font_style == FontStyle::Italic {
+            attrs.style(FontStyle::Italic);
+        }
+        if let Some(color) = &self.color {
+            attrs.text_color(color.clone());
+        }
+        if self.underline {
+            attrs.underline(true);
+        }
+        if self.strikethrough {
+            attrs.strikethrough(true);
+        }
+    }
+}
+
+/**
+ * Layout properties of a paragraph, used when placing it in the page.
+ */
+#[derive(Clone, Debug, Data, PartialEq, Default)]
+pub struct BlockStyle {
+    pub text_align: TextAlign,
+    pub margin_top: f64,
+    pub margin_bottom: f64,
+    pub margin_left: f64,
+    pub margin_right: f64,
+    // indent of the first line, set by the parser for the text of the paragraph
+    pub text_indent: f64,
+}
+
+/**
+ * The information of an element used to match the selectors.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct ElementInfo {
+    pub name: String,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct CompoundSelector {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl CompoundSelector {
+    fn parse(selector: &str) -> Option<CompoundSelector> {
+        // pseudo classes, attributes and sibling combinators are not supported
+        if selector.contains(|c| matches!(c, ':' | '[' | '+' | '~')) {
+            return None;
+        }
+
+        let mut compound = CompoundSelector {
+            tag: None,
+            id: None,
+            classes: Vec::new(),
+        };
+        let mut rest = selector;
+        let tag_end = rest.find(|c| c == '.' || c == '#').unwrap_or(rest.len());
+        let tag = &rest[..tag_end];
+        if !tag.is_empty() && tag != "*" {
+            compound.tag = Some(tag.to_lowercase());
+        }
+        rest = &rest[tag_end..];
+
+        while !rest.is_empty() {
+            let kind = rest.chars().next()?;
+            let end = rest[1..]
+                .find(|c| c == '.' || c == '#')
+                .map(|i| i + 1)
+                .unwrap_or(rest.len());
+            let name = rest[1..end].to_string();
+            if name.is_empty() {
+                return None;
+            }
+            match kind {
+                '.' => compound.classes.push(name),
+                '#' => compound.id = Some(name),
+                _ => return None,
+            }
+            rest = &rest[end..];
+        }
+        Some(compound)
+    }
+
+    fn matches(&self, element: &ElementInfo) -> bool {
+        self.tag.as_ref().map_or(true, |tag| tag == &element.name)
+            && self
+                .id
+                .as_ref()
+                .map_or(true, |id| element.id.as_ref() == Some(id))
+            && self
+                .classes
+                .iter()
+                .all(|class| element.classes.contains(class))
+    }
+
+    fn specificity(&self) -> u32 {
+        self.id.iter().count() as u32 * 10_000
+            + self.classes.len() as u32 * 100
+            + self.tag.iter().count() as u32
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    // compound selectors, from the outermost ancestor to the element
+    selectors: Vec<CompoundSelector>,
+    specificity: u32,
+    style: Style,
+}
+
+impl Rule {
+    fn matches(&self, path: &[&ElementInfo]) -> bool {
+        let (last, ancestors_selectors) = match self.selectors.split_last() {
+            Some(split) => split,
+            None => return false,
+        };
+        let (element, mut ancestors) = match path.split_last() {
+            Some(split) => split,
+            None => return false,
+        };
+        if !last.matches(element) {
+            return false;
+        }
+
+        // each ancestor selector has to match an ancestor, in order
+        for selector in ancestors_selectors.iter().rev() {
+            match ancestors.iter().rposition(|a| selector.matches(a)) {
+                Some(position) => ancestors = &ancestors[..position],
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+/**
+ * A font declared by a @font-face rule.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontFace {
+    // the name used by font-family, lowercase
+    pub family: String,
+    // the urls of the font files, relative to the stylesheet
+    pub sources: Vec<String>,
+}
+
+impl FontFace {
+    fn parse(declarations: &str) -> Option<FontFace> {
+        let mut family = None;
+        let mut sources = Vec::new();
+        for declaration in declarations.split(';') {
+            match declaration.split_once(':') {
+                Some((property, value)) if property.trim().eq_ignore_ascii_case("font-family") => {
+                    family = Some(unquote(value).to_lowercase());
+                }
+                Some((property, value)) if property.trim().eq_ignore_ascii_case("src") => {
+                    // src: url("font.otf") format("opentype"), local(Font)
+                    let mut rest = value;
+                    while let Some(start) = rest.find("url(") {
+                        rest = &rest[start + 4..];
+                        let end = rest.find(')').unwrap_or(rest.len());
+                        sources.push(unquote(&rest[..end]).to_string());
+                        rest = &rest[end..];
+                    }
+                }
+                _ => {}
+            }
+        }
+        family.map(|family| FontFace { family, sources })
+    }
+}
+
+/**
+ * A list of css rules, in the order they were declared,
+ * with the fonts declared by the stylesheet.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Stylesheet {
+    rules: Vec<Rule>,
+    font_faces: Vec<FontFace>,
+}
+
+impl Stylesheet {
+    /**
+     * Parse a stylesheet. At-rules other than font faces, like media queries, are skipped.
+     *
+     * @param css: the text of the stylesheet
+     */
+    pub fn parse(css: &str) -> Stylesheet {
+        let css = strip_comments(css);
+        let mut rules = Vec::new();
+        let mut font_faces = Vec::new();
+        let mut rest = css.as_str();
+
+        while let Some(open) = rest.find('{') {
+            let prelude = rest[..open].trim();
+            let close = match find_block_end(&rest[open..]) {
+                Some(close) => open + close,
+                None => break,
+            };
+            let block = &rest[open + 1..close];
+            rest = &rest[close + 1..];
+
+            // a previous at-rule without block, like @import, ends with ';'
+            let prelude = prelude.rsplit(';').next().unwrap_or_default().trim();
+            if prelude.eq_ignore_ascii_case("@font-face") {
+                font_faces.extend(FontFace::parse(block));
+                continue;
+            }
+            if prelude.starts_with('@') {
+                continue;
+            }
+
+            let style = Style::parse(block);
+            for selector in prelude.split(',') {
+                let selectors: Option<Vec<CompoundSelector>> = selector
+                    .split(|c: char| c.is_whitespace() || c == '>')
+                    .filter(|s| !s.is_empty())
+                    .map(CompoundSelector::parse)
+                    .collect();
+                if let Some(selectors) = selectors.filter(|s| !s.is_empty()) {
+                    rules.push(Rule {
+                        specificity: selectors.iter().map(|s| s.specificity()).sum(),
+                        selectors,
+                        style: style.clone(),
+                    });
+                }
+            }
+        }
+        Stylesheet { rules, font_faces }
+    }
+
+    /**
+     * Add the rules of another stylesheet, declared after the ones of this stylesheet.
+     */
+    pub fn extend(&mut self, other: Stylesheet) {
+        self.rules.extend(other.rules);
+        self.font_faces.extend(other.font_faces);
+    }
+
+    /**
+     * The fonts declared by the @font-face rules of the stylesheet.
+     */
+    pub fn font_faces(&self) -> &[FontFace] {
+        &self.font_faces
+    }
+
+    /**
+     * Get the properties declared for an element.
+     * Rules with an higher specificity win, then the last declared.
+     *
+     * @param path: the element, preceded by its ancestors
+     */
+    pub fn style_for(&self, path: &[&ElementInfo]) -> Style {
+        let mut matching: Vec<&Rule> = self.rules.iter().filter(|r| r.matches(path)).collect();
+        // stable sort keeps the declaration order for equal specificity
+        matching.sort_by_key(|r| r.specificity);
+
+        let mut style = Style::default();
+        for rule in matching {
+            style.merge(&rule.style);
+        }
+        style
+    }
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut result = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        result.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    result.push_str(rest);
+    result
+}
+
+/**
+ * Find the closing brace of a block, taking care of nested blocks.
+ *
+ * @param block: text starting with the opening brace
+ */
+fn find_block_end(block: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in block.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(name: &str, id: Option<&str>, classes: &[&str]) -> ElementInfo {
+        ElementInfo {
+            name: name.to_string(),
+            id: id.map(|id| id.to_string()),
+            classes: classes.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_stylesheet() {
+        let css = "
+            /* comment */
+            @import url(other.css);
+            @media print { p { color: red } }
+            p, div.note { text-indent: 1.5em; margin: 0 }
+            p.first { text-indent: 0 }
+            a:hover { color: red }
+        ";
+        let stylesheet = Stylesheet::parse(css);
+
+        assert_eq!(stylesheet.rules.len(), 3);
+        assert_eq!(stylesheet.rules[0].style.text_indent, Some(Length::Em(1.5)));
+        assert_eq!(stylesheet.rules[0].style.margin_left, Some(Length::Px(0.)));
+    }
+
+    #[test]
+    fn test_style_for_specificity_and_order() {
+        let css = "
+            .chapter p { font-style: italic; text-align: justify }
+            p { font-weight: bold; text-align: center }
+            #intro { font-weight: normal }
+        ";
+        let stylesheet = Stylesheet::parse(css);
+        let body = element("body", None, &[]);
+        let div = element("div", None, &["chapter"]);
+        let p = element("p", Some("intro"), &[]);
+
+        let style = stylesheet.style_for(&[&body, &div, &p]);
+        assert_eq!(style.font_style, Some(FontStyle::Italic));
+        assert_eq!(style.font_weight, Some(FontWeight::REGULAR));
+        assert_eq!(style.text_align, Some(TextAlign::Justified));
+
+        let style = stylesheet.style_for(&[&element("p", None, &[])]);
+        assert_eq!(style.font_style, None);
+        assert_eq!(style.text_align, Some(TextAlign::Center));
+    }
+
+    #[test]
+    fn test_font_faces() {
+        let css = "
+            @font-face { font-family: 'Book Serif'; src: url(\"../Fonts/serif.otf\") format('opentype') }
+            p { font-family: Georgia, \"Book Serif\", serif }
+            h1 { font-family: sans-serif, 'Book Serif' }
+        ";
+        let stylesheet = Stylesheet::parse(css);
+        assert_eq!(
+            stylesheet.font_faces(),
+            &[FontFace {
+                family: "book serif".to_string(),
+                sources: vec!["../Fonts/serif.otf".to_string()],
+            }]
+        );
+
+        let fonts = HashMap::from([("book serif".to_string(), "Book Serif Pro".to_string())]);
+        let mut style = stylesheet.style_for(&[&element("p", None, &[])]);
+        style.resolve_font_family(&fonts);
+        assert_eq!(
+            style.font_family,
+            Some(FontFamily::new_unchecked("Book Serif Pro"))
+        );
+
+        let mut style = stylesheet.style_for(&[&element("h1", None, &[])]);
+        style.resolve_font_family(&fonts);
+        assert_eq!(style.font_family, Some(FontFamily::SANS_SERIF));
+    }
+
+    #[test]
+    fn test_computed_style_inheritance() {
+        let parent = ComputedStyle::new(16.).child(
+            &Style::parse("font-size: 150%; margin-left: 2em; margin-top: 1em"),
+            16.,
+        );
+        assert_eq!(parent.font_size, 24.);
+        assert_eq!(parent.block.margin_left, 48.);
+        assert_eq!(parent.block.margin_top, 24.);
+
+        let child = parent.child(
+            &Style::parse("font-weight: bold; font-size: 12px; margin-left: 10px"),
+            20.,
+        );
+        assert_eq!(child.font_size, 15.);
+        assert_eq!(child.font_weight, FontWeight::BOLD);
+        assert_eq!(child.block.margin_left, 48. + 12.5);
+        assert_eq!(child.block.margin_top, 0.);
+    }
+}
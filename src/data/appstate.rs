@@ -1,8 +1,15 @@
+use std::path::PathBuf;
+
 use druid::{AppDelegate, Command, Data, DelegateCtx, Env, Handled, Lens, Target};
 
 use crate::{
     core::constants::commands::{InternalUICommand, INTERNAL_COMMAND},
-    data::{epub::EpubData, home::Recent, HomePageData},
+    data::{
+        epub::{settings::EpubSettings, EpubData},
+        home::Recent,
+        AppConfig, HomePageData,
+    },
+    search::IndexSettings,
     PageType,
 };
 use epub::doc::EpubDoc;
@@ -17,6 +24,7 @@ pub struct AppState {
     pub epub_data: EpubData,
     pub home_page_data: HomePageData,
     pub active_page: PageType,
+    pub config: AppConfig,
 }
 
 pub struct Delegate;
@@ -24,7 +32,7 @@ pub struct Delegate;
 impl AppDelegate<AppState> for Delegate {
     fn command(
         &mut self,
-        _ctx: &mut DelegateCtx,
+        ctx: &mut DelegateCtx,
         _target: Target,
         cmd: &Command,
         data: &mut AppState,
@@ -33,7 +41,8 @@ impl AppDelegate<AppState> for Delegate {
         if let Some(file_info) = cmd.get(druid::commands::OPEN_FILE) {
 
             if let Some(path) = file_info.path().to_str() {
-                let recent = Recent::new(path.to_owned());
+                let mut recent = Recent::new(path.to_owned());
+                recent.epub_settings = data.config.default_settings.clone();
                 // if recent already exists, do not add it again
                 if !(data
                     .home_page_data
@@ -43,6 +52,12 @@ impl AppDelegate<AppState> for Delegate {
                 {
                     match data.open_file(&recent) {
                         Ok(_) => {
+                            start_indexing_in_thread(
+                                ctx.get_external_handle(),
+                                recent.path.clone(),
+                                recent.epub_settings.clone(),
+                                data.home_page_data.data_dir.clone(),
+                            );
                             data.home_page_data.add_to_recents(recent);
                             data.active_page = PageType::Reader;
                         }
@@ -71,6 +86,8 @@ impl AppDelegate<AppState> for Delegate {
 
                     recent.reached_position = Some(data.epub_data.page_position.clone());
                     recent.epub_settings = data.epub_data.epub_settings.clone();
+                    recent.bookmarks = data.epub_data.bookmarks.iter().cloned().collect();
+                    recent.highlights = data.epub_data.highlights.iter().cloned().collect();
                     data.home_page_data.update_recent(recent);
 
                     return Handled::Yes;
@@ -78,7 +95,19 @@ impl AppDelegate<AppState> for Delegate {
                 InternalUICommand::OpenRecent(recent) => {
                     match data.open_file(recent) {
                         Err(e) => println!("Error: {:?}", e),
-                        _ => {}
+                        _ => start_indexing_in_thread(
+                            ctx.get_external_handle(),
+                            recent.path.clone(),
+                            recent.epub_settings.clone(),
+                            data.home_page_data.data_dir.clone(),
+                        ),
+                    }
+                    return Handled::Yes;
+                }
+                InternalUICommand::SearchIndexReady((book_path, index_dir, settings)) => {
+                    // the reader may have opened another book in the meantime
+                    if &data.epub_data.get_epub_path() == book_path {
+                        data.epub_data.set_search_index(index_dir, *settings);
                     }
                     return Handled::Yes;
                 }
@@ -93,10 +122,16 @@ impl AppDelegate<AppState> for Delegate {
 
 impl AppState {
     pub fn new() -> Self {
+        let (config, error) = AppConfig::load();
+        let mut home_page_data = HomePageData::new(config.data_dir());
+        if let Some(error) = error {
+            home_page_data.show_error(error);
+        }
         AppState {
-            home_page_data: HomePageData::new(),
+            home_page_data,
             epub_data: EpubData::default(),
             active_page: PageType::Home,
+            config,
         }
     }
 
@@ -114,11 +149,57 @@ impl AppState {
 
         self.epub_data = EpubData::new(doc);
         self.epub_data.epub_settings = file_info.epub_settings.to_owned();
+        self.epub_data.bookmarks = file_info.bookmarks.iter().cloned().collect();
+        self.epub_data.highlights = file_info.highlights.iter().cloned().collect();
         if let Some(page_index) = &file_info.reached_position {
             self.epub_data.change_position(page_index.clone());
         }
         Ok(())
     }
+
+    /**
+     * Save the configuration of the application.
+     * If it cannot be written, the error is shown to the user.
+     */
+    pub fn save_config(&mut self) {
+        if let Err(e) = self.config.save() {
+            self.home_page_data.show_error(format!(
+                "The settings could not be saved in {}: {}",
+                AppConfig::path().display(),
+                e
+            ));
+        }
+    }
+}
+
+/**
+ * Build the full-text index of a book in a background thread, if it was
+ * never built, and send its directory to the reader when it can be used.
+ * If the index cannot be built, the reader keeps searching without it.
+ *
+ * @param sink: the sink used to send the result to the main thread
+ * @param book_path: the path of the book
+ * @param settings: the settings of the book, its text is indexed as they render it
+ * @param data_dir: the data directory of the application, with the indexes
+ */
+fn start_indexing_in_thread(
+    sink: druid::ExtEventSink,
+    book_path: String,
+    settings: EpubSettings,
+    data_dir: PathBuf,
+) {
+    std::thread::spawn(move || {
+        if let Ok(index_dir) = crate::search::open_or_build_index(&book_path, &settings, &data_dir)
+        {
+            let settings = IndexSettings::new(&settings);
+            sink.submit_command(
+                INTERNAL_COMMAND,
+                InternalUICommand::SearchIndexReady((book_path, index_dir, settings)),
+                Target::Global,
+            )
+            .expect("command failed to submit");
+        }
+    });
 }
 
 #[derive(PartialEq, Debug)]
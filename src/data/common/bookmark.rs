@@ -0,0 +1,36 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use druid::{Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::page_position::PagePosition;
+
+/**
+ * A place of the book marked by the user.
+ * It is saved with the other information of the book, so it is found again
+ * when the book is opened.
+ */
+#[derive(Clone, Data, Lens, Serialize, Deserialize, Debug)]
+pub struct Bookmark {
+    pub position: PagePosition,
+    pub label: String,
+    // seconds since the UNIX epoch
+    pub created: u64,
+    // beginning of the text at the position
+    pub snippet: String,
+}
+
+impl Bookmark {
+    pub fn new(position: PagePosition, label: String, snippet: String) -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Bookmark {
+            position,
+            label,
+            created,
+            snippet,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use druid::{Color, Data, Lens};
+use serde::{Deserialize, Serialize};
+
+/**
+ * The colors a text can be highlighted with.
+ */
+#[derive(Clone, Copy, Data, PartialEq, Serialize, Deserialize, Debug)]
+pub enum HighlightColor {
+    Yellow,
+    Green,
+    Blue,
+    Pink,
+}
+
+impl HighlightColor {
+    pub const ALL: [HighlightColor; 4] = [
+        HighlightColor::Yellow,
+        HighlightColor::Green,
+        HighlightColor::Blue,
+        HighlightColor::Pink,
+    ];
+
+    /**
+     * The color painted under the highlighted text, transparent so the text is readable.
+     */
+    pub fn color(&self) -> Color {
+        match self {
+            HighlightColor::Yellow => Color::rgba8(255, 235, 59, 120),
+            HighlightColor::Green => Color::rgba8(118, 255, 3, 100),
+            HighlightColor::Blue => Color::rgba8(64, 196, 255, 100),
+            HighlightColor::Pink => Color::rgba8(255, 64, 129, 90),
+        }
+    }
+}
+
+/**
+ * A highlighted part of the text of a book.
+ * The range is in characters of a richtext of the chapter, like the offset
+ * of a PagePosition, so it does not depend on how the text is laid out.
+ * A selection spanning more richtexts is saved as one highlight for each of them.
+ * The reader can attach a note to a highlight.
+ */
+#[derive(Clone, Data, Lens, Serialize, Deserialize, Debug)]
+pub struct Highlight {
+    pub chapter: usize,
+    pub richtext_number: usize,
+    pub start: usize,
+    pub end: usize,
+    pub color: HighlightColor,
+    // seconds since the UNIX epoch
+    pub created: u64,
+    // empty if the highlight has no note
+    #[serde(default)]
+    pub note: String,
+}
+
+impl Highlight {
+    pub fn new(
+        chapter: usize,
+        richtext_number: usize,
+        range: std::ops::Range<usize>,
+        color: HighlightColor,
+    ) -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Highlight {
+            chapter,
+            richtext_number,
+            start: range.start,
+            end: range.end,
+            color,
+            created,
+            note: String::new(),
+        }
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+}
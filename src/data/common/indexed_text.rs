@@ -16,11 +16,28 @@ pub struct IndexedText {
     
     #[lens(name = "value_lens")]
     value: Arc<PagePosition>,
+
+    // byte range of the key shown in bold, like the match of a search result
+    emphasis: Option<(usize, usize)>,
 }
 
 impl IndexedText {  
     pub fn new(key: ArcStr, value: Arc<PagePosition>) -> Self {
-        IndexedText { key, value }
+        IndexedText {
+            key,
+            value,
+            emphasis: None,
+        }
+    }
+
+    /**
+     * Show a part of the key in bold.
+     *
+     * @param range: the byte range of the part of the key
+     */
+    pub fn with_emphasis(mut self, range: std::ops::Range<usize>) -> Self {
+        self.emphasis = Some((range.start, range.end));
+        self
     }
     
     pub fn key(&self) -> &ArcStr {
@@ -31,12 +48,17 @@ impl IndexedText {
         &self.value
     }
 
+    pub fn emphasis(&self) -> Option<std::ops::Range<usize>> {
+        self.emphasis.map(|(start, end)| start..end)
+    }
+
 }
 impl Default for IndexedText {
     fn default() -> Self {
         IndexedText {
             key: ArcStr::from(""),
             value: Arc::new(PagePosition::default()),
+            emphasis: None,
         }
     }
 }
\ No newline at end of file
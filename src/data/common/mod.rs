@@ -1,2 +1,4 @@
+pub(crate) mod bookmark;
+pub(crate) mod highlight;
 pub(crate) mod indexed_text;
 pub(crate) mod page_position;
\ No newline at end of file
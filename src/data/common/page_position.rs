@@ -5,11 +5,18 @@ use serde::{Serialize, Deserialize};
  * This struct is used to store the a position in the book.
  * It can also represent a range of text 
  * 
+ * The offset is the number of characters preceding the position in the
+ * richtext, so the position is kept when the text is laid out again
+ * (with another window size or font size).
+ * The range, like the offset, counts characters.
  */
 #[derive(Clone, Debug, Data, Serialize, Deserialize)]
 pub struct PagePosition {
     chapter: usize,
     richtext_number: usize,
+    // positions saved before the offset was introduced start from the richtext
+    #[serde(default)]
+    offset: usize,
     #[serde(skip)]
     range: Option<std::ops::Range<usize>>,
 }
@@ -17,8 +24,8 @@ pub struct PagePosition {
 impl ToString for PagePosition {
     fn to_string(&self) -> String {
         format!(
-            "Chapter: {} - Pos: {}",
-            self.chapter, self.richtext_number
+            "Chapter: {} - Pos: {} - Offset: {}",
+            self.chapter, self.richtext_number, self.offset
         )
     }
 }
@@ -29,10 +36,24 @@ impl PagePosition {
         PagePosition {
             chapter,
             richtext_number,
+            offset: 0,
             range: None,
         }
     }
 
+    pub const fn with_offset(chapter: usize, richtext_number: usize, offset: usize) -> Self {
+        PagePosition {
+            chapter,
+            richtext_number,
+            offset,
+            range: None,
+        }
+    }
+
+    /**
+     * A range of text, like the match of a search.
+     * The position is the beginning of the range.
+     */
     pub fn with_range(
         chapter: usize,
         richtext_number: usize,
@@ -41,6 +62,7 @@ impl PagePosition {
         PagePosition {
             chapter,
             richtext_number,
+            offset: range.start,
             range: Some(range),
         }
     }
@@ -53,6 +75,10 @@ impl PagePosition {
         self.richtext_number
     }
 
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
     pub fn range(&self) -> &Option<std::ops::Range<usize>> {
         &self.range
     }
@@ -63,6 +89,9 @@ impl PagePosition {
     pub fn set_richtext_number(&mut self, richtext_number: usize) {
         self.richtext_number = richtext_number;
     }
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
 }
 
 
@@ -76,16 +105,38 @@ impl Default for PagePosition {
 
 impl PartialEq for PagePosition {
     fn eq(&self, other: &Self) -> bool {
-        self.chapter == other.chapter && self.richtext_number == other.richtext_number
+        self.chapter == other.chapter
+            && self.richtext_number == other.richtext_number
+            && self.offset == other.offset
     }
 }
 
 impl PartialOrd for PagePosition {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if self.chapter == other.chapter {
-            self.richtext_number.partial_cmp(&other.richtext_number)
-        } else {
-            self.chapter.partial_cmp(&other.chapter)
-        }
+        (self.chapter, self.richtext_number, self.offset).partial_cmp(&(
+            other.chapter,
+            other.richtext_number,
+            other.offset,
+        ))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_position_serialization() {
+        let position = PagePosition::with_offset(3, 12, 140);
+        let json = serde_json::to_string(&position).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PagePosition>(&json).unwrap(),
+            position
+        );
+
+        // positions saved without an offset start from the beginning of the richtext
+        let old_position: PagePosition =
+            serde_json::from_str(r#"{"chapter":3,"richtext_number":12}"#).unwrap();
+        assert_eq!(old_position, PagePosition::new(3, 12));
+        assert!(old_position < position);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,274 @@
+use std::path::PathBuf;
+
+use druid::{Data, Lens, Point, Size, WindowHandle, WindowState};
+use serde::{Deserialize, Serialize};
+
+use crate::core::constants::{epub_settings, APP_DIR_NAME};
+
+use super::{
+    epub::{settings::EpubSettings, theme::Theme},
+    storage, Keymap,
+};
+
+/**
+ * The settings of the application, shared by all the books.
+ * They are saved in the configuration directory of the user
+ * (like ~/.config on Linux) and loaded at startup.
+ */
+#[derive(Clone, Data, Lens, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    // the settings of the books opened for the first time
+    pub default_settings: EpubSettings,
+    // the values allowed for the settings of the books
+    pub ranges: SettingsRanges,
+    // the theme of the home page and of the settings page
+    pub theme: Theme,
+    pub keymap: Keymap,
+    // where the recents and the search indexes are kept, empty for the default directory
+    pub data_dir: String,
+    pub window: WindowGeometry,
+}
+
+impl AppConfig {
+    const CONFIG_FILE: &'static str = "config.json";
+    // used when the configuration directory of the user is unknown
+    const FALLBACK_CONFIG_PATH: &'static str = ".config.json";
+    // used when the data directory of the user is unknown
+    const FALLBACK_DATA_DIR: &'static str = ".";
+
+    /**
+     * The path of the configuration file.
+     */
+    pub fn path() -> PathBuf {
+        match dirs::config_dir() {
+            Some(dir) => dir.join(APP_DIR_NAME).join(AppConfig::CONFIG_FILE),
+            None => PathBuf::from(AppConfig::FALLBACK_CONFIG_PATH),
+        }
+    }
+
+    /**
+     * Load the configuration from its file.
+     * The settings missing in the file take their default value.
+     * A file which cannot be read is moved aside, so it is not overwritten.
+     *
+     * @return the configuration, with the message shown to the user when the
+     *         file could not be read
+     */
+    pub fn load() -> (Self, Option<String>) {
+        let path = AppConfig::path();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => return (AppConfig::default(), None),
+        };
+        match serde_json::from_str(&content) {
+            Ok(config) => (config, None),
+            Err(e) => {
+                let kept = match storage::keep_aside(&path) {
+                    Ok(aside) => format!("it was kept in {}", aside.display()),
+                    Err(e) => format!("it could not be kept aside: {}", e),
+                };
+                let error = format!(
+                    "The configuration could not be read ({}), the default one is used and {}",
+                    e, kept
+                );
+                (AppConfig::default(), Some(error))
+            }
+        }
+    }
+
+    /**
+     * Write the configuration to its file, creating its directory if needed.
+     * The file is replaced atomically and the previous one is kept as backup.
+     */
+    pub fn save(&self) -> std::io::Result<()> {
+        storage::write_atomic(
+            &AppConfig::path(),
+            serde_json::to_string_pretty(self)?.as_bytes(),
+        )
+    }
+
+    /**
+     * The directory of the library and of the search indexes,
+     * by default in the data directory of the user (like ~/.local/share on Linux).
+     */
+    pub fn data_dir(&self) -> PathBuf {
+        match self.data_dir.trim() {
+            "" => match dirs::data_dir() {
+                Some(dir) => dir.join(APP_DIR_NAME),
+                None => PathBuf::from(AppConfig::FALLBACK_DATA_DIR),
+            },
+            dir => PathBuf::from(dir),
+        }
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            default_settings: EpubSettings::default(),
+            ranges: SettingsRanges::default(),
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            data_dir: String::new(),
+            window: WindowGeometry::default(),
+        }
+    }
+}
+
+/**
+ * The minimum and the maximum value of a setting.
+ */
+#[derive(Clone, Copy, Data, Lens, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SettingRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl SettingRange {
+    pub const fn new(min: f64, max: f64) -> Self {
+        SettingRange { min, max }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.min.is_finite() && self.max.is_finite() && self.min < self.max
+    }
+}
+
+/**
+ * The ranges of the sliders of the settings of the books.
+ */
+#[derive(Clone, Data, Lens, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SettingsRanges {
+    pub font_size: SettingRange,
+    pub margin: SettingRange,
+    pub paragraph_spacing: SettingRange,
+    pub line_height: SettingRange,
+    pub first_line_indent: SettingRange,
+}
+
+impl SettingsRanges {
+    pub fn is_valid(&self) -> bool {
+        [
+            self.font_size,
+            self.margin,
+            self.paragraph_spacing,
+            self.line_height,
+            self.first_line_indent,
+        ]
+        .iter()
+        .all(SettingRange::is_valid)
+    }
+
+    /**
+     * The ranges to use, with the default ones in place of the invalid ranges.
+     */
+    pub fn checked(&self) -> SettingsRanges {
+        let defaults = SettingsRanges::default();
+        let check = |range: SettingRange, default| if range.is_valid() { range } else { default };
+        SettingsRanges {
+            font_size: check(self.font_size, defaults.font_size),
+            margin: check(self.margin, defaults.margin),
+            paragraph_spacing: check(self.paragraph_spacing, defaults.paragraph_spacing),
+            line_height: check(self.line_height, defaults.line_height),
+            first_line_indent: check(self.first_line_indent, defaults.first_line_indent),
+        }
+    }
+}
+
+impl Default for SettingsRanges {
+    fn default() -> Self {
+        SettingsRanges {
+            font_size: SettingRange::new(
+                epub_settings::MIN_FONT_SIZE,
+                epub_settings::MAX_FONT_SIZE,
+            ),
+            margin: SettingRange::new(epub_settings::MIN_MARGIN, epub_settings::MAX_MARGIN),
+            paragraph_spacing: SettingRange::new(
+                epub_settings::MIN_PARAGRAPH_SPACING,
+                epub_settings::MAX_PARAGRAPH_SPACING,
+            ),
+            line_height: SettingRange::new(
+                epub_settings::MIN_LINE_HEIGHT,
+                epub_settings::MAX_LINE_HEIGHT,
+            ),
+            first_line_indent: SettingRange::new(0., epub_settings::MAX_FIRST_LINE_INDENT),
+        }
+    }
+}
+
+/**
+ * The size and the position of the main window when it was closed.
+ */
+#[derive(Clone, Data, Lens, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    pub width: f64,
+    pub height: f64,
+    // None to let the system place the window
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub maximized: bool,
+}
+
+impl WindowGeometry {
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    pub fn position(&self) -> Option<Point> {
+        Some(Point::new(self.x?, self.y?))
+    }
+
+    /**
+     * Read the geometry of a window.
+     * The size and the position of a maximized window are not kept,
+     * so it is restored to its previous geometry.
+     */
+    pub fn update(&mut self, window: &WindowHandle) {
+        self.maximized = window.get_window_state() == WindowState::Maximized;
+        if !self.maximized {
+            let size = window.get_size();
+            let position = window.get_position();
+            self.width = size.width;
+            self.height = size.height;
+            self.x = Some(position.x);
+            self.y = Some(position.y);
+        }
+    }
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        WindowGeometry {
+            width: 1000.,
+            height: 800.,
+            x: None,
+            y: None,
+            maximized: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults() {
+        // the settings missing in the file take their default value
+        let config: AppConfig =
+            serde_json::from_str(r#"{ "ranges": { "font_size": { "min": 10, "max": 40 } } }"#)
+                .unwrap();
+        assert_eq!(config.ranges.font_size, SettingRange::new(10., 40.));
+        assert_eq!(config.ranges.margin, SettingsRanges::default().margin);
+        assert_eq!(config.window, WindowGeometry::default());
+
+        let mut ranges = config.ranges.clone();
+        ranges.margin = SettingRange::new(50., 20.);
+        assert!(!ranges.is_valid());
+        assert_eq!(ranges.checked().margin, SettingsRanges::default().margin);
+        assert_eq!(ranges.checked().font_size, SettingRange::new(10., 40.));
+    }
+}
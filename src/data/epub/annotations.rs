@@ -0,0 +1,306 @@
+use std::path::Path;
+
+use druid::FileSpec;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Bookmark, Highlight};
+
+/**
+ * The formats the annotations of a book can be exported to.
+ * Only the JSON format keeps everything needed to import them again.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Markdown,
+        ExportFormat::Json,
+        ExportFormat::Csv,
+    ];
+
+    pub fn file_spec(&self) -> FileSpec {
+        match self {
+            ExportFormat::Markdown => FileSpec::new("Markdown (.md)", &["md"]),
+            ExportFormat::Json => FileSpec::new("JSON (.json)", &["json"]),
+            ExportFormat::Csv => FileSpec::new("CSV (.csv)", &["csv"]),
+        }
+    }
+
+    /**
+     * Get the format of a file from its extension.
+     *
+     * @return None if the extension is not one of the formats
+     */
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * A highlight with the text it covers, the text around it
+ * and the title of its chapter in the table of contents.
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportedHighlight {
+    #[serde(flatten)]
+    pub highlight: Highlight,
+    #[serde(default)]
+    pub chapter_title: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub context_before: String,
+    #[serde(default)]
+    pub context_after: String,
+}
+
+/**
+ * A bookmark with the title of its chapter in the table of contents.
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportedBookmark {
+    #[serde(flatten)]
+    pub bookmark: Bookmark,
+    #[serde(default)]
+    pub chapter_title: String,
+}
+
+/**
+ * The highlights, notes and bookmarks of a book, in reading order.
+ * The JSON serialization of this struct is the format used to move
+ * the annotations between machines.
+ */
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Annotations {
+    pub book: String,
+    // seconds since the UNIX epoch
+    pub exported: u64,
+    #[serde(default)]
+    pub highlights: Vec<ExportedHighlight>,
+    #[serde(default)]
+    pub bookmarks: Vec<ExportedBookmark>,
+}
+
+impl Annotations {
+    /**
+     * Write the annotations to a file in the given format.
+     *
+     * @param path: the file to write
+     * @param format: the format of the file
+     */
+    pub fn save(&self, path: &Path, format: ExportFormat) -> std::io::Result<()> {
+        let content = match format {
+            ExportFormat::Markdown => self.to_markdown(),
+            ExportFormat::Json => self.to_json()?,
+            ExportFormat::Csv => self.to_csv(),
+        };
+        std::fs::write(path, content)
+    }
+
+    /**
+     * Read the annotations from a file written in the JSON format.
+     */
+    pub fn load(path: &Path) -> std::io::Result<Annotations> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn to_json(&self) -> std::io::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /**
+     * Markdown document with the highlights grouped by chapter,
+     * each one quoted with its note, followed by the list of bookmarks.
+     */
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# {}\n", self.book);
+
+        let mut last_chapter = None;
+        for exported in &self.highlights {
+            let highlight = &exported.highlight;
+            if last_chapter != Some(highlight.chapter) {
+                last_chapter = Some(highlight.chapter);
+                markdown.push_str(&format!("\n## {}\n", exported.chapter_title));
+            }
+
+            let quote = format!(
+                "{}**{}**{}",
+                exported.context_before,
+                exported.text.trim(),
+                exported.context_after
+            );
+            markdown.push_str(&format!("\n> {}\n", single_line(&quote)));
+            if !highlight.note.trim().is_empty() {
+                markdown.push_str(&format!("\n{}\n", highlight.note.trim()));
+            }
+            markdown.push_str(&format!(
+                "\n*{:?} - chapter {}, paragraph {}, characters {}-{}*\n",
+                highlight.color,
+                highlight.chapter + 1,
+                highlight.richtext_number + 1,
+                highlight.start,
+                highlight.end
+            ));
+        }
+
+        if !self.bookmarks.is_empty() {
+            markdown.push_str("\n## Bookmarks\n\n");
+            for exported in &self.bookmarks {
+                let bookmark = &exported.bookmark;
+                markdown.push_str(&format!(
+                    "- **{}** (chapter {}, paragraph {}): {}\n",
+                    exported.chapter_title,
+                    bookmark.position.chapter() + 1,
+                    bookmark.position.richtext_number() + 1,
+                    single_line(&bookmark.snippet)
+                ));
+            }
+        }
+        markdown
+    }
+
+    /**
+     * CSV table with a row for each highlight and each bookmark.
+     * Bookmarks have no color, note and range: their text is the beginning of the text at their position.
+     */
+    pub fn to_csv(&self) -> String {
+        const HEADER: [&str; 13] = [
+            "type",
+            "chapter_title",
+            "chapter",
+            "paragraph",
+            "start",
+            "end",
+            "color",
+            "created",
+            "text",
+            "note",
+            "context_before",
+            "context_after",
+            "label",
+        ];
+
+        let mut rows = vec![HEADER.iter().map(|s| s.to_string()).collect::<Vec<_>>()];
+        for exported in &self.highlights {
+            let highlight = &exported.highlight;
+            rows.push(vec![
+                "highlight".to_string(),
+                exported.chapter_title.clone(),
+                highlight.chapter.to_string(),
+                highlight.richtext_number.to_string(),
+                highlight.start.to_string(),
+                highlight.end.to_string(),
+                format!("{:?}", highlight.color),
+                highlight.created.to_string(),
+                exported.text.clone(),
+                highlight.note.clone(),
+                exported.context_before.clone(),
+                exported.context_after.clone(),
+                String::new(),
+            ]);
+        }
+        for exported in &self.bookmarks {
+            let bookmark = &exported.bookmark;
+            rows.push(vec![
+                "bookmark".to_string(),
+                exported.chapter_title.clone(),
+                bookmark.position.chapter().to_string(),
+                bookmark.position.richtext_number().to_string(),
+                bookmark.position.offset().to_string(),
+                String::new(),
+                String::new(),
+                bookmark.created.to_string(),
+                bookmark.snippet.clone(),
+                String::new(),
+                String::new(),
+                String::new(),
+                bookmark.label.clone(),
+            ]);
+        }
+
+        rows.iter()
+            .map(|row| {
+                let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+                fields.join(",") + "\r\n"
+            })
+            .collect()
+    }
+}
+
+/**
+ * Quote a field of a CSV file if it contains a separator, a quote or a line break.
+ */
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{HighlightColor, PagePosition};
+
+    #[test]
+    fn test_annotations_formats() {
+        let mut highlight = Highlight::new(2, 5, 10..20, HighlightColor::Green);
+        highlight.note = "first line\n\"quoted\", second".to_string();
+        let annotations = Annotations {
+            book: "Book".to_string(),
+            exported: 0,
+            highlights: vec![ExportedHighlight {
+                highlight,
+                chapter_title: "Chapter, three".to_string(),
+                text: "highlighted".to_string(),
+                context_before: "before ".to_string(),
+                context_after: " after".to_string(),
+            }],
+            bookmarks: vec![ExportedBookmark {
+                bookmark: Bookmark::new(
+                    PagePosition::with_offset(1, 2, 3),
+                    "Chapter two".to_string(),
+                    "text".to_string(),
+                ),
+                chapter_title: "Chapter two".to_string(),
+            }],
+        };
+
+        let imported: Annotations = serde_json::from_str(&annotations.to_json().unwrap()).unwrap();
+        assert_eq!(imported.highlights[0].highlight.range(), 10..20);
+        assert_eq!(
+            imported.highlights[0].highlight.note,
+            annotations.highlights[0].highlight.note
+        );
+        assert_eq!(
+            imported.bookmarks[0].bookmark.position,
+            PagePosition::with_offset(1, 2, 3)
+        );
+
+        let csv = annotations.to_csv();
+        assert!(csv.contains("\"Chapter, three\""));
+        assert!(csv.contains("\"first line\n\"\"quoted\"\", second\""));
+        assert_eq!(csv.matches("\r\n").count(), 3);
+
+        let markdown = annotations.to_markdown();
+        assert!(markdown.contains("> before **highlighted** after"));
+        assert!(markdown.contains("- **Chapter two** (chapter 2, paragraph 3): text"));
+    }
+}
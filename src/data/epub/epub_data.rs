@@ -1,18 +1,42 @@
 use std::{
+    collections::{BTreeMap, HashMap},
     fs::File,
     io::BufReader,
+    ops::Range,
+    path::{Component, Path, PathBuf},
     sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use druid::{im::Vector, piet::TextStorage, ArcStr, Data, Lens};
+use druid::{im::Vector, text::RichText, ArcStr, Data, ImageBuf, Lens};
 use epub::doc::{EpubDoc, NavPoint};
+use hyphenation::Standard;
 
 use crate::{
-    data::{IndexedText, PagePosition},
-    dom::{generate_renderable_tree, Renderable},
+    css::Stylesheet,
+    data::{Bookmark, Highlight, IndexedText, PagePosition},
+    dom::{
+        chapter_anchors, navigation_documents, page_list, parse_chapter, style_elements,
+        stylesheet_links, unique_identifier, Chapter, Renderable, TextOptions,
+    },
+    hyphenate,
+    search::{char_range, BookIndex, IndexSettings, SearchHit, TextMatcher},
 };
 
-use super::{edit_data::EditData, ocr_data::OcrData, settings::EpubSettings, sidebar::SidebarData};
+use super::{
+    annotations::{Annotations, ExportedBookmark, ExportedHighlight},
+    edit_data::EditData,
+    fonts::{
+        embedded_font_families, is_font, obfuscated_resources, system_font_families, EmbeddedFont,
+    },
+    ocr_data::OcrData,
+    settings::EpubSettings,
+    sidebar::{SearchOptions, SidebarData},
+    theme::Theme,
+};
+
+// number of results shown in a page of the Search panel
+const SEARCH_PAGE_SIZE: usize = 50;
 
 /**
  * EpubData is the main struct that contains all the data of the book.
@@ -28,11 +52,44 @@ pub struct EpubData {
     ocr_data: OcrData,
     pub edit_data: EditData,
 
+    // positions from which an internal link was followed
+    pub link_history: Vector<PagePosition>,
+
+    // the fonts embedded in the book, then the ones of the system
+    pub font_families: Vector<String>,
+
+    // places marked by the user, in reading order
+    pub bookmarks: Vector<Bookmark>,
+    pub highlights: Vector<Highlight>,
+
     #[data(ignore)]
     doc: Option<Arc<Mutex<EpubDoc<BufReader<File>>>>>,
 
     #[data(ignore)]
     cached_chapters: Option<Vec<Vec<String>>>,
+
+    #[data(ignore)]
+    cached_images: Arc<Mutex<HashMap<PathBuf, ImageBuf>>>,
+
+    #[data(ignore)]
+    cached_stylesheets: Arc<Mutex<HashMap<PathBuf, Stylesheet>>>,
+
+    // full-text index of the book once it is built, with the settings it was built with
+    #[data(ignore)]
+    search_index: Option<(Arc<BookIndex>, IndexSettings)>,
+
+    // the matches of the search in each chapter, as richtext and range of characters,
+    // including the ones of the results in the other pages of results
+    #[data(ignore)]
+    search_highlights: Arc<BTreeMap<usize, Vec<(usize, Range<usize>)>>>,
+
+    // the font files embedded in the book, loaded by the page
+    #[data(ignore)]
+    embedded_fonts: Arc<Vec<EmbeddedFont>>,
+
+    // the hyphenation patterns of the language of the book, if known
+    #[data(ignore)]
+    hyphenation: Option<Arc<Standard>>,
 }
 
 impl EpubData {
@@ -83,7 +140,7 @@ impl EpubData {
         });
     }
 
-    pub fn new(doc: EpubDoc<std::io::BufReader<File>>) -> Self {
+    pub fn new(mut doc: EpubDoc<std::io::BufReader<File>>) -> Self {
         let epub = doc.get_epub_path();
         let mut other_doc = EpubDoc::new(epub).unwrap();
 
@@ -93,16 +150,737 @@ impl EpubData {
 
         let edit_data = EditData::default();
 
-        EpubData {
+        let embedded_fonts = Self::font_files(&mut doc);
+        let font_families = embedded_font_families(&embedded_fonts)
+            .into_iter()
+            .chain(system_font_families().iter().cloned())
+            .collect();
+        let hyphenation = doc
+            .mdata("language")
+            .and_then(|language| hyphenate::dictionary(&language))
+            .map(Arc::new);
+
+        let mut epub_data = EpubData {
             sidebar_data: SidebarData::new(toc),
             page_position: PagePosition::ZERO,
             epub_settings: EpubSettings::default(),
             ocr_data: OcrData::default(),
             edit_data,
+            link_history: Vector::new(),
+            font_families,
+            bookmarks: Vector::new(),
+            highlights: Vector::new(),
 
             doc: Some(Arc::new(Mutex::new(doc))),
             cached_chapters: None,
+            cached_images: Arc::new(Mutex::new(HashMap::new())),
+            cached_stylesheets: Arc::new(Mutex::new(HashMap::new())),
+            search_index: None,
+            search_highlights: Arc::new(BTreeMap::new()),
+            embedded_fonts: Arc::new(embedded_fonts),
+            hyphenation,
+        };
+        epub_data.sidebar_data.page_list = epub_data.page_list_parser();
+        epub_data
+    }
+
+    /**
+     * Read the font files listed in the manifest of the book,
+     * de-obfuscating the ones listed by META-INF/encryption.xml.
+     * Fonts which cannot be de-obfuscated are left out.
+     *
+     * @return the font files, in no particular order
+     */
+    fn font_files(doc: &mut EpubDoc<BufReader<File>>) -> Vec<EmbeddedFont> {
+        let paths: Vec<PathBuf> = doc
+            .resources
+            .values()
+            .filter(|(_, media_type)| is_font(media_type))
+            .map(|(path, _)| path.clone())
+            .collect();
+        if paths.is_empty() {
+            return Vec::new();
+        }
+
+        let obfuscated: HashMap<PathBuf, _> = doc
+            .get_resource_by_path("META-INF/encryption.xml")
+            .map(|bytes| obfuscated_resources(&String::from_utf8_lossy(&bytes)))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(uri, obfuscation)| (Self::resolve_href(Path::new(""), &uri), obfuscation))
+            .collect();
+        let identifiers = if obfuscated.is_empty() {
+            Vec::new()
+        } else {
+            Self::identifiers(doc)
+        };
+
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let mut data = doc.get_resource_by_path(&path).ok()?;
+                if let Some(obfuscation) = obfuscated.get(&path) {
+                    if !obfuscation.deobfuscate(&mut data, &identifiers) {
+                        return None;
+                    }
+                }
+                Some(EmbeddedFont::new(path, data))
+            })
+            .collect()
+    }
+
+    /**
+     * The identifiers of the book, with the unique identifier first.
+     */
+    fn identifiers(doc: &mut EpubDoc<BufReader<File>>) -> Vec<String> {
+        let opf_path = doc.root_file.clone();
+        let mut identifiers: Vec<String> = doc
+            .get_resource_by_path(&opf_path)
+            .ok()
+            .and_then(|bytes| unique_identifier(&String::from_utf8_lossy(&bytes)))
+            .into_iter()
+            .collect();
+        if let Some(others) = doc.metadata.get("identifier") {
+            identifiers.extend(
+                others
+                    .iter()
+                    .map(|identifier| identifier.trim().to_string()),
+            );
+        }
+        identifiers
+    }
+
+    /**
+     * The font files embedded in the book, to be loaded by the text backend.
+     */
+    pub fn embedded_fonts(&self) -> Arc<Vec<EmbeddedFont>> {
+        self.embedded_fonts.clone()
+    }
+
+    /**
+     * The families of the embedded fonts, by the lowercase name the styles of the book use:
+     * the one declared by the @font-face rules of the stylesheets, or the one of the font.
+     *
+     * @param stylesheets: the stylesheets of a chapter, with the path their urls are relative to
+     */
+    fn embedded_font_names<'a>(
+        &self,
+        stylesheets: impl Iterator<Item = &'a (PathBuf, Stylesheet)>,
+    ) -> HashMap<String, String> {
+        let mut names: HashMap<String, String> = self
+            .embedded_fonts
+            .iter()
+            .filter_map(|font| font.family.clone())
+            .map(|family| (family.to_lowercase(), family))
+            .collect();
+        for (css_path, stylesheet) in stylesheets {
+            for font_face in stylesheet.font_faces() {
+                let family = font_face.sources.iter().find_map(|src| {
+                    let path = Self::resolve_href(css_path, src);
+                    self.embedded_fonts
+                        .iter()
+                        .find(|font| font.path == path)
+                        .and_then(|font| font.family.clone())
+                });
+                if let Some(family) = family {
+                    names.insert(font_face.family.clone(), family);
+                }
+            }
+        }
+        names
+    }
+
+    /**
+     * Parse the print pages of the book, listed by the page-list of the
+     * navigation document or, for older books, by the pageList of the NCX.
+     * Each page points to the richtext containing its page break.
+     *
+     * @return the print pages in reading order, empty if the book does not list them
+     */
+    fn page_list_parser(&self) -> Vector<IndexedText> {
+        let mut pages = Vector::new();
+        let mut doc = match &self.doc {
+            Some(doc) => doc.lock().unwrap(),
+            None => return pages,
+        };
+        let opf_path = doc.root_file.clone();
+        let opf = match doc.get_resource_by_path(&opf_path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(_) => return pages,
+        };
+
+        // anchors of the chapters containing the pages, parsed once
+        let mut anchors: HashMap<usize, HashMap<String, usize>> = HashMap::new();
+        for nav_href in navigation_documents(&opf) {
+            let nav_path = Self::resolve_href(&opf_path, &nav_href);
+            let nav = match doc.get_resource_by_path(&nav_path) {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Err(_) => continue,
+            };
+
+            for (label, href) in page_list(&nav) {
+                let path = Self::resolve_href(&nav_path, &href);
+                let fragment = href.split_once('#').map(|(_, f)| f).unwrap_or_default();
+                let chapter = match doc.spine.iter().position(|id| {
+                    doc.resources
+                        .get(id)
+                        .map(|(p, _)| p == &path)
+                        .unwrap_or(false)
+                }) {
+                    Some(chapter) => chapter,
+                    None => continue,
+                };
+
+                if !anchors.contains_key(&chapter) {
+                    let spine_id = doc.spine[chapter].clone();
+                    let content = doc
+                        .get_resource(&spine_id)
+                        .ok()
+                        .and_then(|bytes| String::from_utf8(bytes).ok())
+                        .unwrap_or_default();
+                    anchors.insert(chapter, chapter_anchors(&content));
+                }
+                let richtext_number = anchors[&chapter].get(fragment).copied().unwrap_or(0);
+
+                pages.push_back(IndexedText::new(
+                    ArcStr::from(label),
+                    Arc::new(PagePosition::new(chapter, richtext_number)),
+                ));
+            }
+            // the navigation document is preferred to the NCX
+            if !pages.is_empty() {
+                break;
+            }
+        }
+        pages
+    }
+
+    /**
+     * Get the print page containing the current position, if the book lists them.
+     *
+     * @return the label of the print page, like "17" or "xiv"
+     */
+    pub fn print_page(&self) -> Option<ArcStr> {
+        self.sidebar_data
+            .page_list
+            .iter()
+            .take_while(|page| page.value().as_ref() <= &self.page_position)
+            .last()
+            .map(|page| page.key().clone())
+    }
+
+    /**
+     * Get the number of the print page containing the current position.
+     *
+     * @return None if the book does not list its print pages
+     *         or the page is not numbered with digits
+     */
+    pub fn print_page_number(&self) -> Option<usize> {
+        self.print_page()?.trim().parse().ok()
+    }
+
+    /**
+     * Find the position of the print page typed in the sidebar.
+     *
+     * @return the position of the page, or None if the book does not list it
+     */
+    pub fn print_page_position(&self) -> Option<PagePosition> {
+        let label = self.sidebar_data.print_page_input.trim();
+        self.sidebar_data
+            .page_list
+            .iter()
+            .find(|page| page.key().eq_ignore_ascii_case(label))
+            .map(|page| page.value().as_ref().clone())
+    }
+
+    /**
+     * Resolve a reference found in a resource of the book (an image source,
+     * a link or a stylesheet) against the path of the resource containing it.
+     * The fragment of the reference, if any, is dropped.
+     *
+     * @param base: the path of the resource containing the reference
+     * @param href: the reference to resolve
+     *
+     * @return the path of the referenced resource inside the epub archive
+     */
+    pub(crate) fn resolve_href(base: &Path, href: &str) -> PathBuf {
+        let href = href.split('#').next().unwrap_or_default().replace("%20", " ");
+        let mut resolved = PathBuf::new();
+        if let Some(parent) = base.parent() {
+            resolved.push(parent);
+        }
+        for component in Path::new(&href).components() {
+            match component {
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                Component::Normal(part) => resolved.push(part),
+                Component::RootDir => resolved = PathBuf::new(),
+                _ => {}
+            }
+        }
+        resolved
+    }
+
+    /**
+     * Find the chapter targeted by a link found in the current chapter.
+     *
+     * @param href: the target of the link
+     *
+     * @return the index of the chapter in the spine, the parsed chapter
+     *         and the fragment of the link (empty if missing),
+     *         or None if the target is not part of the book
+     */
+    fn link_target(&self, href: &str) -> Option<(usize, Chapter, String)> {
+        let mut doc = self.doc.as_ref()?.lock().unwrap();
+        let current_path = doc.get_current_path().ok()?;
+
+        let (path, fragment) = match href.split_once('#') {
+            Some(("", fragment)) => (current_path, fragment),
+            Some((_, fragment)) => (Self::resolve_href(&current_path, href), fragment),
+            None => (Self::resolve_href(&current_path, href), ""),
+        };
+
+        let chapter = doc
+            .spine
+            .iter()
+            .position(|id| doc.resources.get(id).map(|(p, _)| p == &path).unwrap_or(false))?;
+        let spine_id = doc.spine[chapter].clone();
+
+        let content = String::from_utf8(doc.get_resource(&spine_id).ok()?).ok()?;
+        let parsed = self.render_chapter(&mut doc, &path, &content);
+
+        Some((chapter, parsed, fragment.to_string()))
+    }
+
+    /**
+     * Resolve a link found in the current chapter to a position in the book.
+     * The position points to the richtext containing the element
+     * with the id of the fragment, if any.
+     *
+     * @param href: the target of the link
+     *
+     * @return the position of the target, or None if it is not part of the book
+     */
+    pub fn resolve_link(&self, href: &str) -> Option<PagePosition> {
+        let (chapter, parsed, fragment) = self.link_target(href)?;
+        let richtext_number = parsed.anchors.get(&fragment).copied().unwrap_or(0);
+
+        Some(PagePosition::new(chapter, richtext_number))
+    }
+
+    /**
+     * Get the text of the note targeted by a link found in the current chapter.
+     * Notes are the asides, footnotes and endnotes of the book.
+     * When the link is a note reference, the paragraph it points to
+     * is used as note even if it is not marked as such.
+     *
+     * @param href: the target of the link
+     * @param is_noteref: true if the link is marked as a note reference
+     *
+     * @return the text of the note, or None if the link does not point to a note
+     */
+    pub fn get_note(&self, href: &str, is_noteref: bool) -> Option<RichText> {
+        let (_, parsed, fragment) = self.link_target(href)?;
+        if let Some(note) = parsed.notes.get(&fragment) {
+            return Some(note.clone());
+        }
+        if !is_noteref {
+            return None;
+        }
+
+        match parsed.renderables.get(*parsed.anchors.get(&fragment)?)? {
+            Renderable::Text(text, _) | Renderable::ListItem(_, text, _) => Some(text.clone()),
+            _ => None,
+        }
+    }
+
+    /**
+     * Save the current position before following a link,
+     * so the reader can come back to it.
+     */
+    pub fn push_link_history(&mut self) {
+        self.link_history.push_back(self.page_position.clone());
+    }
+
+    /**
+     * Get the position from which the last link was followed.
+     *
+     * @return the position, or None if no link was followed
+     */
+    pub fn pop_link_history(&mut self) -> Option<PagePosition> {
+        self.link_history.pop_back()
+    }
+
+    /**
+     * Load an image referenced in the current chapter.
+     * Decoded images are cached, so changing the settings does not decode them again.
+     *
+     * @param src: the source of the image, relative to the current chapter
+     *
+     * @return the decoded image, or None if it can not be found or decoded
+     */
+    pub fn get_image(&self, src: &str) -> Option<ImageBuf> {
+        let mut doc = self.doc.as_ref()?.lock().unwrap();
+        let path = Self::resolve_href(&doc.get_current_path().ok()?, src);
+
+        let mut cached_images = self.cached_images.lock().unwrap();
+        if let Some(image) = cached_images.get(&path) {
+            return Some(image.clone());
+        }
+
+        let bytes = doc.get_resource_by_path(&path).ok()?;
+        match ImageBuf::from_data(&bytes) {
+            Ok(image) => {
+                cached_images.insert(path, image.clone());
+                Some(image)
+            }
+            Err(e) => {
+                println!("Error decoding image {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /**
+     * Add a bookmark at the current position, unless there is already one.
+     * The bookmark is named after the chapter of the table of contents
+     * containing the position, and keeps the beginning of its text.
+     *
+     * @return true if the bookmark was added
+     */
+    pub fn add_bookmark(&mut self) -> bool {
+        const SNIPPET_LENGTH: usize = 80;
+
+        let position = PagePosition::with_offset(
+            self.page_position.chapter(),
+            self.page_position.richtext_number(),
+            self.page_position.offset(),
+        );
+        if self.bookmarks.iter().any(|b| b.position == position) {
+            return false;
+        }
+
+        let label = self.toc_label(position.chapter());
+        let snippet = self
+            .get_current_chap()
+            .get(position.richtext_number())
+            .map(|renderable| {
+                let text: String = renderable
+                    .plain_text()
+                    .chars()
+                    .skip(position.offset())
+                    .collect();
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                text.chars().take(SNIPPET_LENGTH).collect()
+            })
+            .unwrap_or_default();
+
+        let index = self
+            .bookmarks
+            .iter()
+            .position(|b| b.position > position)
+            .unwrap_or(self.bookmarks.len());
+        self.bookmarks
+            .insert(index, Bookmark::new(position, label, snippet));
+        true
+    }
+
+    /**
+     * Get the label of the table of contents for a chapter: the title of the last
+     * entry preceding it, or its number if the table of contents has none.
+     *
+     * @param chapter: the index of the chapter in the spine
+     */
+    pub fn toc_label(&self, chapter: usize) -> String {
+        self.sidebar_data
+            .table_of_contents
+            .iter()
+            .take_while(|entry| entry.value().chapter() <= chapter)
+            .last()
+            .map(|entry| entry.key().to_string())
+            .unwrap_or_else(|| format!("Chapter {}", chapter + 1))
+    }
+
+    /**
+     * Get the notes as entries of the sidebar, grouped by chapter.
+     * Each group starts with the label of the chapter, followed by its notes
+     * in reading order; an entry goes to the beginning of the annotated text.
+     */
+    pub fn note_entries(&self) -> Vector<IndexedText> {
+        let mut notes: Vec<&Highlight> = self
+            .highlights
+            .iter()
+            .filter(|h| !h.note.trim().is_empty())
+            .collect();
+        notes.sort_by_key(|h| (h.chapter, h.richtext_number, h.start));
+
+        let mut entries = Vector::new();
+        let mut last_chapter = None;
+        for note in notes {
+            if last_chapter != Some(note.chapter) {
+                last_chapter = Some(note.chapter);
+                entries.push_back(IndexedText::new(
+                    ArcStr::from(self.toc_label(note.chapter)),
+                    Arc::new(PagePosition::new(note.chapter, 0)),
+                ));
+            }
+            // notes are shown on a single line
+            let text = note.note.split_whitespace().collect::<Vec<_>>().join(" ");
+            entries.push_back(IndexedText::new(
+                ArcStr::from(format!("    {}", text)),
+                Arc::new(PagePosition::with_offset(
+                    note.chapter,
+                    note.richtext_number,
+                    note.start,
+                )),
+            ));
+        }
+        entries
+    }
+
+    /**
+     * Get the bookmarks as entries of the sidebar, showing their name and text.
+     */
+    pub fn bookmark_entries(&self) -> Vector<IndexedText> {
+        self.bookmarks
+            .iter()
+            .map(|bookmark| {
+                IndexedText::new(
+                    ArcStr::from(format!("{}: {}", bookmark.label, bookmark.snippet)),
+                    Arc::new(bookmark.position.clone()),
+                )
+            })
+            .collect()
+    }
+
+    /**
+     * Get the title of the book from its metadata, or the name of its file.
+     */
+    pub fn get_title(&self) -> String {
+        let title = self
+            .doc
+            .as_ref()
+            .and_then(|doc| doc.lock().unwrap().mdata("title"));
+        title.unwrap_or_else(|| {
+            Path::new(&self.get_epub_path())
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+    }
+
+    /**
+     * Collect the highlights, notes and bookmarks of the book to export them,
+     * in reading order. Each highlight keeps its text and the text around it.
+     */
+    pub fn annotations(&mut self) -> Annotations {
+        const CONTEXT_LENGTH: usize = 100;
+
+        let strings = self.get_only_strings();
+        let mut highlights: Vec<&Highlight> = self.highlights.iter().collect();
+        highlights.sort_by_key(|h| (h.chapter, h.richtext_number, h.start));
+
+        let highlights = highlights
+            .into_iter()
+            .map(|highlight| {
+                let text: Vec<char> = strings
+                    .get(highlight.chapter)
+                    .and_then(|chapter| chapter.get(highlight.richtext_number))
+                    .map(|text| text.chars().collect())
+                    .unwrap_or_default();
+                let end = highlight.end.min(text.len());
+                let start = highlight.start.min(end);
+                let context_start = start.saturating_sub(CONTEXT_LENGTH);
+                let context_end = (end + CONTEXT_LENGTH).min(text.len());
+                ExportedHighlight {
+                    highlight: highlight.clone(),
+                    chapter_title: self.toc_label(highlight.chapter),
+                    text: text[start..end].iter().collect(),
+                    context_before: text[context_start..start].iter().collect(),
+                    context_after: text[end..context_end].iter().collect(),
+                }
+            })
+            .collect();
+
+        let bookmarks = self
+            .bookmarks
+            .iter()
+            .map(|bookmark| ExportedBookmark {
+                bookmark: bookmark.clone(),
+                chapter_title: self.toc_label(bookmark.position.chapter()),
+            })
+            .collect();
+
+        Annotations {
+            book: self.get_title(),
+            exported: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+            highlights,
+            bookmarks,
+        }
+    }
+
+    /**
+     * Add the annotations exported from another machine to the book.
+     * The ones already in the book are skipped; a note is imported
+     * if the same highlight has no note here.
+     * Nothing is imported if the annotations are of another book, or if some of
+     * them are not in the text of this one, like for another edition of the book.
+     *
+     * @return whether the highlights or the bookmarks of the book changed,
+     *         or the reason why the annotations were not imported
+     */
+    pub fn import_annotations(&mut self, annotations: Annotations) -> Result<bool, String> {
+        let title = self.get_title();
+        if annotations.book != title {
+            return Err(format!(
+                "they are of \"{}\", not of \"{}\"",
+                annotations.book, title
+            ));
+        }
+        let mut chapter_lengths = HashMap::new();
+        let outside = annotations
+            .highlights
+            .iter()
+            .map(|h| (h.highlight.chapter, h.highlight.richtext_number))
+            .chain(annotations.bookmarks.iter().map(|b| {
+                (
+                    b.bookmark.position.chapter(),
+                    b.bookmark.position.richtext_number(),
+                )
+            }))
+            .filter(|(chapter, richtext_number)| {
+                !self.has_element(&mut chapter_lengths, *chapter, *richtext_number)
+            })
+            .count();
+        if outside > 0 {
+            return Err(format!(
+                "{} of them are not in the text of the book",
+                outside
+            ));
+        }
+
+        let mut changed = false;
+        for exported in annotations.highlights {
+            let highlight = exported.highlight;
+            if highlight.start >= highlight.end {
+                continue;
+            }
+            let existing = self.highlights.iter_mut().find(|h| {
+                h.chapter == highlight.chapter
+                    && h.richtext_number == highlight.richtext_number
+                    && h.range() == highlight.range()
+            });
+            match existing {
+                Some(existing) => {
+                    if existing.note.trim().is_empty() && !highlight.note.trim().is_empty() {
+                        existing.note = highlight.note;
+                        changed = true;
+                    }
+                }
+                None => {
+                    self.highlights.push_back(highlight);
+                    changed = true;
+                }
+            }
+        }
+
+        for exported in annotations.bookmarks {
+            let bookmark = exported.bookmark;
+            if self
+                .bookmarks
+                .iter()
+                .any(|b| b.position == bookmark.position)
+            {
+                continue;
+            }
+            let index = self
+                .bookmarks
+                .iter()
+                .position(|b| b.position > bookmark.position)
+                .unwrap_or(self.bookmarks.len());
+            self.bookmarks.insert(index, bookmark);
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    /**
+     * Whether a chapter of the book has the given element.
+     *
+     * @param chapter_lengths: the number of elements of the chapters already rendered
+     * @param chapter: the index of the chapter in the spine
+     * @param richtext_number: the index of the element in the chapter
+     */
+    fn has_element(
+        &self,
+        chapter_lengths: &mut HashMap<usize, usize>,
+        chapter: usize,
+        richtext_number: usize,
+    ) -> bool {
+        let length = *chapter_lengths.entry(chapter).or_insert_with(|| {
+            self.get_chapter(chapter)
+                .map(|(_, renderables)| renderables.len())
+                .unwrap_or(0)
+        });
+        richtext_number < length
+    }
+
+    /**
+     * Get an image of any chapter of the book, like the chapters being paginated.
+     * Images already decoded are taken from the cache, the others are not
+     * added to it, since most of them are not going to be shown.
+     *
+     * @param chapter_path: the path of the chapter containing the image
+     * @param src: the source of the image, relative to the chapter
+     *
+     * @return the decoded image, or None if it can not be found or decoded
+     */
+    pub fn get_chapter_image(&self, chapter_path: &Path, src: &str) -> Option<ImageBuf> {
+        let path = Self::resolve_href(chapter_path, src);
+        if let Some(image) = self.cached_images.lock().unwrap().get(&path) {
+            return Some(image.clone());
         }
+
+        let bytes = self
+            .doc
+            .as_ref()?
+            .lock()
+            .unwrap()
+            .get_resource_by_path(&path)
+            .ok()?;
+        ImageBuf::from_data(&bytes).ok()
+    }
+
+    /**
+     * Get the number of chapters in the spine of the book
+     */
+    pub fn chapter_count(&self) -> usize {
+        self.doc
+            .as_ref()
+            .map(|doc| doc.lock().unwrap().spine.len())
+            .unwrap_or(0)
+    }
+
+    /**
+     * Render any chapter of the book, without changing the current one.
+     *
+     * @param chapter: the index of the chapter in the spine
+     *
+     * @return the path of the chapter and its content as a vector of Renderable,
+     *         or None if the chapter can not be read
+     */
+    pub fn get_chapter(&self, chapter: usize) -> Option<(PathBuf, Vector<Renderable>)> {
+        let mut doc = self.doc.as_ref()?.lock().unwrap();
+        let spine_id = doc.spine.get(chapter)?.clone();
+        let path = doc.resources.get(&spine_id)?.0.clone();
+        let content = String::from_utf8(doc.get_resource(&spine_id).ok()?).ok()?;
+
+        let renderables = self.render_chapter(&mut doc, &path, &content).renderables;
+        Some((path, renderables))
     }
 
     /**
@@ -162,13 +940,126 @@ impl EpubData {
         }
 
         let mut doc = self.doc.as_ref().unwrap().lock().unwrap();
+        let path = doc.get_current_path().unwrap();
+        let content = doc.get_current_str().unwrap();
+
+        self.render_chapter(&mut doc, &path, &content).renderables
+    }
+
+    /**
+     * Render a text as the content of the current chapter,
+     * like the chapter being edited.
+     *
+     * @param text: the HTML of the chapter
+     *
+     * @return the chapter as a vector of Renderable
+     */
+    pub fn render_current_text(&self, text: &str) -> Vector<Renderable> {
+        let mut doc = match &self.doc {
+            Some(doc) => doc.lock().unwrap(),
+            None => return Vector::new(),
+        };
+        let path = doc.get_current_path().unwrap_or_default();
+
+        self.render_chapter(&mut doc, &path, text).renderables
+    }
+
+    /**
+     * Parse a chapter of the book, applying the stylesheets it links
+     * unless the publisher styles are disabled.
+     *
+     * @param doc: the epub document
+     * @param path: the path of the chapter, used to resolve the stylesheets
+     * @param content: the HTML of the chapter
+     */
+    fn render_chapter(
+        &self,
+        doc: &mut EpubDoc<BufReader<File>>,
+        path: &Path,
+        content: &str,
+    ) -> Chapter {
+        let mut options = TextOptions {
+            first_line_indent: self.epub_settings.first_line_indent,
+            hyphenation: self
+                .hyphenation
+                .clone()
+                .filter(|_| self.epub_settings.hyphenation),
+            font_families: HashMap::new(),
+            ignore_colors: self.epub_settings.theme != Theme::default(),
+        };
+        if !self.epub_settings.publisher_styles {
+            return parse_chapter(content, self.epub_settings.font_size, None, &options);
+        }
 
-        generate_renderable_tree(
-            doc.get_current_str().as_ref().unwrap(),
+        let mut stylesheets = Vec::new();
+        let mut cached_stylesheets = self.cached_stylesheets.lock().unwrap();
+        for href in stylesheet_links(content) {
+            let css_path = Self::resolve_href(path, &href);
+            if !cached_stylesheets.contains_key(&css_path) {
+                let css = doc
+                    .get_resource_by_path(&css_path)
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .unwrap_or_default();
+                cached_stylesheets.insert(css_path.clone(), Stylesheet::parse(&css));
+            }
+            stylesheets.push((css_path.clone(), cached_stylesheets[&css_path].clone()));
+        }
+        // the <style> elements are applied by the parser, only their fonts are needed here
+        let inline_styles = (
+            path.to_path_buf(),
+            Stylesheet::parse(&style_elements(content)),
+        );
+        options.font_families =
+            self.embedded_font_names(stylesheets.iter().chain(Some(&inline_styles)));
+
+        let mut stylesheet = Stylesheet::default();
+        for (_, other) in stylesheets {
+            stylesheet.extend(other);
+        }
+
+        parse_chapter(
+            content,
             self.epub_settings.font_size,
+            Some(&stylesheet),
+            &options,
         )
     }
 
+    /**
+     * Enable or disable the styles of the publisher.
+     * The rendered text of the book depends on them, so the cache is cleared.
+     */
+    pub fn set_publisher_styles(&mut self, publisher_styles: bool) {
+        if self.epub_settings.publisher_styles != publisher_styles {
+            self.epub_settings.publisher_styles = publisher_styles;
+            self.cached_chapters = None;
+        }
+    }
+
+    /**
+     * Set the first line indent of the paragraphs, None to keep the one of the book.
+     * The indent is kept in the layout of the rendered paragraphs, so the cache is
+     * cleared; their text, and the positions in it, do not change.
+     */
+    pub fn set_first_line_indent(&mut self, first_line_indent: Option<f64>) {
+        if self.epub_settings.first_line_indent != first_line_indent {
+            self.epub_settings.first_line_indent = first_line_indent;
+            self.cached_chapters = None;
+        }
+    }
+
+    /**
+     * Enable or disable the hyphenation of the words.
+     * The soft hyphens are part of the rendered text, so the cache is cleared.
+     */
+    pub fn set_hyphenation(&mut self, hyphenation: bool) {
+        if self.epub_settings.hyphenation != hyphenation {
+            self.epub_settings.hyphenation = hyphenation;
+            self.cached_chapters = None;
+        }
+    }
+
     /**
      * Get rendered text of the entire book
      * Useful for searching
@@ -185,18 +1076,20 @@ impl EpubData {
             let mut cached_chapters = Vec::new();
             let spine = doc.spine.clone();
             // calculate self.cached_chapters
-            spine.iter().for_each(|spine| {
+            for spine in spine.iter() {
                 let res = String::from_utf8(doc.get_resource(spine).unwrap()).unwrap();
-                let renderable = generate_renderable_tree(&res, self.epub_settings.font_size)
+                let path = doc.resources[spine].0.clone();
+                let renderable = self
+                    .render_chapter(&mut doc, &path, &res)
+                    .renderables
                     .iter()
-                    .filter_map(|r| match r {
-                        Renderable::Text(r) => Some(String::from(r.as_str().clone())),
-                        _ => None,
-                    })
+                    // images and rules are kept as empty strings, so the indexes
+                    // match the ones of the rendered chapter
+                    .map(|r| r.plain_text())
                     .collect::<Vec<String>>();
 
                 cached_chapters.push(renderable);
-            });
+            }
             self.cached_chapters = Some(cached_chapters);
         }
 
@@ -204,49 +1097,295 @@ impl EpubData {
     }
 
     /**
-     * Search the current chapter for the search input
-     * and set the results in the sidebar_data
+     * Use the full-text index of the book for the searches.
+     *
+     * @param index_dir: the directory of the index
+     * @param settings: the settings the indexed text was rendered with
      *
-     * @param search_input: the string to search
+     * @return false if the index can not be opened
+     */
+    pub fn set_search_index(&mut self, index_dir: &Path, settings: IndexSettings) -> bool {
+        self.search_index = BookIndex::open(index_dir).map(|index| (Arc::new(index), settings));
+        self.search_index.is_some()
+    }
+
+    /**
+     * Search the book for the search input and set the first page
+     * of results in the sidebar_data.
+     * Once the index of the book is built the results are ranked and
+     * the input can contain phrases; until then, if an option of the
+     * search is enabled, or if the index was built with other settings
+     * than the ones of the book, the text is scanned.
      */
     pub fn search_string_in_book(&mut self) {
-        const MAX_SEARCH_RESULTS: usize = 100;
-        const BEFORE_MATCH: usize = 13;
+        self.sidebar_data.search_page = 0;
+        self.show_search_page();
+    }
+
+    /**
+     * Set the page of results selected in the sidebar_data.
+     * The results of the page are grouped by chapter, each group starting
+     * with the label of the chapter and its number of results.
+     */
+    fn show_search_page(&mut self) {
+        self.sidebar_data.search_results = Vector::new();
+        self.sidebar_data.search_total = 0;
+        self.sidebar_data.search_selected = None;
+        self.search_highlights = Arc::new(BTreeMap::new());
+        let query = self.sidebar_data.search_input.clone();
+        if query.trim().is_empty() {
+            return;
+        }
+        let offset = self.sidebar_data.search_page * SEARCH_PAGE_SIZE;
+
+        // the results of an index built with other settings point to other offsets
+        let settings = IndexSettings::new(&self.epub_settings);
+        let index = self
+            .search_index
+            .clone()
+            .filter(|(_, index_settings)| *index_settings == settings)
+            .map(|(index, _)| index)
+            .filter(|_| self.sidebar_data.search_options == SearchOptions::default());
+        // the input may not be a valid query, like a phrase without the closing quote
+        let indexed = index.and_then(|index| {
+            let (hits, total) = index.search(&query, offset, SEARCH_PAGE_SIZE).ok()?;
+            let all_hits = index.all_hits(&query).ok()?;
+            Some((hits, total, all_hits))
+        });
+        let (mut hits, total, counts) = match indexed {
+            Some((hits, total, all_hits)) => {
+                let mut counts = BTreeMap::new();
+                let mut highlights = BTreeMap::new();
+                for hit in all_hits {
+                    *counts.entry(hit.chapter).or_insert(0) += 1;
+                    for range in hit.matches() {
+                        highlights
+                            .entry(hit.chapter)
+                            .or_insert_with(Vec::new)
+                            .push((hit.richtext_number, char_range(&hit.text, range)));
+                    }
+                }
+                self.search_highlights = Arc::new(highlights);
+                (hits, total, counts)
+            }
+            None => self.scan_book_for_search_input(offset, SEARCH_PAGE_SIZE),
+        };
+
+        hits.sort_by_key(|hit| (hit.chapter, hit.richtext_number, hit.first_match().start));
         let mut results = Vector::new();
+        let mut last_chapter = None;
+        for hit in &hits {
+            if last_chapter != Some(hit.chapter) {
+                last_chapter = Some(hit.chapter);
+                let label = format!(
+                    "{} ({})",
+                    self.toc_label(hit.chapter),
+                    counts.get(&hit.chapter).unwrap_or(&0)
+                );
+                let length = label.len();
+                results.push_back(
+                    IndexedText::new(
+                        ArcStr::from(label),
+                        Arc::new(PagePosition::new(hit.chapter, 0)),
+                    )
+                    .with_emphasis(0..length),
+                );
+            }
+            results.push_back(Self::search_entry(hit));
+        }
+        self.sidebar_data.search_results = results;
+        self.sidebar_data.search_total = total;
+    }
+
+    /**
+     * Get the entry of the sidebar of a result,
+     * pointing to its first match (or to its first sequence of matching words).
+     */
+    fn search_entry(hit: &SearchHit) -> IndexedText {
+        const CONTEXT_LENGTH: usize = 20;
+        const INDENT: &str = "    ";
+
+        let range = hit.first_match();
+        let (snippet, emphasis) = hit.snippet(CONTEXT_LENGTH);
+        IndexedText::new(
+            ArcStr::from(format!("{}{}", INDENT, snippet)),
+            Arc::new(PagePosition::with_range(
+                hit.chapter,
+                hit.richtext_number,
+                char_range(&hit.text, range),
+            )),
+        )
+        .with_emphasis(emphasis.start + INDENT.len()..emphasis.end + INDENT.len())
+    }
+
+    /**
+     * Show the next page of results of the search.
+     */
+    pub fn next_search_page(&mut self) {
+        if (self.sidebar_data.search_page + 1) * SEARCH_PAGE_SIZE < self.sidebar_data.search_total {
+            self.sidebar_data.search_page += 1;
+            self.show_search_page();
+        }
+    }
+
+    /**
+     * Show the previous page of results of the search.
+     */
+    pub fn previous_search_page(&mut self) {
+        if self.sidebar_data.search_page > 0 {
+            self.sidebar_data.search_page -= 1;
+            self.show_search_page();
+        }
+    }
+
+    /**
+     * Select the result of the search following (or preceding) the selected one.
+     * After the last result of the page the next page of results is shown,
+     * and after the last page the first one.
+     *
+     * @param forward: true for the next result, false for the previous one
+     *
+     * @return the position of the result selected, None if the search has no results
+     */
+    pub fn select_adjacent_search_result(&mut self, forward: bool) -> Option<PagePosition> {
+        let total = self.sidebar_data.search_total;
+        if total == 0 {
+            return None;
+        }
+        let mut selected = self.adjacent_search_result(forward);
+        if selected.is_none() {
+            let pages = (total + SEARCH_PAGE_SIZE - 1) / SEARCH_PAGE_SIZE;
+            let page = self.sidebar_data.search_page;
+            self.sidebar_data.search_page = if forward {
+                (page + 1) % pages
+            } else {
+                (page + pages - 1) % pages
+            };
+            self.show_search_page();
+            selected = self.adjacent_search_result(forward);
+        }
+        self.sidebar_data.search_selected = selected;
+        selected.map(|i| self.sidebar_data.search_results[i].value().as_ref().clone())
+    }
+
+    /**
+     * Get the entry of the result following (or preceding) the selected one
+     * in the page of results, or the first (or last) one if none is selected.
+     */
+    fn adjacent_search_result(&self, forward: bool) -> Option<usize> {
+        // the labels of the chapters are not results
+        let mut results = self
+            .sidebar_data
+            .search_results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| result.value().range().is_some())
+            .map(|(i, _)| i);
+        match (self.sidebar_data.search_selected, forward) {
+            (Some(selected), true) => results.find(|i| *i > selected),
+            (Some(selected), false) => results.filter(|i| *i < selected).last(),
+            (None, true) => results.next(),
+            (None, false) => results.last(),
+        }
+    }
+
+    /**
+     * Select the result of the search at a position, like the one clicked in the sidebar.
+     */
+    pub fn select_search_result(&mut self, position: &PagePosition) {
+        self.sidebar_data.search_selected =
+            self.sidebar_data.search_results.iter().position(|result| {
+                result.value().range().is_some() && result.value().as_ref() == position
+            });
+    }
+
+    /**
+     * Get all the matches of the search in a chapter, in any page of results.
+     *
+     * @return the richtext and the range of characters of each match
+     */
+    pub fn search_ranges(&self, chapter: usize) -> &[(usize, Range<usize>)] {
+        self.search_highlights
+            .get(&chapter)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /**
+     * Get the description of the page of results shown, like "51-100 of 230".
+     */
+    pub fn search_page_label(&self) -> String {
+        let total = self.sidebar_data.search_total;
+        if total == 0 {
+            return String::new();
+        }
+        // the labels of the chapters are not results
+        let shown = self
+            .sidebar_data
+            .search_results
+            .iter()
+            .filter(|result| result.value().range().is_some())
+            .count();
+        let first = self.sidebar_data.search_page * SEARCH_PAGE_SIZE;
+        let last = (first + shown).min(total);
+        format!("{}-{} of {}", first + 1, last, total)
+    }
 
-        if !self.sidebar_data.search_input.is_empty() {
-            let search_lenght = self.sidebar_data.search_input.len();
+    /**
+     * Scan the text of the book for the search input, following the
+     * options of the search. Each occurrence is a result, and all of them
+     * are highlighted in the pages.
+     * An invalid regular expression finds nothing.
+     *
+     * @param offset: the number of results to skip
+     * @param limit: the maximum number of results
+     *
+     * @return the results, the total number of results and the number of results of each chapter
+     */
+    fn scan_book_for_search_input(
+        &mut self,
+        offset: usize,
+        limit: usize,
+    ) -> (Vec<SearchHit>, usize, BTreeMap<usize, usize>) {
+        let mut hits = Vec::new();
+        let mut total = 0;
+        let mut counts = BTreeMap::new();
+        let mut highlights = BTreeMap::new();
 
-            'outer: for (i, chapter) in self.get_only_strings().iter().enumerate() {
+        let matcher = TextMatcher::new(
+            &self.sidebar_data.search_input,
+            &self.sidebar_data.search_options,
+        )
+        .ok()
+        .filter(|_| !self.sidebar_data.search_input.is_empty());
+        if let Some(matcher) = matcher {
+            for (i, chapter) in self.get_only_strings().iter().enumerate() {
                 for (j, richtext) in chapter.iter().enumerate() {
-                    let matches: Vec<usize> = richtext.to_lowercase()
-                        .match_indices(&self.sidebar_data.search_input.to_lowercase())
-                        .map(|(i, _)| i)
-                        .collect();
-                    for occ_match in matches {
-                        let range_position =
-                            PagePosition::with_range(i, j, occ_match..occ_match + search_lenght);
-
-                        let start = if occ_match > BEFORE_MATCH { occ_match - BEFORE_MATCH } 
-                                            else { 0 };
-                        let end =
-                            if occ_match + search_lenght + BEFORE_MATCH < richtext.as_str().len() { occ_match + search_lenght + BEFORE_MATCH } 
-                            else { richtext.as_str().len()};
-                        
-                            // find end of word
-                        let text = ArcStr::from(utf8_slice::slice(&richtext.as_str(), start, end));
-
-                        let value = Arc::new(range_position);
-                        let search_result = IndexedText::new(ArcStr::from(text.to_string()), value);
-                        results.push_back(search_result);
-                        if results.len() > MAX_SEARCH_RESULTS {
-                            break 'outer;
+                    let mut ranges = matcher.find_iter(richtext);
+                    // a regular expression can match an empty text
+                    ranges.retain(|range| !range.is_empty());
+                    for range in ranges {
+                        highlights
+                            .entry(i)
+                            .or_insert_with(Vec::new)
+                            .push((j, char_range(richtext, range.clone())));
+                        if total >= offset && total < offset + limit {
+                            hits.push(SearchHit {
+                                chapter: i,
+                                richtext_number: j,
+                                score: 0.,
+                                text: richtext.clone(),
+                                ranges: vec![range],
+                            });
                         }
+                        total += 1;
+                        *counts.entry(i).or_insert(0) += 1;
                     }
                 }
             }
         }
-        self.sidebar_data.search_results = results
+        self.search_highlights = Arc::new(highlights);
+        (hits, total, counts)
     }
 
     /**
@@ -279,9 +1418,11 @@ impl EpubData {
      * Set the position in the current chapter
      *
      * @param position_in_page: the position in the current chapter
+     * @param offset: the number of characters of the richtext preceding the position
      */
-    pub fn set_position_in_page(&mut self, position_in_page: usize) {
+    pub fn set_position_in_page(&mut self, position_in_page: usize, offset: usize) {
         self.page_position.set_richtext_number(position_in_page);
+        self.page_position.set_offset(offset);
     }
 
     /**
@@ -330,3 +1471,107 @@ impl EpubData {
         return true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_result(chapter: usize, richtext_number: usize) -> IndexedText {
+        IndexedText::new(
+            ArcStr::from("result"),
+            Arc::new(PagePosition::with_range(chapter, richtext_number, 0..4)),
+        )
+    }
+
+    fn chapter_label(chapter: usize) -> IndexedText {
+        IndexedText::new(
+            ArcStr::from("chapter"),
+            Arc::new(PagePosition::new(chapter, 0)),
+        )
+    }
+
+    #[test]
+    fn test_adjacent_search_result_skips_chapter_labels() {
+        let mut data = EpubData::default();
+        data.sidebar_data.search_results = Vector::from(vec![
+            chapter_label(0),
+            search_result(0, 3),
+            chapter_label(1),
+            search_result(1, 5),
+        ]);
+        data.sidebar_data.search_total = 2;
+
+        let position = data.select_adjacent_search_result(true).unwrap();
+        assert_eq!(position.richtext_number(), 3);
+        assert_eq!(data.sidebar_data.search_selected, Some(1));
+        let position = data.select_adjacent_search_result(true).unwrap();
+        assert_eq!(position.richtext_number(), 5);
+        assert_eq!(data.sidebar_data.search_selected, Some(3));
+        data.select_adjacent_search_result(false);
+        assert_eq!(data.sidebar_data.search_selected, Some(1));
+    }
+
+    #[test]
+    fn test_adjacent_search_result_wraps_across_pages() {
+        let dir = std::env::temp_dir().join(format!("search_pages_test_{}", std::process::id()));
+        // two chapters of results, shown in two pages
+        let results = SEARCH_PAGE_SIZE / 2 + 5;
+        let strings = vec![vec!["a word".to_string(); results]; 2];
+        BookIndex::build(&dir, &strings, None).unwrap();
+
+        let mut data = EpubData::default();
+        let settings = IndexSettings::new(&data.epub_settings);
+        assert!(data.set_search_index(&dir, settings));
+        data.sidebar_data.search_input = "word".to_string();
+        data.search_string_in_book();
+        assert_eq!(data.sidebar_data.search_total, 2 * results);
+        let last_entry = |data: &EpubData| data.sidebar_data.search_results.len() - 1;
+
+        // after the last result of the first page, the first one of the second page
+        data.sidebar_data.search_selected = Some(last_entry(&data));
+        assert!(data.select_adjacent_search_result(true).is_some());
+        assert_eq!(data.sidebar_data.search_page, 1);
+        assert_eq!(data.sidebar_data.search_selected, Some(1));
+
+        // before the first result of the second page, the last one of the first page
+        assert!(data.select_adjacent_search_result(false).is_some());
+        assert_eq!(data.sidebar_data.search_page, 0);
+        assert_eq!(data.sidebar_data.search_selected, Some(last_entry(&data)));
+
+        // before the first result, the last one of the last page
+        data.sidebar_data.search_selected = Some(1);
+        assert!(data.select_adjacent_search_result(false).is_some());
+        assert_eq!(data.sidebar_data.search_page, 1);
+        assert_eq!(data.sidebar_data.search_selected, Some(last_entry(&data)));
+
+        // after the last result, the first one
+        assert!(data.select_adjacent_search_result(true).is_some());
+        assert_eq!(data.sidebar_data.search_page, 0);
+        assert_eq!(data.sidebar_data.search_selected, Some(1));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_search_ranges() {
+        let dir = std::env::temp_dir().join(format!("search_ranges_test_{}", std::process::id()));
+        let strings = vec![
+            vec!["The old man and the old sea.".to_string()],
+            vec!["An old boat.".to_string()],
+        ];
+        BookIndex::build(&dir, &strings, None).unwrap();
+
+        let mut data = EpubData::default();
+        let settings = IndexSettings::new(&data.epub_settings);
+        assert!(data.set_search_index(&dir, settings));
+        data.sidebar_data.search_input = "old".to_string();
+        data.search_string_in_book();
+
+        // both the matches of the paragraph are highlighted, not only the one of its result
+        assert_eq!(data.search_ranges(0), &[(0, 4..7), (0, 20..23)]);
+        assert_eq!(data.search_ranges(1), &[(0, 3..6)]);
+        assert!(data.search_ranges(2).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+use std::path::PathBuf;
+
+use lazy_static::lazy_static;
+use sha1::{Digest, Sha1};
+
+use crate::html::{Token, Tokenizer};
+
+lazy_static! {
+    // the fonts of the system are enumerated once, since it takes a while
+    static ref SYSTEM_FONT_FAMILIES: Vec<String> = {
+        let mut database = fontdb::Database::new();
+        database.load_system_fonts();
+        font_families(&database)
+    };
+}
+
+// media types used in the manifests for the font files
+const FONT_MEDIA_TYPES: [&str; 3] = ["font", "opentype", "truetype"];
+
+// the algorithms of encryption.xml used to obfuscate the fonts
+const IDPF_ALGORITHM: &str = "http://www.idpf.org/2008/embedding";
+const ADOBE_ALGORITHM: &str = "http://ns.adobe.com/pdf/enc#RC";
+
+// the first bytes of the font files: TrueType, OpenType, collections and WOFF
+const FONT_SIGNATURES: [&[u8]; 6] = [
+    b"\x00\x01\x00\x00",
+    b"OTTO",
+    b"true",
+    b"ttcf",
+    b"wOFF",
+    b"wOF2",
+];
+
+/**
+ * The algorithms used by the books to obfuscate their fonts, so that
+ * they cannot be extracted from the archive and used elsewhere.
+ * The start of the file is xored with a key derived from an identifier of the book.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Obfuscation {
+    // the SHA-1 of the unique identifier, on the first 1040 bytes
+    Idpf,
+    // the bytes of the UUID of the book, on the first 1024 bytes
+    Adobe,
+}
+
+impl Obfuscation {
+    fn from_algorithm(algorithm: &str) -> Option<Obfuscation> {
+        match algorithm {
+            IDPF_ALGORITHM => Some(Obfuscation::Idpf),
+            ADOBE_ALGORITHM => Some(Obfuscation::Adobe),
+            _ => None,
+        }
+    }
+
+    /**
+     * The key used for a book, and the number of bytes obfuscated.
+     *
+     * @return None if the identifier cannot be used by the algorithm
+     */
+    fn key(&self, identifier: &str) -> Option<(Vec<u8>, usize)> {
+        match self {
+            Obfuscation::Idpf => {
+                let identifier: String = identifier
+                    .chars()
+                    .filter(|c| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+                    .collect();
+                Some((Sha1::digest(identifier.as_bytes()).to_vec(), 1040))
+            }
+            Obfuscation::Adobe => {
+                let hex: String = identifier
+                    .trim()
+                    .trim_start_matches("urn:uuid:")
+                    .chars()
+                    .filter(|c| *c != '-')
+                    .collect();
+                if hex.len() != 32 {
+                    return None;
+                }
+                let key = (0..32)
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                    .collect::<Option<Vec<u8>>>()?;
+                Some((key, 1024))
+            }
+        }
+    }
+
+    /**
+     * Obfuscate or de-obfuscate a font, the operation is the same.
+     */
+    fn apply(&self, font: &mut [u8], identifier: &str) -> bool {
+        match self.key(identifier) {
+            Some((key, length)) => {
+                for (byte, k) in font.iter_mut().take(length).zip(key.iter().cycle()) {
+                    *byte ^= k;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /**
+     * De-obfuscate a font with the first identifier of the book that gives a valid font.
+     * The unique identifier is the one of the specification, but some books
+     * obfuscate with another of their identifiers.
+     *
+     * @return false if the font cannot be recovered
+     */
+    pub fn deobfuscate(&self, font: &mut [u8], identifiers: &[String]) -> bool {
+        for identifier in identifiers {
+            if self.apply(font, identifier) {
+                if has_font_signature(font) {
+                    return true;
+                }
+                // xoring again gives back the obfuscated font
+                self.apply(font, identifier);
+            }
+        }
+        false
+    }
+}
+
+/**
+ * A font file embedded in a book.
+ */
+#[derive(Clone, Debug)]
+pub struct EmbeddedFont {
+    // the path of the file inside the epub archive
+    pub path: PathBuf,
+    pub data: Vec<u8>,
+    // the family read from the file, None if it is not a font
+    pub family: Option<String>,
+}
+
+impl EmbeddedFont {
+    pub fn new(path: PathBuf, data: Vec<u8>) -> Self {
+        let mut database = fontdb::Database::new();
+        database.load_font_data(data.clone());
+        let family = database.faces().first().map(|face| face.family.clone());
+        EmbeddedFont { path, data, family }
+    }
+}
+
+/**
+ * The families of the fonts installed in the system, in alphabetical order.
+ */
+pub fn system_font_families() -> &'static [String] {
+    &SYSTEM_FONT_FAMILIES
+}
+
+/**
+ * The families of the fonts embedded in a book, in alphabetical order.
+ * Files which cannot be read as fonts are ignored.
+ */
+pub fn embedded_font_families(fonts: &[EmbeddedFont]) -> Vec<String> {
+    let mut families: Vec<String> = fonts
+        .iter()
+        .filter_map(|font| font.family.clone())
+        .collect();
+    sort_families(&mut families);
+    families
+}
+
+/**
+ * Check if a resource of the manifest is a font, from its media type.
+ */
+pub fn is_font(media_type: &str) -> bool {
+    let media_type = media_type.to_lowercase();
+    FONT_MEDIA_TYPES
+        .iter()
+        .any(|font| media_type.contains(font))
+}
+
+/**
+ * Find the obfuscated resources of a book, listed by its META-INF/encryption.xml.
+ * Resources encrypted by other algorithms, like the ones of the DRM, are not listed.
+ *
+ * @return the path of each resource, relative to the root of the archive, with its algorithm
+ */
+pub fn obfuscated_resources(encryption: &str) -> Vec<(String, Obfuscation)> {
+    let mut resources = Vec::new();
+    let mut algorithm = None;
+    let mut element = String::new();
+
+    for token in Tokenizer::from(encryption) {
+        match token {
+            Token::ElementStart { local, .. } => {
+                if local == "encrypteddata" {
+                    algorithm = None;
+                }
+                element = local;
+            }
+            Token::Attribute { local, value, .. } => match (element.as_str(), local.as_str()) {
+                ("encryptionmethod", "algorithm") => {
+                    algorithm = Obfuscation::from_algorithm(&value);
+                }
+                ("cipherreference", "uri") => {
+                    if let Some(algorithm) = algorithm {
+                        resources.push((value, algorithm));
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    resources
+}
+
+fn has_font_signature(font: &[u8]) -> bool {
+    FONT_SIGNATURES
+        .iter()
+        .any(|signature| font.starts_with(signature))
+}
+
+fn font_families(database: &fontdb::Database) -> Vec<String> {
+    let mut families: Vec<String> = database
+        .faces()
+        .iter()
+        .map(|face| face.family.clone())
+        .collect();
+    sort_families(&mut families);
+    families
+}
+
+fn sort_families(families: &mut Vec<String>) {
+    families.sort_by_key(|family| family.to_lowercase());
+    families.dedup();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deobfuscate() {
+        let encryption = r#"<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+            <EncryptedData xmlns="http://www.w3.org/2001/04/xmlenc#">
+                <EncryptionMethod Algorithm="http://www.idpf.org/2008/embedding"/>
+                <CipherData><CipherReference URI="OEBPS/Fonts/serif.otf"/></CipherData>
+            </EncryptedData>
+            <EncryptedData xmlns="http://www.w3.org/2001/04/xmlenc#">
+                <EncryptionMethod Algorithm="http://www.w3.org/2001/04/xmlenc#aes128-cbc"/>
+                <CipherData><CipherReference URI="OEBPS/Text/c1.xhtml"/></CipherData>
+            </EncryptedData>
+        </encryption>"#;
+        assert_eq!(
+            obfuscated_resources(encryption),
+            vec![("OEBPS/Fonts/serif.otf".to_string(), Obfuscation::Idpf)]
+        );
+
+        let identifiers = vec![
+            "9780000000000".to_string(),
+            "urn:uuid:0f6fc4d1-1a3d-4b6e-9e1e-2bb4d2f6d0a1".to_string(),
+        ];
+        for obfuscation in [Obfuscation::Idpf, Obfuscation::Adobe] {
+            let font: Vec<u8> = b"OTTO"
+                .iter()
+                .copied()
+                .chain((0..2000u16).map(|i| i as u8))
+                .collect();
+            let mut obfuscated = font.clone();
+            assert!(obfuscation.apply(&mut obfuscated, &identifiers[1]));
+            assert_ne!(obfuscated, font);
+
+            assert!(obfuscation.deobfuscate(&mut obfuscated, &identifiers));
+            assert_eq!(obfuscated, font);
+        }
+    }
+}
@@ -1,9 +1,12 @@
+pub(crate) mod annotations;
 pub(crate) mod settings;
 pub(crate) mod epub_data;
 pub(crate) mod ocr_data;
 pub(crate) mod edit_data;
 pub(crate) mod sidebar;
+pub(crate) mod theme;
+pub(crate) mod fonts;
 
 
 pub(crate) use epub_data::EpubData;
-pub(crate) use sidebar::SidebarData;
\ No newline at end of file
+pub(crate) use sidebar::{SearchOptions, SidebarData};
\ No newline at end of file
@@ -1,7 +1,9 @@
-use druid::{Lens, Data};
+use druid::{im::Vector, Lens, Data};
 use serde::{Serialize, Deserialize};
 
-use crate::core::constants;
+use crate::{core::constants, css::TextAlign};
+
+use super::theme::Theme;
 
 
 /**
@@ -16,20 +18,61 @@ pub enum VisualizationMode {
 }
 
 
+// settings missing in older state files take their default value
 #[derive(Lens, Clone, Data, Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct EpubSettings {
     
     pub font_size: f64,
     pub margin: f64,
     pub paragraph_spacing: f64,
 
+    // None for the default serif font
+    pub font_family: Option<String>,
+    pub line_height: f64,
+    // None to keep the alignment of the book
+    pub text_align: Option<TextAlign>,
+    // in em, None to keep the indent of the book
+    pub first_line_indent: Option<f64>,
+    // break the words at the end of the lines, following the language of the book
+    pub hyphenation: bool,
+
     pub visualization_mode: VisualizationMode,
+
+    // apply the stylesheets of the book; when false the user settings
+    // override the styles of the publisher
+    pub publisher_styles: bool,
+
+    // the theme used, and the ones saved by the user
+    pub theme: Theme,
+    pub custom_themes: Vector<Theme>,
 }
 impl EpubSettings {
     pub fn new() -> Self {
         EpubSettings::default()
     }
 
+    /**
+     * Save the current theme among the custom themes,
+     * replacing the custom theme with the same name if any.
+     */
+    pub fn save_custom_theme(&mut self) {
+        let theme = self.theme.clone();
+        match self.custom_themes.iter().position(|t| t.name == theme.name) {
+            Some(i) => {
+                self.custom_themes.set(i, theme);
+            }
+            None => self.custom_themes.push_back(theme),
+        }
+    }
+
+    /**
+     * Remove the custom theme with the name of the current theme.
+     * The current theme is kept until another one is chosen.
+     */
+    pub fn remove_custom_theme(&mut self) {
+        self.custom_themes.retain(|t| t.name != self.theme.name);
+    }
 }
 
 impl Default for EpubSettings {
@@ -39,7 +82,18 @@ impl Default for EpubSettings {
             margin: constants::epub_settings::DEFAULT_MARGIN,
             paragraph_spacing: constants::epub_settings::DEFAULT_PARAGRAPH_SPACING,
 
+            font_family: None,
+            line_height: constants::epub_settings::DEFAULT_LINE_HEIGHT,
+            text_align: None,
+            first_line_indent: None,
+            hyphenation: false,
+
             visualization_mode: VisualizationMode::SinglePage,
+
+            publisher_styles: true,
+
+            theme: Theme::default(),
+            custom_themes: Vector::new(),
         }
     }
 }
\ No newline at end of file
@@ -2,6 +2,20 @@ use druid::{Lens, Data, im::Vector};
 
 use crate::data::IndexedText;
 
+/**
+ * Options of the search in the book.
+ * The searches with the default options use the index of the book,
+ * the others scan its text.
+ */
+#[derive(Clone, Lens, Data, Default, PartialEq, Debug)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub regex: bool,
+    // "perche" finds "perché"
+    pub ignore_accents: bool,
+}
+
 /**
  * Struct used for maintaining all the data that is displayed in the sidebar.
  * Contains the table of contents and the search results.
@@ -13,6 +27,16 @@ pub struct SidebarData {
     pub search_results: Vector<IndexedText>,
 
     pub search_input: String,
+    pub search_options: SearchOptions,
+    // page of the results shown, and number of results of the search
+    pub search_page: usize,
+    pub search_total: usize,
+    // entry of search_results of the result shown in the book
+    pub search_selected: Option<usize>,
+
+    // print pages of the book, if it lists them
+    pub page_list: Vector<IndexedText>,
+    pub print_page_input: String,
 }
 
 impl SidebarData {
@@ -21,6 +45,12 @@ impl SidebarData {
             table_of_contents,
             search_results: Vector::new(),
             search_input: String::default(),
+            search_options: SearchOptions::default(),
+            search_page: 0,
+            search_total: 0,
+            search_selected: None,
+            page_list: Vector::new(),
+            print_page_input: String::default(),
         }
     }
 }
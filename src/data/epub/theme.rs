@@ -0,0 +1,139 @@
+use druid::{Color, Data, Lens};
+use serde::{Deserialize, Serialize};
+
+/**
+ * The colors of the reader and of the rest of the application.
+ * The colors are hex strings ("#RRGGBB" or "#RRGGBBAA"), so they can be
+ * written by the user; an invalid color is shown as black.
+ */
+#[derive(Clone, Data, Lens, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    // the page and the text of the book
+    pub background: String,
+    pub text: String,
+    pub link: String,
+    // the results of the search shown in the page
+    pub highlight: String,
+    // the sidebar, the home page and the popups
+    pub chrome: String,
+    pub chrome_light: String,
+    pub chrome_text: String,
+}
+
+impl Theme {
+    pub fn day() -> Self {
+        Theme {
+            name: "Day".to_string(),
+            background: "#FFFFFF".to_string(),
+            text: "#000000".to_string(),
+            link: "#0000EE".to_string(),
+            highlight: "#FFFF005A".to_string(),
+            chrome: "#3C4047".to_string(),
+            chrome_light: "#637391".to_string(),
+            chrome_text: "#FFFFFF".to_string(),
+        }
+    }
+
+    pub fn sepia() -> Self {
+        Theme {
+            name: "Sepia".to_string(),
+            background: "#F4ECD8".to_string(),
+            text: "#5B4636".to_string(),
+            link: "#8B4513".to_string(),
+            highlight: "#E0B0507F".to_string(),
+            chrome: "#4A3F35".to_string(),
+            chrome_light: "#7A6A58".to_string(),
+            chrome_text: "#FFF8E7".to_string(),
+        }
+    }
+
+    pub fn night() -> Self {
+        Theme {
+            name: "Night".to_string(),
+            background: "#1E1E1E".to_string(),
+            text: "#D0D0D0".to_string(),
+            link: "#7FB2FF".to_string(),
+            highlight: "#B8A20066".to_string(),
+            chrome: "#121212".to_string(),
+            chrome_light: "#2E3440".to_string(),
+            chrome_text: "#E0E0E0".to_string(),
+        }
+    }
+
+    pub fn presets() -> [Theme; 3] {
+        [Theme::day(), Theme::sepia(), Theme::night()]
+    }
+
+    pub fn background_color(&self) -> Color {
+        parse_color(&self.background)
+    }
+
+    pub fn text_color(&self) -> Color {
+        parse_color(&self.text)
+    }
+
+    pub fn link_color(&self) -> Color {
+        parse_color(&self.link)
+    }
+
+    pub fn highlight_color(&self) -> Color {
+        parse_color(&self.highlight)
+    }
+
+    pub fn chrome_color(&self) -> Color {
+        parse_color(&self.chrome)
+    }
+
+    pub fn chrome_light_color(&self) -> Color {
+        parse_color(&self.chrome_light)
+    }
+
+    pub fn chrome_text_color(&self) -> Color {
+        parse_color(&self.chrome_text)
+    }
+
+    /**
+     * Check that all the colors of the theme can be understood.
+     */
+    pub fn is_valid(&self) -> bool {
+        [
+            &self.background,
+            &self.text,
+            &self.link,
+            &self.highlight,
+            &self.chrome,
+            &self.chrome_light,
+            &self.chrome_text,
+        ]
+        .iter()
+        .all(|color| Color::from_hex_str(color.trim()).is_ok())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::day()
+    }
+}
+
+fn parse_color(color: &str) -> Color {
+    Color::from_hex_str(color.trim()).unwrap_or(Color::BLACK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_colors() {
+        let mut theme = Theme::night();
+        assert!(theme.is_valid());
+        assert_eq!(theme.background_color(), Color::rgb8(0x1E, 0x1E, 0x1E));
+        assert_eq!(theme.highlight_color(), Color::rgba8(0xB8, 0xA2, 0, 0x66));
+
+        theme.text = "not a color".to_string();
+        assert!(!theme.is_valid());
+        assert_eq!(theme.text_color(), Color::BLACK);
+    }
+}
@@ -1,57 +1,80 @@
-use super::recent::Recent;
+use std::path::PathBuf;
+
+use super::{library_result::LibraryResult, library_state::LibraryState, recent::Recent};
 use druid::{im::Vector, Data, Lens};
 
 /**
  * Struct used for maintaining all the data that is displayed in the home page.
- * Contains the list of recently opened books.
+ * Contains the list of recently opened books and the search in them.
  */
 #[derive(Clone, Data, Lens)]
 pub struct HomePageData {
     pub recents: Vector<Recent>,
+
+    pub search_input: String,
+    // shown instead of the recents, unless empty
+    pub search_results: Vector<LibraryResult>,
+    // the number of the last search started, the results of the older ones are discarded
+    #[data(ignore)]
+    pub search_id: usize,
+
+    // the data directory of the application, with the state file and the search indexes
+    #[data(ignore)]
+    pub data_dir: PathBuf,
+    // the errors shown to the user, empty when there is none
+    pub error: String,
+    // set when the library was saved by a newer version of the application,
+    // so the file is not overwritten
+    #[data(ignore)]
+    read_only: bool,
 }
 
 impl HomePageData {
-    const RECENTS_PATH: &'static str = ".recents";
-    pub fn new() -> Self {
-        let mut recents = HomePageData::load_from_state_file();
+    pub fn new(data_dir: PathBuf) -> Self {
+        let (state, error) = LibraryState::load(&data_dir);
+        let read_only = state.is_read_only();
+        let mut recents: Vector<Recent> = state.recents.into();
         recents.retain(|recent| epub::doc::EpubDoc::new(recent.path.clone()).is_ok());
 
-        HomePageData { recents }
+        HomePageData {
+            recents,
+            search_input: String::new(),
+            search_results: Vector::new(),
+            search_id: 0,
+            data_dir,
+            error: error.unwrap_or_default(),
+            read_only,
+        }
     }
 
     /**
-     * Loads the recents from the state file.
-     * If the state file does not exist, it creates a new one.
+     * Shows an error to the user, after the ones not dismissed yet.
      *
-     * @return The list of recents
+     * @param message: the error to show
      */
-    fn load_from_state_file() -> Vector<Recent> {
-        let md = std::fs::metadata(HomePageData::RECENTS_PATH);
-
-        let recents_string = if md.is_err() {
-            std::fs::File::create(HomePageData::RECENTS_PATH).unwrap();
-            return Vector::default();
-        } else {
-            std::fs::read_to_string(HomePageData::RECENTS_PATH).unwrap()
-        };
-
-        let serde_value: Result<Vec<Recent>, serde_json::Error> =
-            serde_json::from_str(&recents_string);
-
-        match serde_value {
-            Ok(recents) => return recents.into(),
-            Err(_) => return Vector::new(),
+    pub fn show_error(&mut self, message: String) {
+        if !self.error.is_empty() {
+            self.error.push('\n');
         }
+        self.error.push_str(&message);
     }
 
     /**
-     * Writes the recents to the state file.
-     *
+     * Writes the recents to the state file, unless it was saved by a newer version.
+     * If they cannot be written, the error is shown to the user.
      */
-    fn write_to_state_file(&self) {
-        let t: Vec<Recent> = self.recents.clone().into_iter().collect();
-        let recents_string = serde_json::to_string(&t).unwrap();
-        std::fs::write(HomePageData::RECENTS_PATH, recents_string).unwrap();
+    fn write_to_state_file(&mut self) {
+        if self.read_only {
+            return;
+        }
+        let state = LibraryState::new(self.recents.iter().cloned().collect());
+        if let Err(e) = state.save(&self.data_dir) {
+            self.show_error(format!(
+                "The library could not be saved in {}: {}",
+                LibraryState::path(&self.data_dir).display(),
+                e
+            ));
+        }
     }
 
     pub fn with_recents(mut self, recents: Vector<Recent>) -> Self {
@@ -71,12 +94,10 @@ impl HomePageData {
 
     pub fn update_recent(&mut self, r: Recent) {
         // substitute the old recent with the new one
-        let position = self
-            .recents
-            .iter()
-            .position(|x| &x.path == &r.path)
-            .unwrap();
-        self.recents[position] = r;
+        match self.recents.iter().position(|x| x.path == r.path) {
+            Some(position) => self.recents[position] = r,
+            None => self.recents.push_back(r),
+        }
 
         self.write_to_state_file();
     }
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use druid::{im::Vector, ArcStr, Data};
+
+use crate::{
+    data::PagePosition,
+    search::{char_range, LibraryMatch},
+};
+
+/**
+ * An entry of the results of a search in the library.
+ * The results of a book follow the entry of the book itself.
+ */
+#[derive(Clone, Data, Debug)]
+pub struct LibraryResult {
+    pub book_path: String,
+    pub label: ArcStr,
+    // None for the entry of the book, which opens it where it was left
+    pub position: Option<Arc<PagePosition>>,
+}
+
+impl LibraryResult {
+    /**
+     * An entry which opens nothing, like the message shown during the search.
+     */
+    pub fn message(message: &str) -> Self {
+        LibraryResult {
+            book_path: String::new(),
+            label: ArcStr::from(message),
+            position: None,
+        }
+    }
+
+    pub fn is_book(&self) -> bool {
+        self.position.is_none()
+    }
+
+    /**
+     * Get the entries of the books matching a search, grouped by book.
+     */
+    pub fn from_matches(matches: Vec<LibraryMatch>) -> Vector<LibraryResult> {
+        const CONTEXT_LENGTH: usize = 30;
+
+        let mut results = Vector::new();
+        for book in matches {
+            let mut label = book.title.clone();
+            if !book.creator.is_empty() {
+                label.push_str(&format!(" - {}", book.creator));
+            }
+            if book.total > 0 {
+                label.push_str(&format!(" ({} results)", book.total));
+            }
+            results.push_back(LibraryResult {
+                book_path: book.book_path.clone(),
+                label: ArcStr::from(label),
+                position: None,
+            });
+
+            for hit in &book.hits {
+                let (snippet, _) = hit.snippet(CONTEXT_LENGTH);
+                results.push_back(LibraryResult {
+                    book_path: book.book_path.clone(),
+                    label: ArcStr::from(format!("    {}", snippet)),
+                    position: Some(Arc::new(PagePosition::with_range(
+                        hit.chapter,
+                        hit.richtext_number,
+                        char_range(&hit.text, hit.first_match()),
+                    ))),
+                });
+            }
+        }
+        results
+    }
+}
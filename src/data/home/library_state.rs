@@ -0,0 +1,292 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::recent::Recent;
+use crate::data::storage;
+
+// increased when the format of the state file changes, see LibraryState::migrate
+const STATE_VERSION: u64 = 2;
+const STATE_FILE: &str = "library.json";
+// version 1 of the state, the list of the recents written in the working directory
+const LEGACY_RECENTS_FILE: &str = ".recents";
+
+/**
+ * The state of the library, saved in the data directory: the books opened,
+ * with the position reached, the settings and the annotations of each one.
+ * The state files written by the older versions are migrated when loaded.
+ */
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LibraryState {
+    version: u64,
+    pub recents: Vec<Recent>,
+    // set when the state was written by a newer version of the application,
+    // which the changes would overwrite
+    #[serde(skip)]
+    read_only: bool,
+}
+
+#[derive(Debug)]
+pub enum StateError {
+    Io(std::io::Error),
+    Invalid(String),
+    // written by a newer version of the application
+    NewerVersion(u64),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::Io(e) => write!(f, "{}", e),
+            StateError::Invalid(e) => write!(f, "invalid content, {}", e),
+            StateError::NewerVersion(version) => write!(
+                f,
+                "version {} was written by a newer version of the application",
+                version
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for StateError {
+    fn from(e: std::io::Error) -> Self {
+        StateError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for StateError {
+    fn from(e: serde_json::Error) -> Self {
+        StateError::Invalid(e.to_string())
+    }
+}
+
+impl LibraryState {
+    pub fn new(recents: Vec<Recent>) -> Self {
+        LibraryState {
+            version: STATE_VERSION,
+            recents,
+            read_only: false,
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join(STATE_FILE)
+    }
+
+    /**
+     * Load the state from the data directory.
+     * A state file which cannot be read is moved aside and replaced by its backup.
+     * A state file written by a newer version is left untouched, and the state
+     * read from it is read only.
+     * Without a state file, the recents of the older versions are migrated.
+     *
+     * @param data_dir: the data directory of the application
+     *
+     * @return the state, with the message shown to the user when the saved state
+     *         could not be read
+     */
+    pub fn load(data_dir: &Path) -> (LibraryState, Option<String>) {
+        let path = LibraryState::path(data_dir);
+        if !path.exists() {
+            return LibraryState::load_legacy(data_dir);
+        }
+        let error = match LibraryState::read(&path) {
+            Ok(state) => return (state, None),
+            Err(StateError::NewerVersion(version)) => {
+                return (
+                    LibraryState::read_newer(&path),
+                    Some(format!(
+                        "The library was saved by a newer version of the application \
+                         (version {}), the changes to it are not going to be saved",
+                        version
+                    )),
+                )
+            }
+            Err(e) => e,
+        };
+
+        let kept = match storage::keep_aside(&path) {
+            Ok(aside) => format!("it was kept in {}", aside.display()),
+            Err(e) => format!("it could not be kept aside: {}", e),
+        };
+        match LibraryState::read(&storage::backup_path(&path)) {
+            Ok(state) => (
+                state,
+                Some(format!(
+                    "The library could not be read ({}), the backup was restored and {}",
+                    error, kept
+                )),
+            ),
+            Err(_) => (
+                LibraryState::new(Vec::new()),
+                Some(format!(
+                    "The library could not be read ({}), {}",
+                    error, kept
+                )),
+            ),
+        }
+    }
+
+    /**
+     * Migrate the recents written by the older versions, in the data directory
+     * or in the working directory. The old file is left untouched.
+     */
+    fn load_legacy(data_dir: &Path) -> (LibraryState, Option<String>) {
+        let legacy_path = [
+            data_dir.join(LEGACY_RECENTS_FILE),
+            PathBuf::from(LEGACY_RECENTS_FILE),
+        ]
+        .into_iter()
+        .find(|path| path.exists());
+        let legacy_path = match legacy_path {
+            Some(path) => path,
+            None => return (LibraryState::new(Vec::new()), None),
+        };
+
+        // the older versions created the file empty
+        let state = match std::fs::read_to_string(&legacy_path) {
+            Ok(content) if content.trim().is_empty() => Ok(LibraryState::new(Vec::new())),
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(StateError::from)
+                .and_then(LibraryState::migrate),
+            Err(e) => Err(StateError::from(e)),
+        };
+        match state {
+            Ok(state) => {
+                let error = state.save(data_dir).err().map(|e| {
+                    format!(
+                        "The library moved from {} could not be saved: {}",
+                        legacy_path.display(),
+                        e
+                    )
+                });
+                (state, error)
+            }
+            Err(e) => (
+                LibraryState::new(Vec::new()),
+                Some(format!(
+                    "The library in {} could not be read: {}",
+                    legacy_path.display(),
+                    e
+                )),
+            ),
+        }
+    }
+
+    /**
+     * Read the recents of a state written by a newer version, as far as they
+     * can be read by this one.
+     */
+    fn read_newer(path: &Path) -> LibraryState {
+        let recents = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<LibraryState>(&content).ok())
+            .map(|state| state.recents)
+            .unwrap_or_default();
+        LibraryState {
+            read_only: true,
+            ..LibraryState::new(recents)
+        }
+    }
+
+    fn read(path: &Path) -> Result<LibraryState, StateError> {
+        let content = std::fs::read_to_string(path)?;
+        LibraryState::migrate(serde_json::from_str(&content)?)
+    }
+
+    /**
+     * Bring a saved state of any version to the current one.
+     *
+     * @param value: the content of the state file
+     */
+    fn migrate(mut value: Value) -> Result<LibraryState, StateError> {
+        // version 1 is the bare list of the recents
+        if value.is_array() {
+            value = json!({ "version": 1, "recents": value });
+        }
+        let version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| StateError::Invalid("the version is missing".to_string()))?;
+        if version > STATE_VERSION {
+            return Err(StateError::NewerVersion(version));
+        }
+        // the recents of version 1 are read as they are, their missing fields take
+        // their default value
+        let mut state: LibraryState = serde_json::from_value(value)?;
+        state.version = STATE_VERSION;
+        Ok(state)
+    }
+
+    /**
+     * Write the state to the data directory, keeping the previous one as backup.
+     */
+    pub fn save(&self, data_dir: &Path) -> std::io::Result<()> {
+        let content = serde_json::to_string(self)?;
+        storage::write_atomic(&LibraryState::path(data_dir), content.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        let legacy = json!([{
+            "path": "book.epub",
+            "reached_position": null,
+            "epub_settings": { "font_size": 20.0 }
+        }]);
+        let state = LibraryState::migrate(legacy).unwrap();
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.recents.len(), 1);
+        assert_eq!(state.recents[0].path, "book.epub");
+        assert_eq!(state.recents[0].epub_settings.font_size, 20.0);
+
+        let saved = serde_json::to_value(&state).unwrap();
+        assert_eq!(LibraryState::migrate(saved).unwrap().recents.len(), 1);
+
+        let newer = json!({ "version": STATE_VERSION + 1, "recents": [] });
+        assert!(matches!(
+            LibraryState::migrate(newer),
+            Err(StateError::NewerVersion(_))
+        ));
+        assert!(LibraryState::migrate(json!({ "recents": [] })).is_err());
+    }
+
+    #[test]
+    fn test_load_newer_version() {
+        let dir = std::env::temp_dir().join(format!("library_state_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = LibraryState::path(&dir);
+        let content = json!({
+            "version": STATE_VERSION + 1,
+            "recents": [{
+                "path": "book.epub",
+                "reached_position": null,
+                "epub_settings": { "font_size": 20.0 }
+            }]
+        })
+        .to_string();
+        std::fs::write(&path, &content).unwrap();
+
+        let (state, error) = LibraryState::load(&dir);
+        assert!(error.is_some());
+        assert!(state.is_read_only());
+        assert_eq!(state.recents.len(), 1);
+        // the file is left for the newer version
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,8 @@
 mod home_data;
+mod library_result;
+mod library_state;
 mod recent;
 
 pub use home_data::HomePageData;
+pub use library_result::LibraryResult;
 pub use recent::{Recent, RecentData};
@@ -1,7 +1,7 @@
 use druid::{Data, Lens, ImageBuf, ArcStr};
 use serde::{Serialize, Deserialize};
 
-use crate::data::{epub::settings::EpubSettings, PagePosition};
+use crate::data::{epub::settings::EpubSettings, Bookmark, Highlight, PagePosition};
 
 /**
  * RecentData represents the data that is displayed for a single book in the home page.
@@ -23,6 +23,14 @@ pub struct Recent {
 
     pub epub_settings: EpubSettings,
 
+    // books saved before bookmarks were introduced have none
+    #[serde(default)]
+    #[data(ignore)]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    #[data(ignore)]
+    pub highlights: Vec<Highlight>,
+
     // ignore this field for serialization
     #[serde(skip)]
     pub image_data: Option<ImageBuf>,
@@ -37,6 +45,8 @@ impl Recent {
             path,
             reached_position: None,
             epub_settings: EpubSettings::default(),
+            bookmarks: Vec::new(),
+            highlights: Vec::new(),
             image_data: None,
             recent_data: None,
         }
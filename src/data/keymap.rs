@@ -0,0 +1,292 @@
+use std::str::FromStr;
+
+use druid::{im::Vector, Data, KbKey, KeyEvent, Lens, Modifiers};
+use serde::{Deserialize, Serialize};
+
+/**
+ * The actions of the reader that can be performed with the keyboard.
+ */
+#[derive(Clone, Copy, Data, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ReaderAction {
+    NextPage,
+    PreviousPage,
+    ChapterStart,
+    ChapterEnd,
+    FocusSearch,
+    AddBookmark,
+    ClosePanel,
+    NextSearchResult,
+    PreviousSearchResult,
+}
+
+impl ReaderAction {
+    pub const ALL: [ReaderAction; 9] = [
+        ReaderAction::NextPage,
+        ReaderAction::PreviousPage,
+        ReaderAction::ChapterStart,
+        ReaderAction::ChapterEnd,
+        ReaderAction::FocusSearch,
+        ReaderAction::AddBookmark,
+        ReaderAction::ClosePanel,
+        ReaderAction::NextSearchResult,
+        ReaderAction::PreviousSearchResult,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReaderAction::NextPage => "Next page",
+            ReaderAction::PreviousPage => "Previous page",
+            ReaderAction::ChapterStart => "Beginning of the chapter",
+            ReaderAction::ChapterEnd => "End of the chapter",
+            ReaderAction::FocusSearch => "Search",
+            ReaderAction::AddBookmark => "Add a bookmark",
+            ReaderAction::ClosePanel => "Close the panel",
+            ReaderAction::NextSearchResult => "Next search result",
+            ReaderAction::PreviousSearchResult => "Previous search result",
+        }
+    }
+
+    fn default_shortcuts(&self) -> &'static str {
+        match self {
+            ReaderAction::NextPage => "ArrowRight, ArrowDown, PageDown, Space",
+            ReaderAction::PreviousPage => "ArrowLeft, ArrowUp, PageUp, Shift+Space",
+            ReaderAction::ChapterStart => "Home",
+            ReaderAction::ChapterEnd => "End",
+            ReaderAction::FocusSearch => "Ctrl+F",
+            ReaderAction::AddBookmark => "Ctrl+B",
+            ReaderAction::ClosePanel => "Escape",
+            ReaderAction::NextSearchResult => "F3, Cmd+G",
+            ReaderAction::PreviousSearchResult => "Shift+F3, Cmd+Shift+G",
+        }
+    }
+}
+
+/**
+ * The keys performing an action.
+ * The shortcuts are separated by commas, and each one is made of the modifiers
+ * (Ctrl, Alt, Shift, Meta, and Cmd which is Meta on macOS and Ctrl elsewhere)
+ * and the key, joined by "+", like "Ctrl+Shift+G" or "PageDown".
+ * The names of the keys are the ones of the web standards, plus "Space".
+ */
+#[derive(Clone, Data, Lens, Debug, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub action: ReaderAction,
+    pub shortcuts: String,
+}
+
+impl KeyBinding {
+    /**
+     * Check that all the shortcuts can be understood.
+     */
+    pub fn is_valid(&self) -> bool {
+        shortcuts(&self.shortcuts).all(|shortcut| parse_shortcut(shortcut).is_some())
+    }
+}
+
+/**
+ * The shortcuts of the reader, saved in the configuration of the application.
+ * The actions missing in the saved keymap, like the ones added after it was
+ * written, take their default shortcuts.
+ */
+#[derive(Clone, Data, Lens, Debug, Serialize, Deserialize)]
+#[serde(from = "Vec<KeyBinding>", into = "Vec<KeyBinding>")]
+pub struct Keymap {
+    pub bindings: Vector<KeyBinding>,
+}
+
+impl Keymap {
+    /**
+     * Get the action performed by a key.
+     * A shortcut with the exact modifiers of the key is preferred to one
+     * matching it only because Shift is ignored, see shortcut_matches.
+     *
+     * @return None if no shortcut matches the key
+     */
+    pub fn action_for(&self, key: &KeyEvent) -> Option<ReaderAction> {
+        let find = |exact: bool| {
+            self.bindings
+                .iter()
+                .find(|binding| {
+                    shortcuts(&binding.shortcuts)
+                        .any(|shortcut| shortcut_matches(shortcut, key, exact))
+                })
+                .map(|binding| binding.action)
+        };
+        find(true).or_else(|| find(false))
+    }
+}
+
+impl From<Vec<KeyBinding>> for Keymap {
+    fn from(saved: Vec<KeyBinding>) -> Self {
+        let mut keymap = Keymap::default();
+        for binding in saved {
+            if let Some(default) = keymap
+                .bindings
+                .iter_mut()
+                .find(|default| default.action == binding.action)
+            {
+                default.shortcuts = binding.shortcuts;
+            }
+        }
+        keymap
+    }
+}
+
+impl From<Keymap> for Vec<KeyBinding> {
+    fn from(keymap: Keymap) -> Self {
+        keymap.bindings.into_iter().collect()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: ReaderAction::ALL
+                .iter()
+                .map(|action| KeyBinding {
+                    action: *action,
+                    shortcuts: action.default_shortcuts().to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/**
+ * Check if a key could edit a text, so it is left to the text inputs when they have the focus.
+ * The keys with Ctrl, Alt or Meta, the function keys and Escape do not.
+ */
+pub fn is_text_editing_key(key: &KeyEvent) -> bool {
+    if key.mods.ctrl() || key.mods.alt() || key.mods.meta() {
+        return false;
+    }
+    matches!(
+        key.key,
+        KbKey::Character(_)
+            | KbKey::ArrowLeft
+            | KbKey::ArrowRight
+            | KbKey::ArrowUp
+            | KbKey::ArrowDown
+            | KbKey::Home
+            | KbKey::End
+            | KbKey::PageUp
+            | KbKey::PageDown
+            | KbKey::Backspace
+            | KbKey::Delete
+            | KbKey::Enter
+            | KbKey::Tab
+    )
+}
+
+fn shortcuts(shortcuts: &str) -> impl Iterator<Item = &str> {
+    shortcuts
+        .split(',')
+        .map(|shortcut| shortcut.trim())
+        .filter(|shortcut| !shortcut.is_empty())
+}
+
+/**
+ * Get the modifiers and the key of a shortcut.
+ *
+ * @return None if a modifier or the key is unknown
+ */
+fn parse_shortcut(shortcut: &str) -> Option<(Modifiers, KbKey)> {
+    // "Ctrl++" is the "+" key
+    let (modifiers, key) = match shortcut.strip_suffix("++") {
+        Some(modifiers) => (modifiers, "+"),
+        None => shortcut.rsplit_once('+').unwrap_or(("", shortcut)),
+    };
+
+    let mut mods = Modifiers::empty();
+    for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+        mods |= match modifier.trim().to_lowercase().as_str() {
+            "ctrl" | "control" => Modifiers::CONTROL,
+            "alt" | "option" => Modifiers::ALT,
+            "shift" => Modifiers::SHIFT,
+            "meta" | "super" => Modifiers::META,
+            "cmd" if cfg!(target_os = "macos") => Modifiers::META,
+            "cmd" => Modifiers::CONTROL,
+            _ => return None,
+        };
+    }
+
+    let key = match key.trim() {
+        "Space" => KbKey::Character(" ".to_string()),
+        "Esc" => KbKey::Escape,
+        key => KbKey::from_str(key).ok()?,
+    };
+    Some((mods, key))
+}
+
+/**
+ * Check if a key is the one of a shortcut.
+ * The letters match in both cases. Unless exact, Shift is ignored
+ * for the other characters, since it is often needed to type them.
+ */
+fn shortcut_matches(shortcut: &str, key: &KeyEvent, exact: bool) -> bool {
+    let (mut mods, shortcut_key) = match parse_shortcut(shortcut) {
+        Some(parsed) => parsed,
+        None => return false,
+    };
+    let mut key_mods =
+        key.mods & (Modifiers::SHIFT | Modifiers::CONTROL | Modifiers::ALT | Modifiers::META);
+
+    match (&shortcut_key, &key.key) {
+        (KbKey::Character(shortcut_char), KbKey::Character(key_char)) => {
+            if !exact && !key_char.chars().any(|c| c.is_alphabetic()) {
+                mods.set(Modifiers::SHIFT, false);
+                key_mods.set(Modifiers::SHIFT, false);
+            }
+            mods == key_mods && shortcut_char.to_lowercase() == key_char.to_lowercase()
+        }
+        _ => mods == key_mods && shortcut_key == key.key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap() {
+        let keymap = Keymap::default();
+        let action =
+            |mods: Modifiers, key: KbKey| keymap.action_for(&KeyEvent::for_test(mods, key));
+
+        assert_eq!(
+            action(Modifiers::empty(), KbKey::PageDown),
+            Some(ReaderAction::NextPage)
+        );
+        assert_eq!(
+            action(Modifiers::empty(), KbKey::Character(" ".to_string())),
+            Some(ReaderAction::NextPage)
+        );
+        assert_eq!(
+            action(Modifiers::SHIFT, KbKey::Character(" ".to_string())),
+            Some(ReaderAction::PreviousPage)
+        );
+        assert_eq!(
+            action(Modifiers::CONTROL, KbKey::Character("F".to_string())),
+            Some(ReaderAction::FocusSearch)
+        );
+        assert_eq!(
+            action(Modifiers::SHIFT, KbKey::F3),
+            Some(ReaderAction::PreviousSearchResult)
+        );
+        assert_eq!(
+            action(Modifiers::empty(), KbKey::Character("f".to_string())),
+            None
+        );
+
+        assert!(KeyBinding {
+            action: ReaderAction::FocusSearch,
+            shortcuts: "Ctrl++, Alt+Shift+PageUp".to_string(),
+        }
+        .is_valid());
+        assert!(!KeyBinding {
+            action: ReaderAction::FocusSearch,
+            shortcuts: "Hyperctrl+F".to_string(),
+        }
+        .is_valid());
+    }
+}
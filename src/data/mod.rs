@@ -2,10 +2,17 @@ pub mod home;
 pub mod epub;
 pub(crate) mod common;
 pub(crate) mod appstate;
+pub(crate) mod keymap;
+pub(crate) mod config;
+pub(crate) mod storage;
 
 pub use home::HomePageData;
-pub use home::{Recent, RecentData};
+pub use home::{LibraryResult, Recent, RecentData};
 pub use appstate::AppState;
+pub use keymap::{KeyBinding, Keymap, ReaderAction};
+pub use config::AppConfig;
 
 pub use common::page_position::PagePosition;
+pub use common::bookmark::Bookmark;
+pub use common::highlight::{Highlight, HighlightColor};
 pub use common::indexed_text::IndexedText;
\ No newline at end of file
@@ -0,0 +1,62 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/**
+ * Module for writing the files of the application, like the state of the
+ * library and the configuration, without losing them if the application
+ * stops while they are written.
+ */
+
+/**
+ * Write a file atomically: the content is written to a temporary file
+ * which then replaces the file, so the file is never half written.
+ * The previous content of the file is kept in its backup.
+ *
+ * @param path: the file, its directory is created if needed
+ * @param content: the new content of the file
+ */
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp_path = with_suffix(path, "tmp");
+    let mut temp = File::create(&temp_path)?;
+    temp.write_all(content)?;
+    temp.sync_all()?;
+    drop(temp);
+
+    if path.exists() {
+        fs::copy(path, backup_path(path))?;
+    }
+    fs::rename(&temp_path, path)
+}
+
+/**
+ * The path of the backup of a file, with its previous content.
+ */
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, "bak")
+}
+
+/**
+ * Move aside a file which cannot be read, so it is not overwritten
+ * and the user can recover it.
+ *
+ * @return the new path of the file
+ */
+pub fn keep_aside(path: &Path) -> io::Result<PathBuf> {
+    let aside = with_suffix(path, "unreadable");
+    fs::rename(path, &aside)?;
+    Ok(aside)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
@@ -1,32 +1,68 @@
-use druid::{im::Vector, text::RichText, ArcStr, Data};
+use std::{collections::HashMap, sync::Arc};
 
-use crate::core::style::LINK_COLOR;
+use druid::{
+    im::Vector,
+    piet::TextStorage,
+    text::{RichText, RichTextBuilder},
+    ArcStr, Command, Data,
+};
+use hyphenation::Standard;
+
+use crate::core::{
+    constants::{
+        commands::{InternalUICommand, INTERNAL_COMMAND},
+        epub_settings::DEFAULT_FONT_SIZE,
+    },
+    style::LINK,
+};
+use crate::css::{BlockStyle, ComputedStyle, ElementInfo, Length, Style, Stylesheet};
+use crate::html::{ElementEnd, Token, Tokenizer};
+use crate::hyphenate::{hyphenate, remove_soft_hyphens};
 /**
  * Module for parsing HTML and generating a tree of renderable objects for 
  * druid GUI framework.
  * 
  * This module define a list of HTML tags that are supported.
  * This module also define Renderable objects that can be used to render the
- * HTML content. It can be an Image or a RichText.
+ * HTML content. It can be an Image, a RichText, a list item, a table or a rule.
  * 
  * generate_renderable_tree is the main function of this module. It takes a
  * string of HTML and returns a vector of Renderable objects.
  * 
- * The mechanism is based on the xmlparser crate.
+ * The HTML is read with the tokenizer of the html module. As in HTML5 parsers,
+ * elements are closed implicitly (like a paragraph when the next one starts)
+ * and stray closing tags are ignored, so sloppy chapters are still shown.
  */
 
 
 #[derive(Debug, PartialEq, Data, Clone)]
 enum HtmlTag {
     Header(u8),
-    Link(i32),
+    Link(String),
+    NoteRef(String),
+    Note,
     Image(String),
     Paragraph,
+    Division,
+    UnorderedList,
+    OrderedList,
+    ListItem,
+    BlockQuote,
+    Preformatted,
+    Code,
+    Table,
+    TableRow,
+    TableCell,
+    TableHeaderCell,
+    LineBreak,
+    HorizontalRule,
     Bold,
     Italic,
     Underline,
     StrikeThrough,
     Title,
+    Style,
+    Script,
     Unhandled,
 }
 
@@ -39,71 +75,892 @@ impl From<&str> for HtmlTag {
             "h4" => HtmlTag::Header(4),
             "h5" => HtmlTag::Header(5),
             "h6" => HtmlTag::Header(6),
-            "a" => HtmlTag::Link(-1),
-            "img" => HtmlTag::Image("".to_string()),
-            "p" => HtmlTag::Paragraph,
+            "a" => HtmlTag::Link("".to_string()),
+            "img" | "image" => HtmlTag::Image("".to_string()),
+            "p" | "figcaption" | "caption" => HtmlTag::Paragraph,
+            "div" | "section" | "article" | "header" | "footer" | "main" | "nav" | "figure"
+            | "dl" | "dt" | "dd" => HtmlTag::Division,
+            "ul" => HtmlTag::UnorderedList,
+            "ol" => HtmlTag::OrderedList,
+            "li" => HtmlTag::ListItem,
+            "blockquote" => HtmlTag::BlockQuote,
+            "pre" => HtmlTag::Preformatted,
+            "code" | "tt" | "kbd" | "samp" => HtmlTag::Code,
+            "table" => HtmlTag::Table,
+            "tr" => HtmlTag::TableRow,
+            "td" => HtmlTag::TableCell,
+            "th" => HtmlTag::TableHeaderCell,
+            "br" => HtmlTag::LineBreak,
+            "hr" => HtmlTag::HorizontalRule,
             "strong" | "b" => HtmlTag::Bold,
             "em" | "i" => HtmlTag::Italic,
             "u" => HtmlTag::Underline,
             "del" | "s" => HtmlTag::StrikeThrough,
             "title" => HtmlTag::Title,
+            "style" => HtmlTag::Style,
+            "script" => HtmlTag::Script,
+            "aside" => HtmlTag::Note,
             _ => HtmlTag::Unhandled,
         }
     }
 }
 impl HtmlTag {
     pub fn should_tag_be_written(&self) -> bool {
-        matches!(self, HtmlTag::Title)
+        matches!(self, HtmlTag::Title | HtmlTag::Style | HtmlTag::Script)
+    }
+
+    /**
+     * Blocks start on a new Renderable: the text preceding them
+     * and the text they contain are never glued together.
+     */
+    fn is_block(&self) -> bool {
+        matches!(
+            self,
+            HtmlTag::Paragraph
+                | HtmlTag::Header(_)
+                | HtmlTag::Division
+                | HtmlTag::UnorderedList
+                | HtmlTag::OrderedList
+                | HtmlTag::ListItem
+                | HtmlTag::BlockQuote
+                | HtmlTag::Preformatted
+        )
     }
 
-    pub fn add_attribute_for_token(&self, mut attrs: druid::text::AttributesAdder, font_size: f64) {
+    /**
+     * The style given to the tag when no stylesheet says otherwise.
+     */
+    fn default_style(&self) -> Style {
+        let mut style = Style::default();
         match self {
             HtmlTag::Header(lvl) => {
-                let font_size = font_size
-                    * match lvl {
-                        1 => 2.,
-                        2 => 1.5,
-                        3 => 1.17,
-                        4 => 1.,
-                        5 => 0.8375,
-                        6 => 0.67,
-                        _ => 1.,
-                    };
-                attrs.size(font_size).weight(druid::FontWeight::BOLD);
-            }
-            HtmlTag::Bold => {
-                attrs.weight(druid::FontWeight::BOLD);
-            }
-            HtmlTag::Italic => {
-                attrs.style(druid::FontStyle::Italic);
-            }
-            HtmlTag::Underline => {
-                attrs.underline(true);
-            }
-            HtmlTag::StrikeThrough => {
-                attrs.strikethrough(true);
-            }
-            HtmlTag::Link(_target) => {
-                //Tag::Link(_link_ty, target, _title) => {
-                attrs.underline(true).text_color(LINK_COLOR);
-            }
-            HtmlTag::Image(_img) => {}
-            _ => {
-                return;
+                let size = match lvl {
+                    1 => 2.,
+                    2 => 1.5,
+                    3 => 1.17,
+                    4 => 1.,
+                    5 => 0.8375,
+                    6 => 0.67,
+                    _ => 1.,
+                };
+                style.font_size = Some(Length::Em(size));
+                style.font_weight = Some(druid::FontWeight::BOLD);
+            }
+            HtmlTag::UnorderedList | HtmlTag::OrderedList => {
+                style.margin_left = Some(Length::Em(2.))
+            }
+            HtmlTag::BlockQuote => {
+                style.margin_left = Some(Length::Em(2.));
+                style.margin_right = Some(Length::Em(2.));
+            }
+            HtmlTag::Preformatted => {
+                style.font_family = Some(druid::FontFamily::MONOSPACE);
+                style.white_space = Some(true);
+            }
+            HtmlTag::Code => style.font_family = Some(druid::FontFamily::MONOSPACE),
+            HtmlTag::Bold | HtmlTag::TableHeaderCell => {
+                style.font_weight = Some(druid::FontWeight::BOLD)
+            }
+            HtmlTag::Italic => style.font_style = Some(druid::FontStyle::Italic),
+            HtmlTag::Underline => style.underline = Some(true),
+            HtmlTag::StrikeThrough => style.strikethrough = Some(true),
+            // anchors without href are only link targets
+            HtmlTag::Link(href) if !href.is_empty() => {
+                style.underline = Some(true);
+                style.color = Some(LINK.into());
+            }
+            HtmlTag::NoteRef(_) => style.color = Some(LINK.into()),
+            _ => {}
+        }
+        style
+    }
+
+    /**
+     * The command submitted when the text of the tag is clicked, if the tag is a link.
+     */
+    fn link_command(&self) -> Option<Command> {
+        match self {
+            HtmlTag::Link(href) if !href.is_empty() => {
+                Some(INTERNAL_COMMAND.with(InternalUICommand::EpubOpenLink(href.clone())))
+            }
+            HtmlTag::NoteRef(href) => {
+                Some(INTERNAL_COMMAND.with(InternalUICommand::EpubOpenNote(href.clone())))
             }
+            _ => None,
         }
     }
 }
 
-// Create an enum for render both images or text
+// Create an enum for render both images or text.
+// Images contain the source path as written in the chapter,
+// it has to be resolved against the chapter path before loading it.
+// Texts carry the layout of their paragraph, as computed from the stylesheets.
+// List items carry their marker (bullet or number) apart from the text,
+// tables are grids of cells, one vector per row.
 #[derive(Debug, Data, Clone)]
 pub enum Renderable {
     Image(ArcStr),
-    Text(RichText),
+    Text(RichText, BlockStyle),
+    ListItem(ArcStr, RichText, BlockStyle),
+    Table(Vector<Vector<RichText>>, BlockStyle),
+    Rule,
+}
+
+impl Renderable {
+    /**
+     * The text of the renderable, as used for searching.
+     * The cells of a table are separated by tabs and its rows by new lines,
+     * images and rules have no text. Soft hyphens are not part of the text.
+     */
+    pub fn plain_text(&self) -> String {
+        match self {
+            Renderable::Text(text, _) | Renderable::ListItem(_, text, _) => {
+                remove_soft_hyphens(text.as_str())
+            }
+            Renderable::Table(rows, _) => rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| remove_soft_hyphens(cell.as_str()))
+                        .collect::<Vec<String>>()
+                        .join("\t")
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Renderable::Image(_) | Renderable::Rule => String::new(),
+        }
+    }
+}
+
+// values of epub:type marking an element as a note
+const NOTE_EPUB_TYPES: [&str; 4] = ["footnote", "endnote", "rearnote", "note"];
+
+/**
+ * The result of parsing a chapter.
+ * It contains the renderables of the main flow, the anchors (ids or names
+ * of the elements, which can be the target of a link) mapped to the index of the
+ * Renderable containing them, and the notes excluded from the main flow,
+ * mapped by their ids.
+ */
+#[derive(Default)]
+pub struct Chapter {
+    pub renderables: Vector<Renderable>,
+    pub anchors: HashMap<String, usize>,
+    pub notes: HashMap<String, RichText>,
+}
+
+/**
+ * The settings of the reader changing the text of a chapter,
+ * applied whether the styles of the publisher are used or not.
+ */
+#[derive(Clone, Default)]
+pub struct TextOptions {
+    // in em, overriding the first line indent of the paragraphs of the book
+    pub first_line_indent: Option<f64>,
+    // the patterns of the language of the book, when hyphenation is enabled
+    pub hyphenation: Option<Arc<Standard>>,
+    // the family of each font embedded in the book, by the lowercase name used in its styles
+    pub font_families: HashMap<String, String>,
+    // the colors of the text set by the styles of the book are left out, since
+    // they are chosen for a light page and could not be read with a dark theme
+    pub ignore_colors: bool,
+}
+
+/**
+ * State kept while parsing a note (aside, footnote or endnote).
+ * The text of the main flow is put aside until the note is closed.
+ */
+struct NoteState {
+    depth: usize,
+    ids: Vec<String>,
+    main_builder: RichTextBuilder,
+    main_pos: usize,
+}
+
+/**
+ * State kept while parsing a table.
+ * The text of each cell is built on its own. Tables nested in a cell
+ * are flattened into the cell, one row per line.
+ */
+#[derive(Default)]
+struct TableState {
+    nested: usize,
+    rows: Vector<Vector<RichText>>,
+    row: Vector<RichText>,
+}
+
+// bullets of the unordered lists, by nesting level
+const BULLETS: [&str; 3] = ["\u{2022}", "\u{25E6}", "\u{25AA}"];
+
+// in em of the paragraph
+const MAX_TEXT_INDENT: f64 = 4.;
+
+// elements whose start closes an open paragraph, as in HTML5
+const CLOSING_PARAGRAPH: [&str; 28] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+// elements which a paragraph is never implicitly closed across
+const PARAGRAPH_SCOPE: [&str; 6] = ["html", "table", "td", "th", "caption", "button"];
+
+/**
+ * An element opened and not closed yet, together with its computed style.
+ */
+struct OpenElement {
+    tag: HtmlTag,
+    info: ElementInfo,
+    inline_style: Option<Style>,
+    style: ComputedStyle,
+}
+
+/**
+ * ChapterBuilder receives the elements found while parsing the HTML
+ * and builds the Chapter out of them.
+ *
+ * Each piece of text is styled with the computed style of the element
+ * containing it, so nested elements never fight over the same range.
+ * When publisher styles are disabled, the stylesheet is None and only the
+ * default style of the tags is used.
+ */
+struct ChapterBuilder {
+    chapter: Chapter,
+    font_size: f64,
+    stylesheet: Option<Stylesheet>,
+    options: TextOptions,
+    builder: RichTextBuilder,
+    current_pos: usize,
+    token_stack: Vec<OpenElement>,
+    // ids of the element currently opened, used if it turns out to be a note
+    element_ids: Vec<String>,
+    note: Option<NoteState>,
+    table: Option<TableState>,
+    // notes and table cells keep their paragraphs in a single text, one per line
+    pending_break: bool,
+    // next number of the opened lists, None for unordered lists
+    lists: Vec<Option<usize>>,
+    // marker of the list item whose text is being built
+    pending_marker: Option<String>,
+    // the last text ended with white space, shown only if more text follows
+    pending_space: bool,
+    // indent of the first line of the text being built, in pixels
+    text_indent: f64,
+    // only the anchors are needed, so the text is counted but not built
+    anchors_only: bool,
+}
+
+impl ChapterBuilder {
+    fn new(font_size: f64, stylesheet: Option<&Stylesheet>, options: &TextOptions) -> Self {
+        ChapterBuilder {
+            chapter: Chapter::default(),
+            font_size,
+            stylesheet: stylesheet.cloned(),
+            options: options.clone(),
+            builder: RichTextBuilder::new(),
+            current_pos: 0,
+            token_stack: Vec::new(),
+            element_ids: Vec::new(),
+            note: None,
+            table: None,
+            pending_break: false,
+            lists: Vec::new(),
+            pending_marker: None,
+            pending_space: false,
+            text_indent: 0.,
+            anchors_only: false,
+        }
+    }
+
+    fn parent_style(&self) -> ComputedStyle {
+        self.token_stack
+            .last()
+            .map(|element| element.style.clone())
+            .unwrap_or_else(|| ComputedStyle::new(self.font_size))
+    }
+
+    fn start_element(&mut self, name: &str) {
+        self.element_ids.clear();
+        self.close_implied_elements(name);
+        let tag = HtmlTag::from(name);
+        let is_note = tag == HtmlTag::Note;
+        if tag.is_block() || matches!(tag, HtmlTag::Table | HtmlTag::HorizontalRule) {
+            self.end_block(self.current_block_style());
+        }
+        match tag {
+            HtmlTag::UnorderedList => self.lists.push(None),
+            HtmlTag::OrderedList => self.lists.push(Some(1)),
+            HtmlTag::ListItem => self.start_list_item(),
+            HtmlTag::Table => self.start_table(),
+            HtmlTag::TableCell | HtmlTag::TableHeaderCell => self.start_table_cell(),
+            _ => {}
+        }
+        // the style is computed again once all the attributes are known
+        let style = self
+            .parent_style()
+            .child(&tag.default_style(), self.font_size);
+        self.token_stack.push(OpenElement {
+            tag,
+            info: ElementInfo {
+                name: name.to_string(),
+                ..ElementInfo::default()
+            },
+            inline_style: None,
+            style,
+        });
+        if is_note {
+            self.start_note();
+        }
+    }
+
+    fn attribute(&mut self, prefix: &str, name: &str, value: &str) {
+        let element = match self.token_stack.last_mut() {
+            Some(element) => element,
+            None => return,
+        };
+        match (prefix, name) {
+            (_, "id") => {
+                element.info.id = Some(value.to_string());
+                self.add_anchor(value)
+            }
+            ("", "name") if matches!(element.tag, HtmlTag::Link(_)) => self.add_anchor(value),
+            ("", "class") => {
+                element.info.classes = value.split_whitespace().map(String::from).collect()
+            }
+            ("", "style") if self.stylesheet.is_some() => {
+                element.inline_style = Some(Style::parse(value))
+            }
+            ("", "start") if element.tag == HtmlTag::OrderedList => {
+                if let (Some(Some(number)), Ok(start)) = (self.lists.last_mut(), value.parse()) {
+                    *number = start;
+                }
+            }
+            ("epub", "type") => self.set_epub_type(value),
+            // <img src="...">, svg <image xlink:href="..."> and <a href="...">
+            (_, "src" | "href") => match &mut element.tag {
+                HtmlTag::Image(src) => *src = value.to_string(),
+                HtmlTag::Link(href) | HtmlTag::NoteRef(href) if name == "href" => {
+                    *href = value.to_string()
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /**
+     * Compute the style of the last opened element, once all its attributes are known.
+     * The default style of the tag comes first, then the stylesheet rules and
+     * finally the style attribute.
+     */
+    fn compute_style(&mut self) {
+        let mut style = match self.token_stack.last() {
+            Some(element) => element.tag.default_style(),
+            None => return,
+        };
+        let ignore_colors = self.options.ignore_colors;
+        let book_style = |mut book_style: Style| {
+            if ignore_colors {
+                book_style.color = None;
+            }
+            book_style
+        };
+        if let Some(stylesheet) = &self.stylesheet {
+            let path: Vec<&ElementInfo> = self.token_stack.iter().map(|e| &e.info).collect();
+            style.merge(&book_style(stylesheet.style_for(&path)));
+        }
+
+        let parent = match self.token_stack.len() {
+            len if len > 1 => self.token_stack[len - 2].style.clone(),
+            _ => ComputedStyle::new(self.font_size),
+        };
+        if let Some(element) = self.token_stack.last_mut() {
+            if let Some(inline_style) = &element.inline_style {
+                style.merge(&book_style(inline_style.clone()));
+            }
+            style.resolve_font_family(&self.options.font_families);
+            element.style = parent.child(&style, self.font_size);
+        }
+    }
+
+    fn add_anchor(&mut self, id: &str) {
+        self.element_ids.push(id.to_string());
+        match &mut self.note {
+            Some(note) => note.ids.push(id.to_string()),
+            None => {
+                // the text of the element will be part of the next renderable
+                self.chapter
+                    .anchors
+                    .entry(id.to_string())
+                    .or_insert(self.chapter.renderables.len());
+            }
+        }
+    }
+
+    fn set_epub_type(&mut self, value: &str) {
+        let types: Vec<&str> = value.split_whitespace().collect();
+        let is_note = NOTE_EPUB_TYPES.iter().any(|t| types.contains(t));
+
+        match self.token_stack.last_mut().map(|element| &mut element.tag) {
+            Some(tag @ HtmlTag::Link(_)) if types.contains(&"noteref") => {
+                if let HtmlTag::Link(href) = tag {
+                    *tag = HtmlTag::NoteRef(std::mem::take(href));
+                }
+            }
+            Some(tag) if is_note && *tag != HtmlTag::Note => {
+                *tag = HtmlTag::Note;
+                self.start_note();
+            }
+            _ => {}
+        }
+    }
+
+    /**
+     * Start collecting the text of a note, outside of the main flow.
+     * Notes nested in another note are part of the outer one.
+     */
+    fn start_note(&mut self) {
+        if self.note.is_some() {
+            return;
+        }
+        for id in self.element_ids.iter() {
+            self.chapter.anchors.remove(id);
+        }
+        self.note = Some(NoteState {
+            depth: self.token_stack.len(),
+            ids: self.element_ids.clone(),
+            main_builder: std::mem::replace(&mut self.builder, RichTextBuilder::new()),
+            main_pos: std::mem::replace(&mut self.current_pos, 0),
+        });
+        self.pending_break = false;
+    }
+
+    fn finish_note(&mut self) {
+        if let Some(note) = self.note.take() {
+            let builder = std::mem::replace(&mut self.builder, note.main_builder);
+            let has_text = self.current_pos > 0;
+            self.current_pos = note.main_pos;
+            self.pending_break = false;
+
+            if has_text {
+                let text = builder.build();
+                for id in note.ids {
+                    self.chapter.notes.insert(id, text.clone());
+                }
+            }
+        }
+    }
+
+    /**
+     * Close the elements implicitly ended by the start of a new element,
+     * like an open list item when the next one starts.
+     *
+     * @param name: the name of the element being started
+     */
+    fn close_implied_elements(&mut self, name: &str) {
+        match name {
+            "li" => self.close_open_element(&["li"], &["ul", "ol", "table", "td", "th"]),
+            "dt" | "dd" => self.close_open_element(&["dt", "dd"], &["dl", "table", "td", "th"]),
+            "tr" => self.close_open_element(&["tr"], &["table"]),
+            "td" | "th" => self.close_open_element(&["td", "th"], &["tr", "table"]),
+            _ => {}
+        }
+        if CLOSING_PARAGRAPH.contains(&name) {
+            self.close_open_element(&["p"], &PARAGRAPH_SCOPE);
+        }
+    }
+
+    /**
+     * Close the innermost open element with one of the given names, together with
+     * the elements opened inside it, unless one of the boundaries is found first.
+     */
+    fn close_open_element(&mut self, names: &[&str], boundaries: &[&str]) {
+        let position = self.token_stack.iter().rposition(|element| {
+            let name = element.info.name.as_str();
+            names.contains(&name) || boundaries.contains(&name)
+        });
+        if let Some(position) = position {
+            if names.contains(&self.token_stack[position].info.name.as_str()) {
+                self.pop_elements(position);
+            }
+        }
+    }
+
+    /**
+     * Handle a closing tag: the matching element is closed, together with the
+     * elements opened inside it and left open. Closing tags without a matching
+     * open element are ignored.
+     *
+     * @param name: the name of the closing tag
+     */
+    fn close_element(&mut self, name: &str) {
+        if let Some(position) = self
+            .token_stack
+            .iter()
+            .rposition(|element| element.info.name == name)
+        {
+            self.pop_elements(position);
+        }
+    }
+
+    /**
+     * Close the open elements until only the given number of them is left.
+     */
+    fn pop_elements(&mut self, len: usize) {
+        while self.token_stack.len() > len {
+            self.pop_element();
+        }
+    }
+
+    /**
+     * Close the last opened element.
+     */
+    fn pop_element(&mut self) {
+        let element = match self.token_stack.pop() {
+            Some(element) => element,
+            None => return,
+        };
+
+        if let Some(note) = &self.note {
+            if self.token_stack.len() < note.depth {
+                self.finish_note();
+                return;
+            }
+        }
+
+        match &element.tag {
+            HtmlTag::Image(src) => self.push_image(src),
+            HtmlTag::LineBreak => self.push_line_break(),
+            HtmlTag::HorizontalRule => self.push_rule(),
+            HtmlTag::TableCell | HtmlTag::TableHeaderCell => self.end_table_cell(),
+            HtmlTag::TableRow => self.end_table_row(),
+            HtmlTag::Table => self.end_table(element.style.block),
+            tag if tag.is_block() => self.end_block(element.style.block),
+            _ => {}
+        }
+        match element.tag {
+            HtmlTag::UnorderedList | HtmlTag::OrderedList => {
+                self.lists.pop();
+            }
+            HtmlTag::ListItem => self.pending_marker = None,
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let element = match self.token_stack.last() {
+            Some(element) => element,
+            None => return,
+        };
+        if element.tag == HtmlTag::Style {
+            if let Some(stylesheet) = &mut self.stylesheet {
+                stylesheet.extend(Stylesheet::parse(text));
+            }
+            return;
+        }
+        if element.tag.should_tag_be_written() {
+            return;
+        }
+
+        let preserve_white_space = element.style.preserve_white_space;
+        let t = if preserve_white_space {
+            // as in browsers, a line break right after the opening tag is not shown
+            match text.strip_prefix('\n') {
+                Some(stripped) if self.current_pos == 0 => stripped.to_string(),
+                _ => text.to_string(),
+            }
+        } else {
+            self.collapse_white_space(text)
+        };
+        if t.is_empty() {
+            return;
+        }
+        let t = match &self.options.hyphenation {
+            Some(dictionary) if !preserve_white_space => hyphenate(&t, dictionary),
+            _ => t,
+        };
+
+        if self.pending_break && self.current_pos > 0 {
+            self.builder.push("\n");
+            self.current_pos += 1;
+        }
+        self.pending_break = false;
+        if self.note.is_some() || self.table.is_some() {
+            // the text of notes and cells is shown as it is, so the marker is part of it
+            if let Some(marker) = self.pending_marker.take() {
+                let marker = format!("{} ", marker);
+                self.builder.push(&marker);
+                self.current_pos += marker.len();
+            }
+        } else if self.current_pos == 0 {
+            self.start_indent();
+        }
+
+        let start = self.current_pos;
+        self.current_pos += t.len();
+        if !self.anchors_only {
+            self.builder.push(&t);
+            self.style_range(start..self.current_pos);
+        }
+    }
+
+    /**
+     * Collapse the runs of white space of a text into single spaces, as in browsers.
+     * White space at the start of a line is dropped, white space at the end of
+     * the text is kept aside until more text follows on the same line.
+     */
+    fn collapse_white_space(&mut self, text: &str) -> String {
+        let is_space = |c: char| c.is_ascii_whitespace();
+        let words = text
+            .split_ascii_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let space_before = self.pending_space || text.starts_with(is_space);
+        if words.is_empty() {
+            self.pending_space = space_before;
+            return words;
+        }
+        self.pending_space = text.ends_with(is_space);
+        if space_before && self.current_pos > 0 && !self.pending_break {
+            format!(" {}", words)
+        } else {
+            words
+        }
+    }
+
+    /**
+     * Apply the style of the innermost element, and the link containing it if any,
+     * to a range of the text being built.
+     */
+    fn style_range(&mut self, range: std::ops::Range<usize>) {
+        let element = match self.token_stack.last() {
+            Some(element) => element,
+            None => return,
+        };
+        let link = self
+            .token_stack
+            .iter()
+            .rev()
+            .find_map(|element| element.tag.link_command());
+
+        let mut attrs = self.builder.add_attributes_for_range(range);
+        element.style.apply(&mut attrs);
+        if let Some(link) = link {
+            attrs.link(link);
+        }
+    }
+
+    /**
+     * Keep the first line indent of the paragraph starting, given to its block style
+     * when it ends, so the text layout indents it without changing the text.
+     * The indent chosen by the reader replaces the one of the paragraphs.
+     */
+    fn start_indent(&mut self) {
+        let style = self.parent_style();
+        let paragraph = self
+            .token_stack
+            .iter()
+            .rev()
+            .find(|element| element.tag.is_block())
+            .filter(|element| element.tag == HtmlTag::Paragraph);
+        let (text_indent, font_size) = match (paragraph, self.options.first_line_indent) {
+            (Some(paragraph), Some(indent)) => (
+                indent * paragraph.style.font_size,
+                paragraph.style.font_size,
+            ),
+            _ => (style.text_indent, style.font_size),
+        };
+        self.text_indent = text_indent.clamp(0., MAX_TEXT_INDENT * font_size);
+    }
+
+    /**
+     * The layout of the innermost paragraph being built.
+     */
+    fn current_block_style(&self) -> BlockStyle {
+        self.token_stack
+            .iter()
+            .rev()
+            .find(|element| element.tag.is_block())
+            .map(|element| element.style.block.clone())
+            .unwrap_or_default()
+    }
+
+    /**
+     * Push the text built so far as a new Renderable, if it is not empty.
+     *
+     * @param block_style: the layout of the paragraph
+     */
+    fn end_block(&mut self, block_style: BlockStyle) {
+        if self.current_pos == 0 {
+            return;
+        }
+        if self.note.is_some() || self.table.is_some() {
+            self.pending_break = true;
+            return;
+        }
+        let text = std::mem::replace(&mut self.builder, RichTextBuilder::new()).build();
+        let block_style = BlockStyle {
+            text_indent: std::mem::take(&mut self.text_indent),
+            ..block_style
+        };
+        let renderable = match self.pending_marker.take() {
+            Some(marker) => Renderable::ListItem(ArcStr::from(marker), text, block_style),
+            None => Renderable::Text(text, block_style),
+        };
+        self.chapter.renderables.push_back(renderable);
+        self.current_pos = 0;
+    }
+
+    fn push_line_break(&mut self) {
+        self.pending_space = false;
+        if self.current_pos > 0 {
+            self.builder.push("\n");
+            self.current_pos += 1;
+        }
+    }
+
+    fn push_rule(&mut self) {
+        if self.note.is_none() && self.table.is_none() {
+            self.chapter.renderables.push_back(Renderable::Rule);
+        }
+    }
+
+    /**
+     * Set the marker of a new list item: its number in ordered lists,
+     * a bullet depending on the nesting level otherwise.
+     */
+    fn start_list_item(&mut self) {
+        let depth = self.lists.len().max(1) - 1;
+        let marker = match self.lists.last_mut() {
+            Some(Some(number)) => {
+                *number += 1;
+                format!("{}.", *number - 1)
+            }
+            _ => BULLETS[depth % BULLETS.len()].to_string(),
+        };
+        self.pending_marker = Some(marker);
+    }
+
+    fn start_table(&mut self) {
+        match &mut self.table {
+            Some(table) => table.nested += 1,
+            // notes are shown as a single text, tables in them are flattened
+            None if self.note.is_none() => self.table = Some(TableState::default()),
+            None => {}
+        }
+    }
+
+    fn start_table_cell(&mut self) {
+        // text outside of the cells, like a caption, gets a cell of its own
+        if matches!(&self.table, Some(table) if table.nested == 0) && self.current_pos > 0 {
+            self.end_table_cell();
+        }
+    }
+
+    fn end_table_cell(&mut self) {
+        match &mut self.table {
+            Some(table) if table.nested == 0 => {
+                let builder = std::mem::replace(&mut self.builder, RichTextBuilder::new());
+                table.row.push_back(builder.build());
+                self.current_pos = 0;
+                self.pending_break = false;
+                self.pending_marker = None;
+            }
+            _ => self.end_block(BlockStyle::default()),
+        }
+    }
+
+    fn end_table_row(&mut self) {
+        match &mut self.table {
+            Some(table) if table.nested == 0 => {
+                if !table.row.is_empty() {
+                    let row = std::mem::take(&mut table.row);
+                    table.rows.push_back(row);
+                }
+            }
+            _ => self.end_block(BlockStyle::default()),
+        }
+    }
+
+    /**
+     * Push the table built so far as a new Renderable.
+     * Nested tables only end a line of the cell containing them.
+     */
+    fn end_table(&mut self, block_style: BlockStyle) {
+        match &mut self.table {
+            Some(table) if table.nested > 0 => {
+                table.nested -= 1;
+                self.end_block(block_style);
+            }
+            Some(_) => {
+                if self.current_pos > 0 {
+                    self.end_table_cell();
+                }
+                self.end_table_row();
+                if let Some(table) = self.table.take() {
+                    if !table.rows.is_empty() {
+                        self.chapter
+                            .renderables
+                            .push_back(Renderable::Table(table.rows, block_style));
+                    }
+                }
+            }
+            None => self.end_block(block_style),
+        }
+    }
+
+    /**
+     * Push an image in the tree of renderables.
+     * The text preceding the image is pushed first, so the order of the chapter is kept.
+     * Images inside notes and tables are not shown.
+     */
+    fn push_image(&mut self, src: &str) {
+        if src.is_empty() || self.note.is_some() || self.table.is_some() {
+            return;
+        }
+        self.end_block(self.current_block_style());
+        self.chapter
+            .renderables
+            .push_back(Renderable::Image(ArcStr::from(src)));
+    }
+
+    fn finish(mut self) -> Chapter {
+        // elements left open at the end of the chapter are closed as usual
+        self.pop_elements(0);
+        self.finish_note();
+        self.end_block(BlockStyle::default());
+        self.chapter
+    }
 }
 
 /**
  * Generate a vector of Renderable objects from a string of HTML.
+ * The styles of the publisher are not applied.
  * 
  * @param text: the HTML string
  * @param font_size: the font size to use for the text
@@ -111,97 +968,288 @@ pub enum Renderable {
  * @return a vector of Renderable objects
  */
 pub fn generate_renderable_tree(text: &str, font_size: f64) -> Vector<Renderable> {
-    let mut renderables: Vector<Renderable> = Vector::new();
-    let mut current_pos = 0;
-    let mut builder = druid::text::RichTextBuilder::new();
-    let mut token_stack: Vec<(usize, HtmlTag)> = Vec::new();
+    parse_chapter(text, font_size, None, &TextOptions::default()).renderables
+}
 
-    for tok_result in xmlparser::Tokenizer::from(text) {
-        if tok_result.is_err() {
-            // handle error
-            continue;
-        }
-        let token = tok_result.unwrap();
+/**
+ * Parse a string of HTML into a Chapter, containing the Renderable objects
+ * together with the anchors and the notes defined in the HTML.
+ *
+ * @param text: the HTML string
+ * @param font_size: the font size to use for the text
+ * @param stylesheet: the stylesheets linked by the chapter, None to ignore
+ *                    the styles of the publisher (including style elements and attributes)
+ * @param options: the settings of the reader changing the text
+ *
+ * @return the parsed Chapter
+ */
+pub fn parse_chapter(
+    text: &str,
+    font_size: f64,
+    stylesheet: Option<&Stylesheet>,
+    options: &TextOptions,
+) -> Chapter {
+    build_chapter(text, ChapterBuilder::new(font_size, stylesheet, options))
+}
+
+/**
+ * Find the anchors of a chapter, mapped to the index of the Renderable containing
+ * them as parse_chapter does, without building the text of the chapter.
+ * The styles of the publisher do not change how the chapter is split in
+ * renderables, so they are not applied.
+ *
+ * @param text: the HTML string
+ *
+ * @return the anchors of the chapter
+ */
+pub fn chapter_anchors(text: &str) -> HashMap<String, usize> {
+    let mut chapter_builder = ChapterBuilder::new(DEFAULT_FONT_SIZE, None, &TextOptions::default());
+    chapter_builder.anchors_only = true;
+    build_chapter(text, chapter_builder).anchors
+}
+
+fn build_chapter(text: &str, mut chapter_builder: ChapterBuilder) -> Chapter {
+    for token in Tokenizer::from(text) {
         match token {
-            xmlparser::Token::ElementStart {
-                prefix: _,
+            Token::ElementStart { local, .. } => chapter_builder.start_element(&local),
+            Token::Attribute {
+                prefix,
                 local,
-                span: _,
-            } => {
-                token_stack.push((current_pos, HtmlTag::from(local.as_str())));
-            }
-            xmlparser::Token::ElementEnd { end, span: _ } => {
-                match end {
-                    xmlparser::ElementEnd::Open => {
-                        continue;
-                    }
+                value,
+            } => chapter_builder.attribute(&prefix, &local, &value),
+            Token::ElementEnd(ElementEnd::Open) => chapter_builder.compute_style(),
+            Token::ElementEnd(ElementEnd::Close(name)) => chapter_builder.close_element(&name),
+            Token::ElementEnd(ElementEnd::Empty) => {
+                chapter_builder.compute_style();
+                chapter_builder.pop_element();
+            }
+            Token::Text(text) => chapter_builder.text(&text),
+        }
+    }
 
-                    xmlparser::ElementEnd::Close(_, closed_token) => {
-                        let (pos, tk) = token_stack.pop().expect("No token on stack");
-                        if tk != HtmlTag::from(closed_token.as_str()) {
-                            println!(
-                                "ERROR: closing tag {:?} does not match started tag {:?}",
-                                closed_token.as_str(),
-                                tk
-                            );
-                            continue;
-                        }
-
-                        tk.add_attribute_for_token(
-                            builder.add_attributes_for_range(pos..current_pos),
-                            font_size,
-                        );
-
-
-                        if matches!(
-                            tk,
-                            HtmlTag::Paragraph
-                                | HtmlTag::Header(_)
-                                | HtmlTag::Image(_)
-                                | HtmlTag::Link(_)
-                        ) {
-                            if current_pos == 0 {
-                                continue;
-                            }
-                            let text = builder.build();
-                            renderables.push_back(Renderable::Text(text));
-
-                            builder = druid::text::RichTextBuilder::new();
-                            current_pos = 0;
-                        }
-                    }
-                    xmlparser::ElementEnd::Empty => {
-                        token_stack.pop().expect("No token on stack");
+    chapter_builder.finish()
+}
+
+/**
+ * Find the stylesheets linked by a chapter, with <link rel="stylesheet" href="...">.
+ *
+ * @param text: the HTML string
+ *
+ * @return the hrefs of the stylesheets, in the order they are linked
+ */
+pub fn stylesheet_links(text: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    // rel and href of the link element being parsed
+    let mut current_link: Option<(bool, Option<String>)> = None;
+
+    for token in Tokenizer::from(text) {
+        match token {
+            Token::ElementStart { local, .. } => {
+                current_link = (local == "link").then(|| (false, None));
+            }
+            Token::Attribute { local, value, .. } => {
+                if let Some((is_stylesheet, href)) = &mut current_link {
+                    match local.as_str() {
+                        "rel" => *is_stylesheet = value.to_ascii_lowercase().contains("stylesheet"),
+                        "href" => *href = Some(value),
+                        _ => {}
                     }
                 }
             }
+            Token::ElementEnd(_) => {
+                if let Some((true, Some(href))) = current_link.take() {
+                    links.push(href);
+                }
+            }
+            _ => {}
+        }
+    }
+    links
+}
 
-            xmlparser::Token::Text { text } => {
-                let (_, inner_tag) = token_stack.last().unwrap();
+/**
+ * Collect the content of the <style> elements of a chapter.
+ *
+ * @param text: the HTML string
+ */
+pub fn style_elements(text: &str) -> String {
+    let mut css = String::new();
+    let mut in_style = false;
+    for token in Tokenizer::from(text) {
+        match token {
+            Token::ElementStart { local, .. } => in_style = local == "style",
+            Token::Text(text) if in_style => css.push_str(&text),
+            Token::ElementEnd(ElementEnd::Close(_)) => in_style = false,
+            _ => {}
+        }
+    }
+    css
+}
 
-                if inner_tag.should_tag_be_written() || text.trim().is_empty() {
-                    continue;
-                } else {
-                    let t = text.as_str().replace("\n", "");
-                    current_pos = current_pos + t.len();
+/**
+ * Find the documents of the book which can list its print pages:
+ * the navigation document (EPUB 3) and the NCX (EPUB 2).
+ *
+ * @param opf: the package document of the book
+ *
+ * @return the hrefs of the documents, relative to the package document,
+ *         with the navigation document first
+ */
+pub fn navigation_documents(opf: &str) -> Vec<String> {
+    let mut nav = Vec::new();
+    let mut ncx = Vec::new();
+    // href, media type and properties of the manifest item being parsed
+    let mut current_item: Option<(String, String, String)> = None;
 
-                    builder.push(&t);
+    for token in Tokenizer::from(opf) {
+        match token {
+            Token::ElementStart { local, .. } => {
+                current_item = (local == "item").then(Default::default);
+            }
+            Token::Attribute { local, value, .. } => {
+                if let Some((href, media_type, properties)) = &mut current_item {
+                    match local.as_str() {
+                        "href" => *href = value,
+                        "media-type" => *media_type = value,
+                        "properties" => *properties = value,
+                        _ => {}
+                    }
                 }
             }
-            xmlparser::Token::Attribute {
-                prefix: _,
-                local: _,
-                value: _,
-                span: _,
-            } => {
-                // TODO: Handle Images
+            Token::ElementEnd(_) => {
+                if let Some((href, media_type, properties)) = current_item.take() {
+                    if properties.split_whitespace().any(|p| p == "nav") {
+                        nav.push(href);
+                    } else if media_type == "application/x-dtbncx+xml" {
+                        ncx.push(href);
+                    }
+                }
             }
+            _ => {}
+        }
+    }
+    nav.extend(ncx);
+    nav
+}
 
-            _ => continue,
+/**
+ * Find the unique identifier of the book, the dc:identifier
+ * referenced by the unique-identifier attribute of the package.
+ *
+ * @param opf: the package document of the book
+ */
+pub fn unique_identifier(opf: &str) -> Option<String> {
+    let mut unique_id = None;
+    // id and value of each identifier
+    let mut identifiers: Vec<(Option<String>, String)> = Vec::new();
+    let mut element = String::new();
+
+    for token in Tokenizer::from(opf) {
+        match token {
+            Token::ElementStart { local, .. } => {
+                if local == "identifier" {
+                    identifiers.push((None, String::new()));
+                }
+                element = local;
+            }
+            Token::Attribute { local, value, .. } => match (element.as_str(), local.as_str()) {
+                ("package", "unique-identifier") => unique_id = Some(value),
+                ("identifier", "id") => {
+                    if let Some((id, _)) = identifiers.last_mut() {
+                        *id = Some(value);
+                    }
+                }
+                _ => {}
+            },
+            Token::Text(text) if element == "identifier" => {
+                if let Some((_, identifier)) = identifiers.last_mut() {
+                    identifier.push_str(&text);
+                }
+            }
+            Token::ElementEnd(ElementEnd::Close(_)) => element.clear(),
+            _ => {}
         }
     }
+    identifiers
+        .into_iter()
+        .find(|(id, _)| id.is_some() && *id == unique_id)
+        .map(|(_, identifier)| identifier.trim().to_string())
+}
+
+/**
+ * Parse the print pages listed by a navigation document, in the
+ * <nav epub:type="page-list"> element, or by a NCX, in the <pageList> element.
+ *
+ * @param text: the navigation document or the NCX
+ *
+ * @return the label of each page with its href, relative to the document
+ */
+pub fn page_list(text: &str) -> Vec<(String, String)> {
+    let mut pages = Vec::new();
+    // for each open nav element, whether it is the page list
+    let mut navs: Vec<bool> = Vec::new();
+    let mut is_page_list_nav = false;
+    let mut in_ncx_page_list = false;
+    let mut element = String::new();
+    // label and href of the page being parsed
+    let mut current_page: Option<(String, Option<String>)> = None;
 
-    renderables
+    for token in Tokenizer::from(text) {
+        let in_page_list = in_ncx_page_list || navs.contains(&true);
+        match token {
+            Token::ElementStart { local, .. } => {
+                match local.as_str() {
+                    "nav" => is_page_list_nav = false,
+                    "pagelist" => in_ncx_page_list = true,
+                    "a" | "pagetarget" if in_page_list => {
+                        current_page = Some((String::new(), None));
+                    }
+                    _ => {}
+                }
+                element = local;
+            }
+            Token::Attribute {
+                prefix,
+                local,
+                value,
+            } => match (element.as_str(), prefix.as_str(), local.as_str()) {
+                ("nav", "epub", "type") | ("nav", "", "role") => {
+                    is_page_list_nav |= value
+                        .split_whitespace()
+                        .any(|t| t == "page-list" || t == "doc-pagelist");
+                }
+                ("a", "", "href") | ("content", "", "src") => {
+                    if let Some((_, href)) = &mut current_page {
+                        *href = Some(value);
+                    }
+                }
+                _ => {}
+            },
+            Token::ElementEnd(ElementEnd::Open) if element == "nav" => {
+                navs.push(is_page_list_nav);
+            }
+            Token::ElementEnd(ElementEnd::Close(name)) => match name.as_str() {
+                "nav" => {
+                    navs.pop();
+                }
+                "pagelist" => in_ncx_page_list = false,
+                "a" | "pagetarget" => {
+                    if let Some((label, Some(href))) = current_page.take() {
+                        pages.push((label.split_whitespace().collect::<Vec<_>>().join(" "), href));
+                    }
+                }
+                _ => {}
+            },
+            Token::Text(text) => {
+                if let Some((label, _)) = &mut current_page {
+                    label.push_str(&text);
+                    label.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    pages
 }
 
 
@@ -233,7 +1281,7 @@ mod tests {
         let text = renderables[0].clone();
         // get Text from Renderable
         let text = match text {
-            Renderable::Text(t) => t,
+            Renderable::Text(t, _) => t,
             _ => unreachable!("Renderable is not a Text"),
         };
 
@@ -267,13 +1315,15 @@ mod tests {
 
         let renderables = generate_renderable_tree(html, 12.0);
 
-        assert_eq!(renderables.len(), 0);
+        // the stray closing tag is ignored
+        assert_eq!(renderables.len(), 1);
+        assert_eq!(renderables[0].plain_text(), "Test with wrong tag");
     }
 
     #[test]
     fn test_generate_renderable_tree_without_root() {
         // Here we test if the parser can handle html without a root tag
-        // We expect that we get a single renderable (first one encountered)
+        // We expect that we get a renderable for each element
         let html = "
         <h1>Dummy text</h1>
         <p>Lorem ipsum</p>
@@ -281,7 +1331,297 @@ mod tests {
 
         let renderables = generate_renderable_tree(html, 12.0);
 
-        assert_eq!(renderables.len(), 1);
+        assert_eq!(renderables.len(), 3);
+    }
+
+    #[test]
+    fn test_generate_renderable_tree_with_images() {
+        let html = r#"
+        <html>
+            <body>
+                <p>Before the image</p>
+                <img src="../Images/cover.jpg" alt="cover"/>
+                <svg><image width="10" height="10" xlink:href="../Images/map.png"></image></svg>
+                <p>After the image</p>
+            </body>
+        </html>"#;
+
+        let renderables = generate_renderable_tree(html, 12.0);
+
+        assert_eq!(renderables.len(), 4);
+        match &renderables[1] {
+            Renderable::Image(src) => assert_eq!(src.as_ref(), "../Images/cover.jpg"),
+            _ => unreachable!("Renderable is not an Image"),
+        }
+        match &renderables[2] {
+            Renderable::Image(src) => assert_eq!(src.as_ref(), "../Images/map.png"),
+            _ => unreachable!("Renderable is not an Image"),
+        }
+    }
+
+    #[test]
+    fn test_generate_renderable_tree_with_anchors() {
+        let html = r#"
+        <html>
+            <body>
+                <h1 id="title">Dummy text</h1>
+                <p>Lorem ipsum <a href="notes.xhtml#note1">1</a> dolor</p>
+                <p id="second"><b>Dolor sit amet </b></p>
+                <p><a name="legacy"/>Consectetur</p>
+            </body>
+        </html>"#;
+
+        let chapter = parse_chapter(html, 12.0, None, &TextOptions::default());
+
+        // links are inline, so the paragraph is not split
+        assert_eq!(chapter.renderables.len(), 4);
+        assert_eq!(chapter.anchors.get("title"), Some(&0));
+        assert_eq!(chapter.anchors.get("second"), Some(&2));
+        assert_eq!(chapter.anchors.get("legacy"), Some(&3));
+        assert_eq!(chapter.anchors.get("note1"), None);
+        assert_eq!(chapter_anchors(html), chapter.anchors);
+    }
+
+    #[test]
+    fn test_parse_chapter_with_notes() {
+        let html = r##"
+        <html>
+            <body>
+                <p>Lorem ipsum<a epub:type="noteref" href="#fn1">1</a> dolor</p>
+                <aside id="fn1"><p>First note</p><p>Second line</p></aside>
+                <div epub:type="footnote" id="fn2"><p>Other note</p></div>
+                <p>Sit amet</p>
+            </body>
+        </html>"##;
+
+        let chapter = parse_chapter(html, 12.0, None, &TextOptions::default());
+
+        // notes are not part of the main flow
+        assert_eq!(chapter.renderables.len(), 2);
+        assert_eq!(chapter.anchors.get("fn1"), None);
+        assert_eq!(
+            chapter.notes.get("fn1").map(|n| n.as_str().to_string()),
+            Some("First note\nSecond line".to_string())
+        );
+        assert_eq!(
+            chapter.notes.get("fn2").map(|n| n.as_str().to_string()),
+            Some("Other note".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_chapter_with_only_notes() {
+        let html = r#"
+        <html>
+            <body>
+                <aside id="fn1"><p>First note</p></aside>
+                <aside id="fn2"><p>Second note</p></aside>
+            </body>
+        </html>"#;
+
+        let chapter = parse_chapter(html, 12.0, None, &TextOptions::default());
+
+        // the chapter has no text to show, only the notes
+        assert!(chapter.renderables.is_empty());
+        assert!(chapter.anchors.is_empty());
+        assert_eq!(chapter.notes.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_chapter_with_styles() {
+        let html = r#"
+        <html>
+            <head>
+                <link rel="stylesheet" type="text/css" href="../Styles/book.css"/>
+                <style>p.indented { text-indent: 1em; text-align: center }</style>
+            </head>
+            <body>
+                <p class="indented">Lorem ipsum</p>
+                <p style="margin-left: 2em">Dolor sit amet</p>
+            </body>
+        </html>"#;
+        let stylesheet = Stylesheet::parse("p { margin-top: 1em }");
+
+        let chapter = parse_chapter(html, 12.0, Some(&stylesheet), &TextOptions::default());
+
+        assert_eq!(
+            stylesheet_links(html),
+            vec!["../Styles/book.css".to_string()]
+        );
+        assert_eq!(chapter.renderables.len(), 2);
+        match &chapter.renderables[0] {
+            Renderable::Text(text, block) => {
+                assert_eq!(text.as_str(), "Lorem ipsum");
+                assert_eq!(block.text_indent, 12.);
+                assert_eq!(block.text_align, crate::css::TextAlign::Center);
+                assert_eq!(block.margin_top, 12.);
+            }
+            _ => unreachable!("Renderable is not a Text"),
+        }
+        match &chapter.renderables[1] {
+            Renderable::Text(_, block) => assert_eq!(block.margin_left, 24.),
+            _ => unreachable!("Renderable is not a Text"),
+        }
+
+        // without publisher styles, style elements and attributes are ignored too
+        let chapter = parse_chapter(html, 12.0, None, &TextOptions::default());
+        match &chapter.renderables[0] {
+            Renderable::Text(text, block) => {
+                assert_eq!(text.as_str(), "Lorem ipsum");
+                assert_eq!(*block, BlockStyle::default());
+            }
+            _ => unreachable!("Renderable is not a Text"),
+        }
+
+        // the indent chosen by the reader replaces the one of the book
+        let options = TextOptions {
+            first_line_indent: Some(1.5),
+            ..TextOptions::default()
+        };
+        let chapter = parse_chapter(html, 12.0, None, &options);
+        match &chapter.renderables[0] {
+            Renderable::Text(text, block) => {
+                assert_eq!(text.as_str(), "Lorem ipsum");
+                assert_eq!(block.text_indent, 18.);
+            }
+            _ => unreachable!("Renderable is not a Text"),
+        }
+    }
+
+    #[test]
+    fn test_parse_chapter_with_blocks() {
+        let html = r#"
+        <html>
+            <body>
+                <div>Before the list<ol start="3"><li>Third</li><li><p>Fourth</p></li></ol></div>
+                <ul><li>Bullet<br/>second line</li></ul>
+                <blockquote>Quoted</blockquote>
+                <hr/>
+                <pre>
+let x = 1;
+  let y = 2;</pre>
+                <table>
+                    <tr><th>Name</th><th>Value</th></tr>
+                    <tr><td>a</td><td><p>1</p><p>2</p></td></tr>
+                </table>
+            </body>
+        </html>"#;
+
+        let renderables = generate_renderable_tree(html, 10.0);
+
+        assert_eq!(renderables.len(), 8);
+        assert_eq!(renderables[0].plain_text(), "Before the list");
+        match (&renderables[1], &renderables[2], &renderables[3]) {
+            (
+                Renderable::ListItem(third, _, _),
+                Renderable::ListItem(fourth, _, block),
+                Renderable::ListItem(bullet, text, _),
+            ) => {
+                assert_eq!(third.as_ref(), "3.");
+                assert_eq!(fourth.as_ref(), "4.");
+                assert_eq!(block.margin_left, 20.);
+                assert_eq!(bullet.as_ref(), BULLETS[0]);
+                assert_eq!(text.as_str(), "Bullet\nsecond line");
+            }
+            _ => unreachable!("Renderables are not list items"),
+        }
+        match &renderables[4] {
+            Renderable::Text(_, block) => assert_eq!(block.margin_left, 20.),
+            _ => unreachable!("Renderable is not a Text"),
+        }
+        assert!(matches!(renderables[5], Renderable::Rule));
+        assert_eq!(renderables[6].plain_text(), "let x = 1;\n  let y = 2;");
+        match &renderables[7] {
+            Renderable::Table(rows, _) => assert_eq!(rows.len(), 2),
+            _ => unreachable!("Renderable is not a Table"),
+        }
+        assert_eq!(renderables[7].plain_text(), "Name\tValue\na\t1\n2");
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_malformed_chapters() {
+        let texts = |html: &str| -> Vec<String> {
+            generate_renderable_tree(html, 12.0)
+                .iter()
+                .map(|renderable| renderable.plain_text())
+                .collect()
+        };
+
+        assert_eq!(
+            texts(include_str!(
+                "../examples/assets/malformed/unclosed_tags.xhtml"
+            )),
+            vec!["Prima\u{A0}riga", "Seconda riga \u{E8} qui", "uno", "due"]
+        );
+
+        let stray_tags = include_str!("../examples/assets/malformed/stray_tags.xhtml");
+        assert_eq!(
+            texts(stray_tags),
+            vec![
+                "Chapter\u{A0}2",
+                "One\ntwo",
+                "",
+                "Last & final &unknown; line"
+            ]
+        );
+        match &generate_renderable_tree(stray_tags, 12.0)[2] {
+            Renderable::Image(src) => assert_eq!(src.as_ref(), "../Images/fig&1.jpg"),
+            _ => unreachable!("Renderable is not an Image"),
+        }
+
+        assert_eq!(
+            texts(include_str!("../examples/assets/malformed/no_root.html")),
+            vec![
+                "Title",
+                "Text with bold and italic mixed tags",
+                "a\tb\nc\td",
+                "After\u{2026}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_page_list() {
+        let opf = r#"<?xml version="1.0"?>
+        <package><manifest>
+            <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+            <item id="nav" href="Text/nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+            <item id="c1" href="Text/c1.xhtml" media-type="application/xhtml+xml"/>
+        </manifest></package>"#;
+        assert_eq!(navigation_documents(opf), vec!["Text/nav.xhtml", "toc.ncx"]);
+
+        let opf = r#"<package unique-identifier="uid"><metadata>
+            <dc:identifier id="isbn">9780000000000</dc:identifier>
+            <dc:identifier id="uid"> urn:uuid:0f6fc4d1-1a3d-4b6e-9e1e-2bb4d2f6d0a1 </dc:identifier>
+        </metadata></package>"#;
+        assert_eq!(
+            unique_identifier(opf).as_deref(),
+            Some("urn:uuid:0f6fc4d1-1a3d-4b6e-9e1e-2bb4d2f6d0a1")
+        );
+
+        let nav = r##"<html><body>
+            <nav epub:type="toc"><ol><li><a href="c1.xhtml">Chapter 1</a></li></ol></nav>
+            <nav epub:type="page-list" hidden=""><ol>
+                <li><a href="c1.xhtml#page_iv">iv</a></li>
+                <li><a href="c1.xhtml#page_1"> 1 </a></li>
+            </ol></nav>
+        </body></html>"##;
+        assert_eq!(
+            page_list(nav),
+            vec![
+                ("iv".to_string(), "c1.xhtml#page_iv".to_string()),
+                ("1".to_string(), "c1.xhtml#page_1".to_string())
+            ]
+        );
+
+        let ncx = r##"<ncx><navMap><navPoint><navLabel><text>Chapter 1</text></navLabel>
+            <content src="Text/c1.xhtml"/></navPoint></navMap>
+            <pageList><pageTarget id="p12" type="normal" value="12">
+                <navLabel><text>12</text></navLabel><content src="Text/c1.xhtml#p12"/>
+            </pageTarget></pageList></ncx>"##;
+        assert_eq!(
+            page_list(ncx),
+            vec![("12".to_string(), "Text/c1.xhtml#p12".to_string())]
+        );
+    }
+}
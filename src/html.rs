@@ -0,0 +1,653 @@
+use std::collections::VecDeque;
+
+/**
+ * Module for tokenizing HTML, as found in the chapters of real books.
+ *
+ * Unlike an XML tokenizer, it never stops on errors: comments, doctypes and
+ * processing instructions are skipped, void elements (like <br>) are closed
+ * even without "/>", attributes can be unquoted or without value, and a '<'
+ * not starting a tag is kept as text. Character references, both numeric and
+ * named (like &nbsp;), are decoded in texts and attribute values.
+ *
+ * Names of elements and attributes are lowercased; the building of the
+ * tree, with the implicit closing of the elements, is left to the dom module.
+ */
+
+// elements which never have a content, nor a closing tag
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+// elements whose content is text, even if it contains '<'
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+// the named character references of HTML 4: the Latin-1 characters,
+// the typographic symbols and the Greek letters
+const NAMED_ENTITIES: [(&str, char); 253] = [
+    ("quot", '"'),
+    ("amp", '&'),
+    ("apos", '\''),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("nbsp", '\u{A0}'),
+    ("iexcl", '¡'),
+    ("cent", '¢'),
+    ("pound", '£'),
+    ("curren", '¤'),
+    ("yen", '¥'),
+    ("brvbar", '¦'),
+    ("sect", '§'),
+    ("uml", '¨'),
+    ("copy", '©'),
+    ("ordf", 'ª'),
+    ("laquo", '«'),
+    ("not", '¬'),
+    ("shy", '\u{AD}'),
+    ("reg", '®'),
+    ("macr", '¯'),
+    ("deg", '°'),
+    ("plusmn", '±'),
+    ("sup2", '²'),
+    ("sup3", '³'),
+    ("acute", '´'),
+    ("micro", 'µ'),
+    ("para", '¶'),
+    ("middot", '·'),
+    ("cedil", '¸'),
+    ("sup1", '¹'),
+    ("ordm", 'º'),
+    ("raquo", '»'),
+    ("frac14", '¼'),
+    ("frac12", '½'),
+    ("frac34", '¾'),
+    ("iquest", '¿'),
+    ("Agrave", 'À'),
+    ("Aacute", 'Á'),
+    ("Acirc", 'Â'),
+    ("Atilde", 'Ã'),
+    ("Auml", 'Ä'),
+    ("Aring", 'Å'),
+    ("AElig", 'Æ'),
+    ("Ccedil", 'Ç'),
+    ("Egrave", 'È'),
+    ("Eacute", 'É'),
+    ("Ecirc", 'Ê'),
+    ("Euml", 'Ë'),
+    ("Igrave", 'Ì'),
+    ("Iacute", 'Í'),
+    ("Icirc", 'Î'),
+    ("Iuml", 'Ï'),
+    ("ETH", 'Ð'),
+    ("Ntilde", 'Ñ'),
+    ("Ograve", 'Ò'),
+    ("Oacute", 'Ó'),
+    ("Ocirc", 'Ô'),
+    ("Otilde", 'Õ'),
+    ("Ouml", 'Ö'),
+    ("times", '×'),
+    ("Oslash", 'Ø'),
+    ("Ugrave", 'Ù'),
+    ("Uacute", 'Ú'),
+    ("Ucirc", 'Û'),
+    ("Uuml", 'Ü'),
+    ("Yacute", 'Ý'),
+    ("THORN", 'Þ'),
+    ("szlig", 'ß'),
+    ("agrave", 'à'),
+    ("aacute", 'á'),
+    ("acirc", 'â'),
+    ("atilde", 'ã'),
+    ("auml", 'ä'),
+    ("aring", 'å'),
+    ("aelig", 'æ'),
+    ("ccedil", 'ç'),
+    ("egrave", 'è'),
+    ("eacute", 'é'),
+    ("ecirc", 'ê'),
+    ("euml", 'ë'),
+    ("igrave", 'ì'),
+    ("iacute", 'í'),
+    ("icirc", 'î'),
+    ("iuml", 'ï'),
+    ("eth", 'ð'),
+    ("ntilde", 'ñ'),
+    ("ograve", 'ò'),
+    ("oacute", 'ó'),
+    ("ocirc", 'ô'),
+    ("otilde", 'õ'),
+    ("ouml", 'ö'),
+    ("divide", '÷'),
+    ("oslash", 'ø'),
+    ("ugrave", 'ù'),
+    ("uacute", 'ú'),
+    ("ucirc", 'û'),
+    ("uuml", 'ü'),
+    ("yacute", 'ý'),
+    ("thorn", 'þ'),
+    ("yuml", 'ÿ'),
+    ("OElig", 'Œ'),
+    ("oelig", 'œ'),
+    ("Scaron", 'Š'),
+    ("scaron", 'š'),
+    ("Yuml", 'Ÿ'),
+    ("fnof", 'ƒ'),
+    ("circ", 'ˆ'),
+    ("tilde", '˜'),
+    ("Alpha", 'Α'),
+    ("Beta", 'Β'),
+    ("Gamma", 'Γ'),
+    ("Delta", 'Δ'),
+    ("Epsilon", 'Ε'),
+    ("Zeta", 'Ζ'),
+    ("Eta", 'Η'),
+    ("Theta", 'Θ'),
+    ("Iota", 'Ι'),
+    ("Kappa", 'Κ'),
+    ("Lambda", 'Λ'),
+    ("Mu", 'Μ'),
+    ("Nu", 'Ν'),
+    ("Xi", 'Ξ'),
+    ("Omicron", 'Ο'),
+    ("Pi", 'Π'),
+    ("Rho", 'Ρ'),
+    ("Sigma", 'Σ'),
+    ("Tau", 'Τ'),
+    ("Upsilon", 'Υ'),
+    ("Phi", 'Φ'),
+    ("Chi", 'Χ'),
+    ("Psi", 'Ψ'),
+    ("Omega", 'Ω'),
+    ("alpha", 'α'),
+    ("beta", 'β'),
+    ("gamma", 'γ'),
+    ("delta", 'δ'),
+    ("epsilon", 'ε'),
+    ("zeta", 'ζ'),
+    ("eta", 'η'),
+    ("theta", 'θ'),
+    ("iota", 'ι'),
+    ("kappa", 'κ'),
+    ("lambda", 'λ'),
+    ("mu", 'μ'),
+    ("nu", 'ν'),
+    ("xi", 'ξ'),
+    ("omicron", 'ο'),
+    ("pi", 'π'),
+    ("rho", 'ρ'),
+    ("sigmaf", 'ς'),
+    ("sigma", 'σ'),
+    ("tau", 'τ'),
+    ("upsilon", 'υ'),
+    ("phi", 'φ'),
+    ("chi", 'χ'),
+    ("psi", 'ψ'),
+    ("omega", 'ω'),
+    ("thetasym", 'ϑ'),
+    ("upsih", 'ϒ'),
+    ("piv", 'ϖ'),
+    ("ensp", '\u{2002}'),
+    ("emsp", '\u{2003}'),
+    ("thinsp", '\u{2009}'),
+    ("zwnj", '\u{200C}'),
+    ("zwj", '\u{200D}'),
+    ("lrm", '\u{200E}'),
+    ("rlm", '\u{200F}'),
+    ("ndash", '–'),
+    ("mdash", '—'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("sbquo", '‚'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("bdquo", '„'),
+    ("dagger", '†'),
+    ("Dagger", '‡'),
+    ("bull", '•'),
+    ("hellip", '…'),
+    ("permil", '‰'),
+    ("prime", '′'),
+    ("Prime", '″'),
+    ("lsaquo", '‹'),
+    ("rsaquo", '›'),
+    ("oline", '‾'),
+    ("frasl", '⁄'),
+    ("euro", '€'),
+    ("image", 'ℑ'),
+    ("weierp", '℘'),
+    ("real", 'ℜ'),
+    ("trade", '™'),
+    ("alefsym", 'ℵ'),
+    ("larr", '←'),
+    ("uarr", '↑'),
+    ("rarr", '→'),
+    ("darr", '↓'),
+    ("harr", '↔'),
+    ("crarr", '↵'),
+    ("lArr", '⇐'),
+    ("uArr", '⇑'),
+    ("rArr", '⇒'),
+    ("dArr", '⇓'),
+    ("hArr", '⇔'),
+    ("forall", '∀'),
+    ("part", '∂'),
+    ("exist", '∃'),
+    ("empty", '∅'),
+    ("nabla", '∇'),
+    ("isin", '∈'),
+    ("notin", '∉'),
+    ("ni", '∋'),
+    ("prod", '∏'),
+    ("sum", '∑'),
+    ("minus", '−'),
+    ("lowast", '∗'),
+    ("radic", '√'),
+    ("prop", '∝'),
+    ("infin", '∞'),
+    ("ang", '∠'),
+    ("and", '∧'),
+    ("or", '∨'),
+    ("cap", '∩'),
+    ("cup", '∪'),
+    ("int", '∫'),
+    ("there4", '∴'),
+    ("sim", '∼'),
+    ("cong", '≅'),
+    ("asymp", '≈'),
+    ("ne", '≠'),
+    ("equiv", '≡'),
+    ("le", '≤'),
+    ("ge", '≥'),
+    ("sub", '⊂'),
+    ("sup", '⊃'),
+    ("nsub", '⊄'),
+    ("sube", '⊆'),
+    ("supe", '⊇'),
+    ("oplus", '⊕'),
+    ("otimes", '⊗'),
+    ("perp", '⊥'),
+    ("sdot", '⋅'),
+    ("lceil", '⌈'),
+    ("rceil", '⌉'),
+    ("lfloor", '⌊'),
+    ("rfloor", '⌋'),
+    ("lang", '〈'),
+    ("rang", '〉'),
+    ("loz", '◊'),
+    ("spades", '♠'),
+    ("clubs", '♣'),
+    ("hearts", '♥'),
+    ("diams", '♦'),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementEnd {
+    // end of a start tag, the element has a content
+    Open,
+    // closing tag, with the name of the element
+    Close(String),
+    // end of an empty element, or of a void element
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    ElementStart {
+        prefix: String,
+        local: String,
+    },
+    Attribute {
+        prefix: String,
+        local: String,
+        value: String,
+    },
+    ElementEnd(ElementEnd),
+    Text(String),
+}
+
+/**
+ * Iterator over the tokens of an HTML string.
+ */
+pub struct Tokenizer<'a> {
+    input: &'a str,
+    pos: usize,
+    tokens: VecDeque<Token>,
+    // raw text element whose content is expected next
+    raw_text_element: Option<String>,
+}
+
+impl<'a> From<&'a str> for Tokenizer<'a> {
+    fn from(input: &'a str) -> Self {
+        Tokenizer {
+            input,
+            pos: 0,
+            tokens: VecDeque::new(),
+            raw_text_element: None,
+        }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        // a text at the end of the queue can still be continued by the next piece of input
+        while self.pos < self.input.len()
+            && (self.tokens.is_empty() || matches!(self.tokens.back(), Some(Token::Text(_))))
+        {
+            self.read_next();
+        }
+        self.tokens.pop_front()
+    }
+}
+
+impl<'a> Tokenizer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    /**
+     * Read the next piece of the input, queueing the tokens found in it.
+     */
+    fn read_next(&mut self) {
+        if let Some(element) = self.raw_text_element.take() {
+            self.read_raw_text(&element);
+            return;
+        }
+
+        let rest = self.rest();
+        if rest.starts_with("<!--") {
+            self.skip_after(4, "-->");
+        } else if rest.starts_with("<![CDATA[") {
+            let content = &rest[9..];
+            let end = content.find("]]>").unwrap_or(content.len());
+            self.push_text(content[..end].to_string());
+            self.pos = (self.pos + 9 + end + 3).min(self.input.len());
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            self.skip_after(2, ">");
+        } else if rest.starts_with("</") {
+            self.read_closing_tag();
+        } else if rest.len() > 1
+            && rest.starts_with('<')
+            && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            self.read_start_tag();
+        } else {
+            self.read_text();
+        }
+    }
+
+    fn skip_after(&mut self, start: usize, end: &str) {
+        self.pos = match self.rest()[start..].find(end) {
+            Some(i) => self.pos + start + i + end.len(),
+            None => self.input.len(),
+        };
+    }
+
+    fn push_text(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        // adjacent texts (like a '<' not starting a tag) are a single token
+        if let Some(Token::Text(previous)) = self.tokens.back_mut() {
+            previous.push_str(&text);
+        } else {
+            self.tokens.push_back(Token::Text(text));
+        }
+    }
+
+    fn read_text(&mut self) {
+        let rest = self.rest();
+        // the first char is kept even if it is a '<' which does not start a tag
+        let first_len = rest.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+        let end = rest[first_len..]
+            .find('<')
+            .map(|i| i + first_len)
+            .unwrap_or(rest.len());
+        self.pos += end;
+        self.push_text(decode_entities(&rest[..end]));
+    }
+
+    fn read_raw_text(&mut self, element: &str) {
+        let rest = self.rest();
+        let closing_tag = format!("</{}", element);
+        let end = rest
+            .to_ascii_lowercase()
+            .find(&closing_tag)
+            .unwrap_or(rest.len());
+        self.pos += end;
+        self.push_text(rest[..end].to_string());
+    }
+
+    fn read_closing_tag(&mut self) {
+        let rest = &self.rest()[2..];
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        let (_, local) = split_name(&rest[..name_len]);
+        self.skip_after(2, ">");
+        if !local.is_empty() {
+            self.tokens
+                .push_back(Token::ElementEnd(ElementEnd::Close(local)));
+        }
+    }
+
+    fn read_start_tag(&mut self) {
+        let input = self.input;
+        let mut pos = self.pos + 1;
+        let name_end = input[pos..]
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .map(|i| pos + i)
+            .unwrap_or(input.len());
+        let (prefix, local) = split_name(&input[pos..name_end]);
+        pos = name_end;
+
+        let mut tokens = vec![Token::ElementStart {
+            prefix,
+            local: local.clone(),
+        }];
+        let end = loop {
+            pos += input[pos..].len() - input[pos..].trim_start().len();
+            let rest = &input[pos..];
+            if rest.is_empty() {
+                // the tag is not terminated: it is dropped
+                self.pos = input.len();
+                return;
+            } else if rest.starts_with("/>") {
+                pos += 2;
+                break ElementEnd::Empty;
+            } else if rest.starts_with('>') {
+                pos += 1;
+                break ElementEnd::Open;
+            } else if rest.starts_with('/') {
+                pos += 1;
+                continue;
+            }
+
+            let name_len = rest
+                .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+                .unwrap_or(rest.len())
+                .max(1);
+            let (attribute_prefix, attribute_local) = split_name(&rest[..name_len]);
+            pos += name_len;
+            pos += input[pos..].len() - input[pos..].trim_start().len();
+
+            let mut value = String::new();
+            if input[pos..].starts_with('=') {
+                pos += 1;
+                pos += input[pos..].len() - input[pos..].trim_start().len();
+                let rest = &input[pos..];
+                let (raw_value, len) = match rest.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = rest[1..].find(quote).map(|i| i + 1).unwrap_or(rest.len());
+                        (&rest[1..end], (end + 1).min(rest.len()))
+                    }
+                    _ => {
+                        let end = rest
+                            .find(|c: char| c.is_whitespace() || c == '>')
+                            .unwrap_or(rest.len());
+                        (&rest[..end], end)
+                    }
+                };
+                value = decode_entities(raw_value);
+                pos += len;
+            }
+            tokens.push(Token::Attribute {
+                prefix: attribute_prefix,
+                local: attribute_local,
+                value,
+            });
+        };
+
+        let end = if VOID_ELEMENTS.contains(&local.as_str()) {
+            ElementEnd::Empty
+        } else {
+            end
+        };
+        if end == ElementEnd::Open && RAW_TEXT_ELEMENTS.contains(&local.as_str()) {
+            self.raw_text_element = Some(local);
+        }
+        tokens.push(Token::ElementEnd(end));
+
+        self.pos = pos;
+        self.tokens.extend(tokens);
+    }
+}
+
+/**
+ * Split a qualified name, like "epub:type", in its lowercased prefix and local name.
+ */
+fn split_name(name: &str) -> (String, String) {
+    let name = name.to_ascii_lowercase();
+    match name.split_once(':') {
+        Some((prefix, local)) => (prefix.to_string(), local.to_string()),
+        None => (String::new(), name),
+    }
+}
+
+/**
+ * Decode the character references of a text.
+ * Unknown references are kept as they are.
+ *
+ * @param text: the text to decode
+ *
+ * @return the decoded text
+ */
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 32)
+            .and_then(|end| {
+                let reference = &rest[1..end + 1];
+                let c = match reference.strip_prefix('#') {
+                    Some(number) => {
+                        let code = match number.strip_prefix(|c: char| c == 'x' || c == 'X') {
+                            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                            None => number.parse().ok(),
+                        };
+                        code.and_then(char::from_u32)
+                    }
+                    None => NAMED_ENTITIES
+                        .iter()
+                        .find(|(name, _)| *name == reference)
+                        .map(|(_, c)| *c),
+                };
+                c.map(|c| (c, end + 2))
+            });
+
+        match decoded {
+            Some((c, len)) => {
+                result.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(local: &str) -> Token {
+        Token::ElementStart {
+            prefix: String::new(),
+            local: local.to_string(),
+        }
+    }
+
+    fn attribute(prefix: &str, local: &str, value: &str) -> Token {
+        Token::Attribute {
+            prefix: prefix.to_string(),
+            local: local.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tokenize_sloppy_html() {
+        let html = "<!DOCTYPE html><!-- comment --><P Class=intro epub:type=\"x\" hidden>a<br>b &lt; c</p>";
+        let tokens: Vec<Token> = Tokenizer::from(html).collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                start("p"),
+                attribute("", "class", "intro"),
+                attribute("epub", "type", "x"),
+                attribute("", "hidden", ""),
+                Token::ElementEnd(ElementEnd::Open),
+                Token::Text("a".to_string()),
+                start("br"),
+                Token::ElementEnd(ElementEnd::Empty),
+                Token::Text("b < c".to_string()),
+                Token::ElementEnd(ElementEnd::Close("p".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_raw_text_and_unterminated_tags() {
+        let html = "<style>p > a { color: red }</style>1 < 2<p class=\"a";
+        let tokens: Vec<Token> = Tokenizer::from(html).collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                start("style"),
+                Token::ElementEnd(ElementEnd::Open),
+                Token::Text("p > a { color: red }".to_string()),
+                Token::ElementEnd(ElementEnd::Close("style".to_string())),
+                Token::Text("1 < 2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("a&nbsp;b &amp; &#233;&#x20AC; &unknown; & c"),
+            "a\u{A0}b & é€ &unknown; & c"
+        );
+        assert_eq!(
+            decode_entities("&Auml;rger &Oslash;l &AElig;"),
+            "Ärger Øl Æ"
+        );
+        assert_eq!(decode_entities("x&minus;y&prime;"), "x−y′");
+    }
+}
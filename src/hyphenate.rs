@@ -0,0 +1,113 @@
+use hyphenation::{Hyphenator, Language, Load, Standard};
+
+/**
+ * Module for the hyphenation of the text of a book.
+ *
+ * Soft hyphens are inserted in the words, where they can be broken: the text
+ * layout shows a hyphen only when a line is broken there. Soft hyphens are not
+ * part of the text of the book, so they are not counted in the positions.
+ */
+
+pub const SOFT_HYPHEN: char = '\u{AD}';
+
+// shorter words are never broken
+const MIN_WORD_CHARS: usize = 5;
+
+// languages written without the region in the books, and the patterns used for them
+const DEFAULT_PATTERNS: [(&str, &str); 6] = [
+    ("en", "en-us"),
+    ("de", "de-1996"),
+    ("el", "el-monoton"),
+    ("sr", "sr-cyrl"),
+    ("mn", "mn-cyrl"),
+    ("la", "la-x-classic"),
+];
+
+/**
+ * Load the hyphenation patterns of a language.
+ *
+ * @param language: the language of the book, as written in its metadata, like "en-GB" or "it"
+ *
+ * @return None if the language is not known
+ */
+pub fn dictionary(language: &str) -> Option<Standard> {
+    let code = language.trim().to_lowercase().replace('_', "-");
+    let primary = code.split('-').next().unwrap_or_default();
+    let language = Language::try_from_code(&code)
+        .or_else(|| Language::try_from_code(primary))
+        .or_else(|| {
+            DEFAULT_PATTERNS
+                .iter()
+                .find(|(prefix, _)| *prefix == primary)
+                .and_then(|(_, patterns)| Language::try_from_code(patterns))
+        })?;
+    // the patterns are embedded in the binary, they can only be missing if the
+    // language is not among the ones embedded: the words are then not hyphenated
+    Standard::from_embedded(language).ok()
+}
+
+/**
+ * Insert soft hyphens where the words of a text can be broken.
+ */
+pub fn hyphenate(text: &str, dictionary: &Standard) -> String {
+    let mut hyphenated = String::with_capacity(text.len());
+    let mut word_start = None;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (c.is_alphabetic(), word_start) {
+            (true, None) => word_start = Some(i),
+            (false, Some(start)) => {
+                hyphenated.push_str(&hyphenate_word(&text[start..i], dictionary));
+                word_start = None;
+            }
+            _ => {}
+        }
+        if word_start.is_none() && i < text.len() {
+            hyphenated.push(c);
+        }
+    }
+    hyphenated
+}
+
+fn hyphenate_word(word: &str, dictionary: &Standard) -> String {
+    if word.chars().count() < MIN_WORD_CHARS {
+        return word.to_string();
+    }
+    let breaks = dictionary.hyphenate(word).breaks;
+    let mut hyphenated = String::with_capacity(word.len() + breaks.len() * 2);
+    let mut start = 0;
+    for end in breaks {
+        hyphenated.push_str(&word[start..end]);
+        hyphenated.push(SOFT_HYPHEN);
+        start = end;
+    }
+    hyphenated.push_str(&word[start..]);
+    hyphenated
+}
+
+/**
+ * Remove the soft hyphens of a text, giving back the text of the book.
+ */
+pub fn remove_soft_hyphens(text: &str) -> String {
+    text.replace(SOFT_HYPHEN, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyphenate() {
+        assert!(dictionary("xx").is_none());
+        let dictionary = dictionary("en").unwrap();
+
+        let text = "The hyphenation of anything, in a book.";
+        let hyphenated = hyphenate(text, &dictionary);
+        assert!(hyphenated.contains(SOFT_HYPHEN));
+        assert!(hyphenated.starts_with("The hy"));
+        assert!(hyphenated.ends_with(" in a book."));
+        assert_eq!(remove_soft_hyphens(&hyphenated), text);
+    }
+}
@@ -6,10 +6,10 @@ use crate::core::style;
 
 use data::appstate::Delegate;
 use data::home::HomePageData;
-use data::AppState;
+use data::{config::SettingsRanges, AppConfig, AppState};
 use druid::{
-    widget::{Controller, Flex, List, Scroll, ViewSwitcher},
-    AppLauncher, Color, Data, Env, Event, EventCtx, WidgetExt, WindowDesc,
+    widget::{Controller, Either, Flex, List, Scroll, ViewSwitcher},
+    AppLauncher, Data, Env, Event, EventCtx, LensExt, WidgetExt, WindowDesc, WindowState,
 };
 
 use druid::Widget;
@@ -18,30 +18,47 @@ mod core;
 mod data;
 mod widgets;
 
+mod css;
 mod dom;
+mod html;
+mod hyphenate;
 mod ocr;
+mod search;
 
 use widgets::{
     epub_page::{epub_controller::EpubPageController, sidebar::Sidebar},
+    home_page::library_search::{library_search_input, library_search_results},
     recent_item::RecentWidget,
+    settings_page::settings_page,
     RoundButton,
 };
 
+const KEYMAP_EDITOR_SIZE: druid::Size = druid::Size::new(500., 400.);
+
 #[derive(Data, PartialEq, Clone, Copy, Debug)]
 pub enum PageType {
     Home,
     Reader,
+    Settings,
 }
 
 fn main() {
     let data = AppState::new();
-    let window = WindowDesc::new(navigator())
+    let geometry = &data.config.window;
+    let mut window = WindowDesc::new(navigator())
         .title(APP_NAME)
-        .window_size((1000.0, 800.0));
+        .window_size(geometry.size());
+    if let Some(position) = geometry.position() {
+        window = window.set_position(position);
+    }
+    if geometry.maximized {
+        window = window.set_window_state(WindowState::Maximized);
+    }
 
     AppLauncher::with_window(window)
         .log_to_console()
         .delegate(Delegate)
+        .configure_env(|env, data: &AppState| style::apply_theme(env, &data.config.theme))
         .launch(data)
         .unwrap();
 }
@@ -50,12 +67,23 @@ fn main() {
 pub fn navigator() -> impl Widget<AppState> {
     ViewSwitcher::new(
         |data: &AppState, _env| data.active_page,
-        move |active_page, _, _| match active_page {
+        move |active_page, data: &AppState, _| match active_page {
             PageType::Home => home_page().lens(AppState::home_page_data).boxed(),
-            PageType::Reader => read_ebook().boxed(),
+            PageType::Reader => read_ebook(&data.config.ranges.checked()).boxed(),
+            PageType::Settings => settings_page(&data.config.ranges.checked())
+                .lens(AppState::config)
+                .boxed(),
         },
     )
     .controller(MainController {})
+    // the colors of the reader follow the theme of the book
+    .env_scope(|env, data: &AppState| {
+        let theme = match data.active_page {
+            PageType::Reader => &data.epub_data.epub_settings.theme,
+            PageType::Home | PageType::Settings => &data.config.theme,
+        };
+        style::apply_theme(env, theme)
+    })
 }
 
 pub fn home_page() -> impl Widget<HomePageData> {
@@ -65,9 +93,16 @@ pub fn home_page() -> impl Widget<HomePageData> {
     .vertical()
     .lens(HomePageData::recents);
 
+    // the results of a search in the library replace the recents
+    let list = Either::new(
+        |data: &HomePageData, _| data.search_results.is_empty(),
+        list,
+        Scroll::new(library_search_results()).vertical(),
+    );
+
     let title = druid::widget::Label::new(APP_NAME)
         .with_text_size(26.0)
-        .with_text_color(Color::WHITE)
+        .with_text_color(style::CHROME_TEXT)
         .center();
 
     let open_epub = RoundButton::new(druid_material_icons::normal::content::ADD_CIRCLE)
@@ -80,30 +115,76 @@ pub fn home_page() -> impl Widget<HomePageData> {
         })
         .with_radius(40.);
 
+    let open_settings = RoundButton::new(druid_material_icons::normal::action::SETTINGS)
+        .with_click_handler(|ctx, _, _env| {
+            ctx.submit_command(
+                INTERNAL_COMMAND.with(InternalUICommand::UINavigate(PageType::Settings)),
+            );
+        });
+
     let layout = Flex::column()
         .with_child(
             Flex::row()
                 .main_axis_alignment(druid::widget::MainAxisAlignment::SpaceBetween)
                 .with_child(title)
-                .with_child(open_epub)
+                .with_child(
+                    Flex::row()
+                        .with_child(open_settings)
+                        .with_default_spacer()
+                        .with_child(open_epub),
+                )
                 .expand_width(),
         )
+        .with_child(error_banner())
+        .with_child(library_search_input().padding(5.0))
         .with_flex_child(list, 1.);
-    druid::widget::Container::new(layout)
-        .background(style::get_color_unchecked(style::PRIMARY_DARK))
+    druid::widget::Container::new(layout).background(style::CHROME)
+}
+
+/**
+ * The errors met while reading or writing the files of the application,
+ * shown in the home page and in the reader until the user dismisses them.
+ */
+fn error_banner() -> impl Widget<HomePageData> {
+    let message = druid::widget::Label::dynamic(|error: &String, _| error.clone())
+        .with_line_break_mode(druid::widget::LineBreaking::WordWrap)
+        .with_text_color(druid::Color::WHITE);
+    let dismiss = druid::widget::Button::new("Dismiss").on_click(|_, error: &mut String, _| {
+        error.clear();
+    });
+    let banner = Flex::row()
+        .with_flex_child(message.expand_width(), 1.)
+        .with_default_spacer()
+        .with_child(dismiss)
+        .padding(8.)
+        .background(druid::Color::rgb8(0xB0, 0x30, 0x30))
+        .rounded(5.)
+        .padding(5.)
+        .lens(HomePageData::error);
+
+    Either::new(
+        |data: &HomePageData, _| data.error.is_empty(),
+        Flex::row(),
+        banner,
+    )
 }
 
-pub fn read_ebook() -> impl Widget<AppState> {
+pub fn read_ebook(ranges: &SettingsRanges) -> impl Widget<AppState> {
     let ret = widgets::epub_page::textcontainer::TextContainer::new().lens(AppState::epub_data);
 
     let flex = Flex::row()
-        .with_child(Sidebar::new().lens(AppState::epub_data))
+        .with_child(Sidebar::new(ranges).lens(AppState::epub_data))
         .with_flex_child(ret.expand(), 1.);
-    flex.controller(EpubPageController {})
+    Flex::column()
+        .with_child(error_banner().lens(AppState::home_page_data))
+        .with_flex_child(flex, 1.)
+        .controller(EpubPageController {})
 }
 
 /**
  * Main Controller handles the navigation between UI pages.
+ * It also saves the configuration of the application when the settings page is left,
+ * and with the geometry of the window when it is closed.
  */
 struct MainController;
 impl Controller<AppState, ViewSwitcher<AppState, PageType>> for MainController {
@@ -120,15 +201,35 @@ impl Controller<AppState, ViewSwitcher<AppState, PageType>> for MainController {
                 if let Some(internal) = cmd.get(INTERNAL_COMMAND) {
                     match internal {
                         InternalUICommand::UINavigate(page) => {
+                            if data.active_page == PageType::Settings && *page != PageType::Settings
+                            {
+                                data.save_config();
+                            }
                             data.active_page = *page;
                             ctx.request_layout();
                         }
+                        InternalUICommand::OpenKeymapEditor => {
+                            ctx.new_sub_window(
+                                druid::WindowConfig::default()
+                                    .window_size(KEYMAP_EDITOR_SIZE)
+                                    .set_level(druid::WindowLevel::AppWindow),
+                                widgets::build_keymap_ui()
+                                    .lens(AppState::config.then(AppConfig::keymap)),
+                                data.clone(),
+                                env.clone(),
+                            );
+                            ctx.set_handled();
+                        }
                         _ => {}
                     }
 
                     ctx.request_layout();
                 }
             }
+            Event::WindowCloseRequested => {
+                data.config.window.update(ctx.window());
+                data.save_config();
+            }
             _ => {}
         }
         child.event(ctx, event, data, env);
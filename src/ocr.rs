@@ -121,15 +121,23 @@ pub fn search_with_ocr_input(full_text: Vec<Vec<String>>, image_path: &str) -> P
   * @param image_1: the first image of the book
   * @param image_2: the second image of the book
   * @param current_position: the current position of the epub reader
+  * @param print_page: the print page of the current position, if the book lists its pages
   *
-  * @return the estimated position of the current page of epub reader in the the physical book
+  * @return the estimated position of the current page of epub reader in the the physical book,
+  *         or the print page when it is known
   */
 pub fn reverse_search_with_ocr_input(
     full_text: Vec<Vec<String>>,
     image_1: &str,
     image_2: &str,
     current_position: &PagePosition,
+    print_page: Option<usize>,
 ) -> usize {
+    // the page list of the book gives the exact page, no estimate is needed
+    if let Some(print_page) = print_page {
+        return print_page;
+    }
+
     let image_1_rec = search_with_ocr_input(full_text.clone(), image_1);
     let image_2_rec = search_with_ocr_input(full_text.clone(), image_2);
     let mut lt = leptess::LepTess::new(None, "eng").unwrap();
@@ -142,7 +150,7 @@ pub fn reverse_search_with_ocr_input(
         return usize::MAX;
     }
     let image_2_text = text_preparation(&lt.get_utf8_text().unwrap());
-    
+
     // count all character in book
     let mut full_count = 0;
     for chapter in &full_text {
@@ -312,12 +320,31 @@ mod tests {
         let image_2 = "examples/assets/pavese_page_197.jpg";
         let current_position = PagePosition::new(7, 14); // page 18
 
-        let result = reverse_search_with_ocr_input(full_text, image_1, image_2, &current_position);
+        let result = reverse_search_with_ocr_input(full_text, image_1, image_2, &current_position, None);
         let expected_range = 8..18;
         assert!(expected_range.contains(&result));
     }
     
 
+    #[test]
+    fn test_reverse_search_with_ocr_input_with_print_page() {
+        let full_text = Vec::new();
+        let image_1 = "examples/assets/pavese_page_81.jpg";
+        let image_2 = "examples/assets/pavese_page_197.jpg";
+        let current_position = PagePosition::new(7, 14);
+
+        let result =
+            reverse_search_with_ocr_input(full_text, image_1, image_2, &current_position, Some(18));
+
+        assert_eq!(result, 18);
+
+        // the images are not read when the print page is known
+        let image = "examples/assets/image_not_existing.jpg";
+        let result =
+            reverse_search_with_ocr_input(Vec::new(), image, image, &current_position, Some(18));
+        assert_eq!(result, 18);
+    }
+
     #[test]
     fn test_reverse_search_with_ocr_input_with_wrong_image_or_non_existing_image() {
         let full_text = Vec::new();
@@ -325,7 +352,7 @@ mod tests {
         let image_2 = "examples/assets/not_an_image.jpg";
         let current_position = PagePosition::new(8, 0);
 
-        let result = reverse_search_with_ocr_input(full_text, image_1, image_2, &current_position);
+        let result = reverse_search_with_ocr_input(full_text, image_1, image_2, &current_position, None);
 
         assert_eq!(result, usize::MAX);
     }
@@ -337,7 +364,7 @@ mod tests {
         let image_2 = "examples/assets/pavese_page_197.jpg";
         let current_position = PagePosition::new(8, 0);
 
-        let result = reverse_search_with_ocr_input(full_text, image_1, image_2, &current_position);
+        let result = reverse_search_with_ocr_input(full_text, image_1, image_2, &current_position, None);
 
         assert_eq!(result, 0);
     }
@@ -0,0 +1,806 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use lazy_static::lazy_static;
+
+use regex::{Regex, RegexBuilder};
+use tantivy::{
+    collector::{Count, DocSetCollector, TopDocs},
+    query::QueryParser,
+    schema::{Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, STORED},
+    tokenizer::{Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, TextAnalyzer},
+    Document, Index, IndexReader, Term,
+};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::data::epub::{settings::EpubSettings, SearchOptions};
+
+/**
+ * Full-text search of a book, with an index saved on disk.
+ * The index of a book is built once, in the background, the first time
+ * the book is opened, and it is found again from the hash of the file.
+ * Each richtext of the book is a document of the index, so a result
+ * points to a richtext like the positions of the book.
+ */
+// inside the data directory of the application
+const INDEX_DIR: &str = ".search_index";
+// the index is built again when its schema changes
+const INDEX_VERSION: u32 = 2;
+const WRITER_MEMORY: usize = 50_000_000;
+
+lazy_static! {
+    // the hashes of the books computed in the session, by path, with the
+    // modification time of the file they were computed from
+    static ref BOOK_HASHES: Mutex<HashMap<PathBuf, (SystemTime, String)>> =
+        Mutex::new(HashMap::new());
+}
+
+// the languages with a stemmer, by their code in the metadata of the books
+const STEMMERS: [(&str, Language); 18] = [
+    ("da", Language::Danish),
+    ("de", Language::German),
+    ("en", Language::English),
+    ("es", Language::Spanish),
+    ("fi", Language::Finnish),
+    ("fr", Language::French),
+    ("hu", Language::Hungarian),
+    ("it", Language::Italian),
+    ("nb", Language::Norwegian),
+    ("nl", Language::Dutch),
+    ("nn", Language::Norwegian),
+    ("no", Language::Norwegian),
+    ("pt", Language::Portuguese),
+    ("ro", Language::Romanian),
+    ("ru", Language::Russian),
+    ("sv", Language::Swedish),
+    ("ta", Language::Tamil),
+    ("tr", Language::Turkish),
+];
+// the words of the books in the other languages are indexed as they are
+const UNSTEMMED_TOKENIZER: &str = "default";
+
+/**
+ * A richtext matching a query.
+ * The ranges are the byte offsets of the matching words in the text.
+ */
+#[derive(Clone, Debug)]
+pub struct SearchHit {
+    pub chapter: usize,
+    pub richtext_number: usize,
+    pub score: f32,
+    pub text: String,
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl SearchHit {
+    /**
+     * Get the matches of the hit, each one joined with the matching words
+     * following it, like the words of a phrase.
+     */
+    pub fn matches(&self) -> Vec<Range<usize>> {
+        let mut matches: Vec<Range<usize>> = Vec::new();
+        for next in &self.ranges {
+            if let Some(range) = matches.last_mut() {
+                let between = self.text.get(range.end..next.start).unwrap_or("x");
+                if !between.chars().any(|c| c.is_alphanumeric()) {
+                    range.end = next.end;
+                    continue;
+                }
+            }
+            matches.push(next.clone());
+        }
+        matches
+    }
+
+    /**
+     * Get the first match of the hit, see matches.
+     */
+    pub fn first_match(&self) -> Range<usize> {
+        self.matches().into_iter().next().unwrap_or(0..0)
+    }
+
+    /**
+     * Get the first match of the hit with the text around it.
+     *
+     * @param context_length: the maximum number of characters kept before and after the match
+     *
+     * @return the snippet, and the byte range of the match in the snippet
+     */
+    pub fn snippet(&self, context_length: usize) -> (String, Range<usize>) {
+        snippet(&self.text, self.first_match(), context_length)
+    }
+}
+
+/**
+ * Convert a byte range of a text to a range of characters, like the
+ * offsets of the positions in the book.
+ */
+pub fn char_range(text: &str, range: Range<usize>) -> Range<usize> {
+    let start = text[..range.start].chars().count();
+    start..start + text[range].chars().count()
+}
+
+/**
+ * Get a match with the text around it, on a single line.
+ * The context is cut at the boundaries of the words, and an ellipsis
+ * marks the text left out.
+ *
+ * @param text: the text containing the match
+ * @param range: the byte range of the match in the text
+ * @param context_length: the maximum number of characters kept before and after the match
+ *
+ * @return the snippet, and the byte range of the match in the snippet
+ */
+pub fn snippet(text: &str, range: Range<usize>, context_length: usize) -> (String, Range<usize>) {
+    let mut start = text[..range.start]
+        .char_indices()
+        .rev()
+        .take(context_length)
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(range.start);
+    // a word cut in half is left out, unless it is the one of the match
+    if start > 0 && !text[..start].ends_with(char::is_whitespace) {
+        if let Some(space) = text[start..range.start].find(char::is_whitespace) {
+            start += space;
+        }
+    }
+    start = range.start - text[start..range.start].trim_start().len();
+
+    let mut end = text[range.end..]
+        .char_indices()
+        .nth(context_length)
+        .map(|(i, _)| range.end + i)
+        .unwrap_or(text.len());
+    if end < text.len() && !text[end..].starts_with(char::is_whitespace) {
+        if let Some(space) = text[range.end..end].rfind(char::is_whitespace) {
+            end = range.end + space;
+        }
+    }
+    end = range.end + text[range.end..end].trim_end().len();
+
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if end < text.len() { "…" } else { "" };
+    // line breaks are one byte long like the spaces replacing them
+    let snippet = format!("{}{}{}", prefix, &text[start..end], suffix)
+        .replace(|c: char| c == '\n' || c == '\r' || c == '\t', " ");
+    let emphasis_start = prefix.len() + range.start - start;
+    (snippet, emphasis_start..emphasis_start + range.len())
+}
+
+/**
+ * The settings of the reader the indexed text depends on, so the results point
+ * to the richtexts and the offsets of the book as it is rendered.
+ * The styles of the publisher change the white space of the text; the hyphenation
+ * and the first line indent do not, since the soft hyphens are not indexed
+ * and the indent is part of the layout of the paragraphs.
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct IndexSettings {
+    pub publisher_styles: bool,
+}
+
+impl IndexSettings {
+    pub fn new(settings: &EpubSettings) -> Self {
+        IndexSettings {
+            publisher_styles: settings.publisher_styles,
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        if self.publisher_styles {
+            "styles"
+        } else {
+            "plain"
+        }
+    }
+}
+
+pub struct BookIndex {
+    index: Index,
+    reader: IndexReader,
+    text: Field,
+    chapter: Field,
+    richtext_number: Field,
+}
+
+/**
+ * Compute the hash of the content of a book (64 bit FNV-1a), which does not change
+ * when the book is moved or renamed, unlike its path.
+ *
+ * @param path: the path of the book
+ */
+pub fn book_hash(path: &Path) -> std::io::Result<String> {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let bytes = std::fs::read(path)?;
+    let hash = bytes.iter().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    });
+    Ok(format!("{:016x}", hash))
+}
+
+/**
+ * Get the hash of a book, computed again only if the file was modified since
+ * the last time, so the books are not read whole at each search.
+ *
+ * @param path: the path of the book
+ */
+fn cached_book_hash(path: &Path) -> std::io::Result<String> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    if let Some((time, hash)) = BOOK_HASHES.lock().unwrap().get(path) {
+        if *time == modified {
+            return Ok(hash.clone());
+        }
+    }
+    let hash = book_hash(path)?;
+    BOOK_HASHES
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), (modified, hash.clone()));
+    Ok(hash)
+}
+
+/**
+ * Get the directory of the index of a book.
+ *
+ * @param data_dir: the data directory of the application
+ * @param hash: the hash of the book, see book_hash
+ * @param settings: the settings the book is rendered with
+ */
+pub fn index_dir(data_dir: &Path, hash: &str, settings: IndexSettings) -> PathBuf {
+    data_dir
+        .join(INDEX_DIR)
+        .join(format!("{}-v{}-{}", hash, INDEX_VERSION, settings.key()))
+}
+
+/**
+ * Get the name of the tokenizer of the text of a book, stemming the words
+ * in the language of the book when it has a stemmer.
+ *
+ * @param language: the language of the book, as written in its metadata, like "en-GB" or "it"
+ */
+fn tokenizer_name(language: Option<&str>) -> String {
+    let code = language.unwrap_or_default().trim().to_lowercase();
+    let code = code
+        .split(|c| c == '-' || c == '_')
+        .next()
+        .unwrap_or_default();
+    STEMMERS
+        .iter()
+        .find(|(stemmer_code, _)| *stemmer_code == code)
+        .map(|(stemmer_code, _)| format!("stem_{}", stemmer_code))
+        .unwrap_or_else(|| UNSTEMMED_TOKENIZER.to_string())
+}
+
+/**
+ * Register the stemming tokenizers in an index, which only knows the
+ * tokenizers of tantivy by itself.
+ */
+fn register_tokenizers(index: &Index) {
+    for (code, language) in STEMMERS {
+        index.tokenizers().register(
+            &format!("stem_{}", code),
+            TextAnalyzer::from(SimpleTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser)
+                .filter(Stemmer::new(language)),
+        );
+    }
+}
+
+fn schema(tokenizer: &str) -> Schema {
+    let mut schema_builder = Schema::builder();
+    // words are stemmed if the language has a stemmer, and their positions
+    // are kept for the phrase queries
+    let indexing = TextFieldIndexing::default()
+        .set_tokenizer(tokenizer)
+        .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+    schema_builder.add_text_field(
+        "text",
+        TextOptions::default()
+            .set_indexing_options(indexing)
+            .set_stored(),
+    );
+    schema_builder.add_u64_field("chapter", STORED);
+    schema_builder.add_u64_field("richtext_number", STORED);
+    schema_builder.build()
+}
+
+impl BookIndex {
+    /**
+     * Open the index saved in a directory.
+     *
+     * @return None if the directory does not contain a complete index
+     */
+    pub fn open(dir: &Path) -> Option<BookIndex> {
+        // the metadata is written by the commit, so the index is complete if it exists
+        if !dir.join("meta.json").exists() {
+            return None;
+        }
+        let index = Index::open_in_dir(dir).ok()?;
+        BookIndex::from_index(index).ok()
+    }
+
+    /**
+     * Build the index of a book in a directory.
+     * The index is written in a temporary directory, renamed when it is complete,
+     * so a build interrupted by the closing of the application is not used.
+     *
+     * @param dir: the directory of the index
+     * @param strings: the text of each richtext of each chapter
+     * @param language: the language of the book, see tokenizer_name
+     */
+    pub fn build(
+        dir: &Path,
+        strings: &[Vec<String>],
+        language: Option<&str>,
+    ) -> tantivy::Result<BookIndex> {
+        let tmp_dir = dir.with_extension("tmp");
+        if tmp_dir.exists() {
+            std::fs::remove_dir_all(&tmp_dir)?;
+        }
+        std::fs::create_dir_all(&tmp_dir)?;
+
+        let schema = schema(&tokenizer_name(language));
+        let text = schema.get_field("text").unwrap();
+        let chapter = schema.get_field("chapter").unwrap();
+        let richtext_number = schema.get_field("richtext_number").unwrap();
+
+        let index = Index::create_in_dir(&tmp_dir, schema)?;
+        register_tokenizers(&index);
+        let mut index_writer = index.writer(WRITER_MEMORY)?;
+        for (i, chapter_strings) in strings.iter().enumerate() {
+            for (j, richtext) in chapter_strings.iter().enumerate() {
+                if richtext.trim().is_empty() {
+                    continue;
+                }
+                let mut doc = Document::default();
+                doc.add_text(text, richtext);
+                doc.add_u64(chapter, i as u64);
+                doc.add_u64(richtext_number, j as u64);
+                index_writer.add_document(doc)?;
+            }
+        }
+        index_writer.commit()?;
+        index_writer.wait_merging_threads()?;
+        drop(index);
+
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        std::fs::rename(&tmp_dir, dir)?;
+        BookIndex::from_index(Index::open_in_dir(dir)?)
+    }
+
+    fn from_index(index: Index) -> tantivy::Result<BookIndex> {
+        register_tokenizers(&index);
+        let schema = index.schema();
+        let field = |name: &str| {
+            schema
+                .get_field(name)
+                .ok_or_else(|| tantivy::TantivyError::SchemaError(format!("missing {}", name)))
+        };
+        let text = field("text")?;
+        let chapter = field("chapter")?;
+        let richtext_number = field("richtext_number")?;
+        let reader = index.reader()?;
+        Ok(BookIndex {
+            index,
+            reader,
+            text,
+            chapter,
+            richtext_number,
+        })
+    }
+
+    /**
+     * Search the book, with the syntax of the tantivy query parser:
+     * all the words must be found, in any form ("walking" finds "walked")
+     * if the language of the book has a stemmer,
+     * and the words between quotes must be found in this order.
+     *
+     * @param query: the text to search
+     * @param offset: the number of best results to skip
+     * @param limit: the maximum number of results
+     *
+     * @return the results, from the best one, and the total number of results
+     */
+    pub fn search(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> tantivy::Result<(Vec<SearchHit>, usize)> {
+        let query = self.parse_query(query)?;
+        let searcher = self.reader.searcher();
+        let (top_docs, count) = searcher.search(
+            &query,
+            &(TopDocs::with_limit(limit).and_offset(offset), Count),
+        )?;
+
+        // the words of the query, as they are indexed
+        let mut terms = BTreeMap::new();
+        query.query_terms(&mut terms);
+        let analyzer = self.index.tokenizer_for_field(self.text)?;
+
+        let mut hits = Vec::new();
+        for (score, address) in top_docs {
+            hits.push(self.hit(&searcher.doc(address)?, &terms, &analyzer, score));
+        }
+        Ok((hits, count))
+    }
+
+    /**
+     * Get all the results of a search, in no particular order and without score.
+     *
+     * @param query: the text to search, see search
+     */
+    pub fn all_hits(&self, query: &str) -> tantivy::Result<Vec<SearchHit>> {
+        let query = self.parse_query(query)?;
+        let searcher = self.reader.searcher();
+        let mut terms = BTreeMap::new();
+        query.query_terms(&mut terms);
+        let analyzer = self.index.tokenizer_for_field(self.text)?;
+
+        let mut hits = Vec::new();
+        for address in searcher.search(&query, &DocSetCollector)? {
+            hits.push(self.hit(&searcher.doc(address)?, &terms, &analyzer, 0.));
+        }
+        Ok(hits)
+    }
+
+    /**
+     * Build the result of a matching document, finding the words of the query in its text.
+     *
+     * @param terms: the words of the query, as they are indexed
+     * @param analyzer: the tokenizer the text was indexed with
+     */
+    fn hit(
+        &self,
+        doc: &Document,
+        terms: &BTreeMap<Term, bool>,
+        analyzer: &TextAnalyzer,
+        score: f32,
+    ) -> SearchHit {
+        let u64_value = |field: Field| {
+            doc.get_first(field)
+                .and_then(|value| value.as_u64())
+                .unwrap_or(0) as usize
+        };
+        let text = doc
+            .get_first(self.text)
+            .and_then(|value| value.as_text())
+            .unwrap_or_default()
+            .to_string();
+
+        let mut ranges = Vec::new();
+        let mut stream = analyzer.token_stream(&text);
+        while stream.advance() {
+            let token = stream.token();
+            if terms.contains_key(&Term::from_field_text(self.text, &token.text)) {
+                ranges.push(token.offset_from..token.offset_to);
+            }
+        }
+
+        SearchHit {
+            chapter: u64_value(self.chapter),
+            richtext_number: u64_value(self.richtext_number),
+            score,
+            text,
+            ranges,
+        }
+    }
+
+    fn parse_query(&self, query: &str) -> tantivy::Result<Box<dyn tantivy::query::Query>> {
+        let mut query_parser = QueryParser::for_index(&self.index, vec![self.text]);
+        query_parser.set_conjunction_by_default();
+        query_parser
+            .parse_query(query)
+            .map_err(|e| tantivy::TantivyError::InvalidArgument(format!("{:?}", e)))
+    }
+}
+
+/**
+ * Open the index of a book, building it if the book was never indexed
+ * with the same settings.
+ * It reads and renders the whole book, so it is called in a background thread.
+ *
+ * @param book_path: the path of the book
+ * @param settings: the settings of the book, its text is indexed as they render it
+ * @param data_dir: the data directory of the application
+ *
+ * @return the directory of the index
+ */
+pub fn open_or_build_index(
+    book_path: &str,
+    settings: &EpubSettings,
+    data_dir: &Path,
+) -> Result<PathBuf, String> {
+    let hash = cached_book_hash(Path::new(book_path)).map_err(|e| e.to_string())?;
+    let dir = index_dir(data_dir, &hash, IndexSettings::new(settings));
+    if BookIndex::open(&dir).is_some() {
+        return Ok(dir);
+    }
+
+    let doc = epub::doc::EpubDoc::new(book_path).map_err(|e| e.to_string())?;
+    let language = doc.mdata("language");
+    let mut epub_data = crate::data::epub::EpubData::new(doc);
+    epub_data.epub_settings = settings.clone();
+    let strings = epub_data.get_only_strings();
+    BookIndex::build(&dir, &strings, language.as_deref()).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/**
+ * Finds the occurrences of a text in the text of the book,
+ * following the options of the search.
+ */
+pub struct TextMatcher {
+    regex: Regex,
+    ignore_accents: bool,
+}
+
+impl TextMatcher {
+    /**
+     * @param query: the text to find, or a regular expression if the option is enabled
+     * @param options: the options of the search
+     *
+     * @return an error if the query is not a valid regular expression
+     */
+    pub fn new(query: &str, options: &SearchOptions) -> Result<TextMatcher, regex::Error> {
+        let query = if options.ignore_accents {
+            fold_accents(query).0
+        } else {
+            query.to_string()
+        };
+        let mut pattern = if options.regex {
+            query
+        } else {
+            regex::escape(&query)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .build()?;
+        Ok(TextMatcher {
+            regex,
+            ignore_accents: options.ignore_accents,
+        })
+    }
+
+    /**
+     * Find the occurrences in a text.
+     *
+     * @return the byte offsets of the occurrences in the text
+     */
+    pub fn find_iter(&self, text: &str) -> Vec<Range<usize>> {
+        if !self.ignore_accents {
+            return self
+                .regex
+                .find_iter(text)
+                .map(|m| m.range())
+                .filter(|range| !range.is_empty())
+                .collect();
+        }
+
+        let (folded, offsets) = fold_accents(text);
+        let original = |i: usize| offsets.get(i).copied().unwrap_or(text.len());
+        self.regex
+            .find_iter(&folded)
+            .map(|m| m.range())
+            .filter(|range| !range.is_empty())
+            .map(|range| original(range.start)..original(range.end))
+            .collect()
+    }
+}
+
+/**
+ * Remove the accents from a text, decomposing its characters (NFD)
+ * and dropping the combining marks, so "perché" becomes "perche".
+ *
+ * @return the text without accents and, for each of its bytes,
+ *         the offset of the character of the text it comes from
+ */
+fn fold_accents(text: &str) -> (String, Vec<usize>) {
+    let mut folded = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len());
+    for (i, c) in text.char_indices() {
+        for decomposed in std::iter::once(c).nfd().filter(|d| !is_combining_mark(*d)) {
+            folded.push(decomposed);
+            offsets.extend(std::iter::repeat(i).take(decomposed.len_utf8()));
+        }
+    }
+    (folded, offsets)
+}
+
+/**
+ * A book of the library matching a search, by its metadata or by its text.
+ */
+pub struct LibraryMatch {
+    pub book_path: String,
+    pub title: String,
+    pub creator: String,
+    // all the words are found in the title, the creator or the publisher
+    pub metadata_match: bool,
+    // the best results in the text, and the number of results
+    pub hits: Vec<SearchHit>,
+    pub total: usize,
+}
+
+/**
+ * Search the metadata and the text of some books.
+ * The books never indexed are indexed first, so it is called in a background thread.
+ *
+ * @param books: the paths of the books, with their settings
+ * @param query: the text to search, see BookIndex::search
+ * @param hits_per_book: the maximum number of results in the text of each book
+ * @param data_dir: the data directory of the application, with the indexes
+ * @param on_match: called with each book matching the search, in the order of books,
+ *                  as soon as it is searched
+ */
+pub fn search_library(
+    books: &[(String, EpubSettings)],
+    query: &str,
+    hits_per_book: usize,
+    data_dir: &Path,
+    mut on_match: impl FnMut(LibraryMatch),
+) {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|word| word.trim_matches('"').to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    for (book_path, settings) in books {
+        let doc = match epub::doc::EpubDoc::new(book_path) {
+            Ok(doc) => doc,
+            Err(_) => continue,
+        };
+        let title = doc.mdata("title").unwrap_or_default();
+        let creator = doc.mdata("creator").unwrap_or_default();
+        let publisher = doc.mdata("publisher").unwrap_or_default();
+        let metadata = format!("{} {} {}", title, creator, publisher).to_lowercase();
+        let metadata_match =
+            !words.is_empty() && words.iter().all(|word| metadata.contains(word.as_str()));
+
+        let (hits, total) = open_or_build_index(book_path, settings, data_dir)
+            .ok()
+            .and_then(|dir| BookIndex::open(&dir))
+            .and_then(|index| index.search(query, 0, hits_per_book).ok())
+            .unwrap_or_default();
+
+        if metadata_match || total > 0 {
+            on_match(LibraryMatch {
+                book_path: book_path.clone(),
+                title,
+                creator,
+                metadata_match,
+                hits,
+                total,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_index() {
+        let dir = std::env::temp_dir().join(format!("book_index_test_{}", std::process::id()));
+        let strings = vec![
+            vec![
+                "The old man walked along the shore.".to_string(),
+                String::new(),
+                "He was walking home, tired and old.".to_string(),
+            ],
+            vec!["An old man and the sea.".to_string()],
+        ];
+        let index = BookIndex::build(&dir, &strings, Some("en-GB")).unwrap();
+        assert!(BookIndex::open(&dir).is_some());
+
+        // stemming: "walks" finds "walked" and "walking"
+        let (hits, count) = index.search("walks", 0, 10).unwrap();
+        assert_eq!(count, 2);
+        let hit = hits
+            .iter()
+            .find(|h| h.chapter == 0 && h.richtext_number == 2);
+        assert_eq!(hit.unwrap().ranges, vec![7..14]);
+
+        // phrase query
+        let (hits, count) = index.search("\"old man\"", 0, 10).unwrap();
+        assert_eq!(count, 2);
+        assert!(hits.iter().all(|h| h.richtext_number != 2));
+
+        // pagination
+        let (hits, count) = index.search("old", 1, 10).unwrap();
+        assert_eq!((hits.len(), count), (2, 3));
+
+        let mut hits = index.all_hits("old").unwrap();
+        hits.sort_by_key(|hit| (hit.chapter, hit.richtext_number));
+        let found: Vec<_> = hits
+            .iter()
+            .map(|h| (h.chapter, h.richtext_number))
+            .collect();
+        assert_eq!(found, vec![(0, 0), (0, 2), (1, 0)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tokenizer_name() {
+        assert_eq!(tokenizer_name(Some("it")), "stem_it");
+        assert_eq!(tokenizer_name(Some(" RU_ru ")), "stem_ru");
+        assert_eq!(tokenizer_name(Some("ja")), UNSTEMMED_TOKENIZER);
+        assert_eq!(tokenizer_name(None), UNSTEMMED_TOKENIZER);
+
+        // the words of an italian book are stemmed in italian
+        let dir = std::env::temp_dir().join(format!("book_index_it_test_{}", std::process::id()));
+        let strings = vec![vec!["Le montagne erano lontane.".to_string()]];
+        let index = BookIndex::build(&dir, &strings, Some("it")).unwrap();
+        let (_, count) = index.search("montagna", 0, 10).unwrap();
+        assert_eq!(count, 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_snippet() {
+        let text = "Perché l'università è così lontana da qui, diceva Zoë.";
+        let start = text.find("così").unwrap();
+        let range = start..start + "così".len();
+        assert_eq!(char_range(text, range.clone()), 22..26);
+
+        // the words cut in half are left out
+        let (snippet, emphasis) = snippet(text, range, 12);
+        assert_eq!(snippet, "…è così lontana da…");
+        assert_eq!(&snippet[emphasis], "così");
+
+        let (snippet, emphasis) = snippet("first line\nsecond", 11..17, 20);
+        assert_eq!(snippet, "first line second");
+        assert_eq!(&snippet[emphasis], "second");
+    }
+
+    #[test]
+    fn test_text_matcher() {
+        let text = "Perché l'università? Universo, PERCHE";
+        let find = |query: &str, options: SearchOptions| {
+            TextMatcher::new(query, &options).unwrap().find_iter(text)
+        };
+
+        assert_eq!(find("perche", SearchOptions::default()), vec![33..39]);
+        let ignore_accents = SearchOptions {
+            ignore_accents: true,
+            ..Default::default()
+        };
+        assert_eq!(find("perche", ignore_accents.clone()), vec![0..7, 33..39]);
+        assert_eq!(find("perché", ignore_accents), vec![0..7, 33..39]);
+
+        let case_sensitive = SearchOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(find("Univers", case_sensitive), vec![23..30]);
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(find("univers", whole_word.clone()), vec![]);
+        assert_eq!(find("universo", whole_word), vec![23..31]);
+        let regex = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert_eq!(find(r"univers\w+", regex.clone()), vec![10..21, 23..31]);
+        assert!(TextMatcher::new("univers(", &regex).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use druid::{
-    ArcStr, BoxConstraints, Color, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx,
-    PaintCtx, RenderContext, Size, TextLayout, UpdateCtx, Widget,
+    text::{Attribute, RichText},
+    BoxConstraints, Color, Data, Env, Event, EventCtx, FontWeight, LayoutCtx, LifeCycle,
+    LifeCycleCtx, PaintCtx, RenderContext, Size, TextLayout, UpdateCtx, Widget,
 };
 
 use crate::{
@@ -14,7 +15,7 @@ const CLICKABLE_LABEL_BACKGROUND : Color = Color::rgba8(190, 190, 190, 60);
  * A clickable label is a widget that can be used to navigate to a specific position in the ebook.
  */
 pub struct ClickableLabel {
-    layout: TextLayout<ArcStr>,
+    layout: TextLayout<RichText>,
 }
 impl ClickableLabel {
     pub fn new() -> Self {
@@ -22,6 +23,17 @@ impl ClickableLabel {
             layout: TextLayout::new(),
         }
     }
+
+    /**
+     * Get the text of the label, with its emphasized part in bold.
+     */
+    fn text(data: &IndexedText) -> RichText {
+        let text = RichText::new(data.key().clone());
+        match data.emphasis() {
+            Some(range) => text.with_attribute(range, Attribute::Weight(FontWeight::BOLD)),
+            None => text,
+        }
+    }
 }
 impl Widget<IndexedText> for ClickableLabel {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut IndexedText, _env: &Env) {
@@ -49,7 +61,7 @@ impl Widget<IndexedText> for ClickableLabel {
     ) {
         match event {
             LifeCycle::WidgetAdded => {
-                self.layout.set_text(data.key().clone());
+                self.layout.set_text(Self::text(data));
                 self.layout.set_text_size(13.);
                 self.layout.rebuild_if_needed(ctx.text(), env);
             }
@@ -68,7 +80,7 @@ impl Widget<IndexedText> for ClickableLabel {
         env: &Env,
     ) {
         if !(old_data.same(data)) {
-            self.layout.set_text(data.key().clone());
+            self.layout.set_text(Self::text(data));
             self.layout.rebuild_if_needed(ctx.text(), env);
             ctx.request_layout();
         }
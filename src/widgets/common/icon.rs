@@ -1,14 +1,16 @@
 pub use druid_material_icons::normal;
 use druid_material_icons::IconPaths;
 
-use druid::{kurbo::Affine, Color};
+use druid::{kurbo::Affine, Color, KeyOrValue};
+
+use crate::core::style;
 
 use druid::widget::prelude::*;
 
 #[derive(Debug, Clone)]
 pub struct Icon {
     paths: IconPaths,
-    color: Color,
+    color: KeyOrValue<Color>,
 }
 /**
  * A widget that displays an icon from the druid_material_icons crate.
@@ -18,12 +20,12 @@ impl Icon {
     pub fn new(paths: IconPaths) -> Self {
         Self {
             paths,
-            color: Color::WHITE,
+            color: style::CHROME_TEXT.into(),
         }
     }
 
-    pub fn set_color(&mut self, color: &Color) {
-        self.color = color.clone();
+    pub fn set_color(&mut self, color: impl Into<KeyOrValue<Color>>) {
+        self.color = color.into();
     }
 }
 
@@ -36,7 +38,8 @@ impl<T: Data> Widget<T> for Icon {
         // Try to preserve aspect ratio if possible, but if not then allow non-uniform scaling.
         bc.constrain_aspect_ratio(self.paths.size.aspect_ratio(), self.paths.size.width)
     }
-    fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, _: &Env) {
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, env: &Env) {
+        let color = self.color.resolve(env);
         let Size { width, height } = ctx.size();
         let Size {
             width: icon_width,
@@ -47,7 +50,7 @@ impl<T: Data> Widget<T> for Icon {
             height * icon_height.recip(),
         ));
         for path in self.paths.paths {
-            ctx.fill(path, &self.color);
+            ctx.fill(path, &color);
         }
     }
 }
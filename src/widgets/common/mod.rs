@@ -8,4 +8,4 @@ pub(crate) mod tooltip;
 pub use clickable_label::ClickableLabel;
 pub use group_button::GroupButton;
 pub use icon::Icon;
-pub use tooltip::TooltipController;
+pub use tooltip::{open_floating_window, TooltipController};
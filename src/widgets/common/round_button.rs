@@ -1,5 +1,5 @@
 use super::icon::Icon;
-use druid::{widget::prelude::*, Color, Cursor, Point, WidgetPod};
+use druid::{widget::prelude::*, Color, Cursor, KeyOrValue, Point, WidgetPod};
 use druid_material_icons::IconPaths;
 
 pub struct RoundButton<T> {
@@ -28,8 +28,8 @@ impl<T: Data> RoundButton<T> {
         self
     }
 
-    pub fn with_color(mut self, color: Color) -> Self {
-        self.icon.widget_mut().set_color(&color);
+    pub fn with_color(mut self, color: impl Into<KeyOrValue<Color>>) -> Self {
+        self.icon.widget_mut().set_color(color);
         self
     }
 
@@ -3,10 +3,37 @@ use std::time::{Duration, Instant};
 use druid::{
     commands::CLOSE_WINDOW,
     widget::{Controller, Label},
-    Env, Event, EventCtx, LifeCycle, LifeCycleCtx, Point, Size, TimerToken, Widget, WindowConfig,
-    WindowId, WindowLevel, WindowSizePolicy,
+    Data, Env, Event, EventCtx, LifeCycle, LifeCycleCtx, Point, Size, TimerToken, Widget,
+    WindowConfig, WindowId, WindowLevel, WindowSizePolicy,
 };
 
+/**
+ * Open a window without titlebar at the given position, sized on its content.
+ * It is used to show tooltips and popovers over the current window.
+ *
+ * @param position_in_window_coordinates: the position of the new window
+ *
+ * @return the id of the new window, used to close it
+ */
+pub fn open_floating_window<U: Data>(
+    ctx: &mut EventCtx,
+    widget: impl Widget<U> + 'static,
+    data: U,
+    position_in_window_coordinates: Point,
+    env: &Env,
+) -> WindowId {
+    ctx.new_sub_window(
+        WindowConfig::default()
+            .show_titlebar(false)
+            .window_size_policy(WindowSizePolicy::Content)
+            .set_level(WindowLevel::Tooltip(ctx.window().clone()))
+            .set_position(position_in_window_coordinates),
+        widget,
+        data,
+        env.clone(),
+    )
+}
+
 /**
  * Tooltip is a ControllerHost that can be used to create a tooltip that can be shown on mouse hover.
  */
@@ -85,15 +112,12 @@ impl<T, W: Widget<T>> Controller<T, W> for TooltipController {
                         let tooltip_position_in_window_coordinates =
                             (position_in_window_coordinates.to_vec2() + cursor_size.to_vec2())
                                 .to_point();
-                        let win_id = ctx.new_sub_window(
-                            WindowConfig::default()
-                                .show_titlebar(false)
-                                .window_size_policy(WindowSizePolicy::Content)
-                                .set_level(WindowLevel::Tooltip(ctx.window().clone()))
-                                .set_position(tooltip_position_in_window_coordinates),
+                        let win_id = open_floating_window(
+                            ctx,
                             Label::<()>::new(self.tip.clone()),
                             (),
-                            env.clone(),
+                            tooltip_position_in_window_coordinates,
+                            env,
                         );
                         Some(TooltipState::Showing(win_id))
                     }
@@ -4,8 +4,17 @@ use druid::{
 };
 
 use crate::{
-    core::constants::commands::{InternalUICommand, INTERNAL_COMMAND},
-    data::{epub::EpubData, AppState, PagePosition},
+    core::constants::commands::{
+        InternalUICommand, EXPORT_ANNOTATIONS, IMPORT_ANNOTATIONS, INTERNAL_COMMAND,
+    },
+    data::{
+        epub::{
+            annotations::{Annotations, ExportFormat},
+            EpubData,
+        },
+        keymap::is_text_editing_key,
+        AppState, PagePosition, ReaderAction,
+    },
     widgets::EditWidget,
     PageType,
 };
@@ -72,6 +81,7 @@ impl Controller<AppState, Flex<AppState>> for EpubPageController {
                                 img2.to_owned(),
                                 strings.to_owned(),
                                 data.epub_data.page_position.clone(),
+                                data.epub_data.print_page_number(),
                             );
                             ctx.request_update();
                             ctx.set_handled();
@@ -92,6 +102,48 @@ impl Controller<AppState, Flex<AppState>> for EpubPageController {
                                 ctx.set_handled();
                             }
                         }
+                        InternalUICommand::AddBookmark => {
+                            if data.epub_data.add_bookmark() {
+                                // bookmarks are saved with the other information of the book
+                                ctx.submit_command(INTERNAL_COMMAND.with(
+                                    InternalUICommand::UpdateBookInfo(
+                                        data.epub_data.get_epub_path(),
+                                    ),
+                                ));
+                            }
+                            ctx.set_handled();
+                        }
+                        InternalUICommand::ExportAnnotations => {
+                            let formats = ExportFormat::ALL.iter().map(|f| f.file_spec()).collect();
+                            let filedialog = druid::FileDialogOptions::new()
+                                .accept_command(EXPORT_ANNOTATIONS)
+                                .default_type(ExportFormat::Markdown.file_spec())
+                                .default_name(format!(
+                                    "{} - annotations",
+                                    data.epub_data.get_title()
+                                ))
+                                .title("Export annotations");
+                            ctx.submit_command(
+                                druid::commands::SHOW_SAVE_PANEL
+                                    .with(filedialog.allowed_types(formats)),
+                            );
+                            ctx.set_handled();
+                        }
+                        InternalUICommand::ImportAnnotations => {
+                            let filedialog = druid::FileDialogOptions::new()
+                                .accept_command(IMPORT_ANNOTATIONS)
+                                .title("Import annotations");
+                            ctx.submit_command(druid::commands::SHOW_OPEN_PANEL.with(
+                                filedialog.allowed_types(vec![ExportFormat::Json.file_spec()]),
+                            ));
+                            ctx.set_handled();
+                        }
+                        InternalUICommand::PageKeyDown(key) => {
+                            if let Some(action) = data.config.keymap.action_for(key) {
+                                perform_action(ctx, action, data);
+                            }
+                            ctx.set_handled();
+                        }
                         InternalUICommand::SaveModification(path) => {
                             data.epub_data.save_new_epub(path);
                             ctx.request_update();
@@ -100,6 +152,48 @@ impl Controller<AppState, Flex<AppState>> for EpubPageController {
 
                         _ => {}
                     }
+                } else if let Some(file_info) = cmd.get(EXPORT_ANNOTATIONS) {
+                    // the format is chosen by the extension of the file, Markdown if it has none
+                    let (path, format) = match ExportFormat::from_path(file_info.path()) {
+                        Some(format) => (file_info.path().to_path_buf(), format),
+                        None => (
+                            file_info.path().with_extension("md"),
+                            ExportFormat::Markdown,
+                        ),
+                    };
+                    if let Err(e) = data.epub_data.annotations().save(&path, format) {
+                        data.home_page_data.show_error(format!(
+                            "The annotations could not be exported to {}: {}",
+                            path.display(),
+                            e
+                        ));
+                    }
+                    ctx.set_handled();
+                } else if let Some(file_info) = cmd.get(IMPORT_ANNOTATIONS) {
+                    let imported = Annotations::load(file_info.path())
+                        .map_err(|e| e.to_string())
+                        .and_then(|annotations| data.epub_data.import_annotations(annotations));
+                    match imported {
+                        Ok(true) => {
+                            ctx.submit_command(INTERNAL_COMMAND.with(
+                                InternalUICommand::UpdateBookInfo(data.epub_data.get_epub_path()),
+                            ));
+                        }
+                        Ok(false) => {}
+                        Err(e) => data.home_page_data.show_error(format!(
+                            "The annotations could not be imported from {}: {}",
+                            file_info.path().display(),
+                            e
+                        )),
+                    }
+                    ctx.set_handled();
+                }
+            }
+            // the keys which could edit a text are sent by the page, when it has the focus
+            Event::KeyDown(key) if !is_text_editing_key(key) => {
+                if let Some(action) = data.config.keymap.action_for(key) {
+                    perform_action(ctx, action, data);
+                    ctx.set_handled();
                 }
             }
             _ => {}
@@ -108,6 +202,25 @@ impl Controller<AppState, Flex<AppState>> for EpubPageController {
     }
 }
 
+/**
+ * Perform an action of the keymap, with the command doing the same.
+ */
+fn perform_action(ctx: &mut EventCtx, action: ReaderAction, data: &AppState) {
+    let chapter = data.epub_data.page_position.chapter();
+    let command = match action {
+        ReaderAction::NextPage => InternalUICommand::EpubNavigate(true),
+        ReaderAction::PreviousPage => InternalUICommand::EpubNavigate(false),
+        ReaderAction::ChapterStart => InternalUICommand::EpubGoToPos(PagePosition::new(chapter, 0)),
+        ReaderAction::ChapterEnd => InternalUICommand::EpubGoToChapterEnd,
+        ReaderAction::FocusSearch => InternalUICommand::FocusSearch,
+        ReaderAction::AddBookmark => InternalUICommand::AddBookmark,
+        ReaderAction::ClosePanel => InternalUICommand::ClosePanel,
+        ReaderAction::NextSearchResult => InternalUICommand::EpubGoToSearchResult(true),
+        ReaderAction::PreviousSearchResult => InternalUICommand::EpubGoToSearchResult(false),
+    };
+    ctx.submit_command(INTERNAL_COMMAND.with(command));
+}
+
 fn start_ocr_search_in_thread(
     sink: druid::ExtEventSink,
     image_path: String,
@@ -138,6 +251,7 @@ fn start_reverse_ocr_search_in_thread(
     image_2: String,
     strings: Vec<Vec<String>>,
     current_position: PagePosition,
+    print_page: Option<usize>,
 ) {
     std::thread::spawn(move || {
         let res = crate::ocr::reverse_search_with_ocr_input(
@@ -145,6 +259,7 @@ fn start_reverse_ocr_search_in_thread(
             &image_1,
             &image_2,
             &current_position,
+            print_page,
         );
         sink.submit_command(
             INTERNAL_COMMAND,
@@ -1,3 +1,5 @@
 pub mod epub_controller;
+pub mod page_item;
+pub mod pagination;
 pub mod sidebar;
 pub mod textcontainer;
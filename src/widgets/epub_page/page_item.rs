@@ -0,0 +1,752 @@
+use std::ops::Range;
+
+use druid::piet::{
+    InterpolationMode, PietText, PietTextLayoutBuilder, TextLayout as _,
+    TextStorage as PietTextStorage,
+};
+use druid::{
+    text::{Link, RichText, TextStorage},
+    ArcStr, Color, Command, Data, Env, ImageBuf, Insets, PaintCtx, Point, Rect, RenderContext,
+    Size, TextLayout, Vec2,
+};
+
+use crate::css::BlockStyle;
+use crate::hyphenate::SOFT_HYPHEN;
+
+const RULE_HEIGHT: f64 = 20.;
+const TABLE_CELL_PADDING: f64 = 5.;
+// space between the marker of a list item and its text
+const MARKER_SPACING: f64 = 6.;
+const BORDER_COLOR: Color = Color::GRAY;
+
+/**
+ * PageItem is a single element laid out by the PageSplitter.
+ * It can be a paragraph of text (with the marker of its list item, if any),
+ * an image coming from the book, a table or an horizontal rule.
+ *
+ * Images are scaled to fit the page width and are never taller than
+ * the page, so they can always be shown on a single page.
+ * Paragraphs and tables are placed inside the margins given by their block style;
+ * the columns of a table share the width equally.
+ *
+ * The lines of a paragraph can be shown on different pages, the other
+ * elements are a single line which is never split.
+ *
+ * The text layout has no line height, so the lines of a paragraph are spaced
+ * by drawing them one at a time, each with the extra space split above and below it.
+ * The offsets of the text are in bytes, and the numbers of characters do not count
+ * the soft hyphens of the hyphenation, which are not part of the book.
+ *
+ * The first line of an indented paragraph is laid out on its own, in the width
+ * left by the indent, and drawn shifted by it; the text itself is never changed,
+ * so the offsets do not depend on the indent.
+ */
+#[derive(Clone)]
+pub enum PageItem {
+    Text {
+        layout: TextLayout<ParagraphText>,
+        // the indented first line, drawn in place of the first line of the layout
+        first_line: Option<TextLayout<ParagraphText>>,
+        marker: Option<TextLayout<ArcStr>>,
+        block: BlockStyle,
+        // margins of the block and indent of the first line, as fitted in the page
+        // by the last wrap
+        margins: Insets,
+        indent: f64,
+        wrap_width: f64,
+        // relative to the natural height of the lines
+        line_height: f64,
+    },
+    Image {
+        image: ImageBuf,
+        size: Size,
+    },
+    Table {
+        rows: Vec<Vec<TextLayout<RichText>>>,
+        block: BlockStyle,
+        margins: Insets,
+        column_width: f64,
+        row_heights: Vec<f64>,
+    },
+    Rule {
+        width: f64,
+    },
+}
+
+impl PageItem {
+    pub fn new_text(
+        mut layout: TextLayout<ParagraphText>,
+        marker: Option<TextLayout<ArcStr>>,
+        block: BlockStyle,
+    ) -> Self {
+        layout.set_text_alignment(block.text_align.into());
+        PageItem::Text {
+            layout,
+            first_line: None,
+            marker,
+            block,
+            margins: Insets::ZERO,
+            indent: 0.,
+            wrap_width: 0.,
+            line_height: 1.,
+        }
+    }
+
+    /**
+     * Set the line height of a paragraph, relative to the natural height of its lines.
+     * The other elements are not changed.
+     */
+    pub fn with_line_height(mut self, height: f64) -> Self {
+        if let PageItem::Text { line_height, .. } = &mut self {
+            *line_height = height;
+        }
+        self
+    }
+
+    pub fn new_image(image: ImageBuf) -> Self {
+        PageItem::Image {
+            image,
+            size: Size::ZERO,
+        }
+    }
+
+    pub fn new_table(rows: Vec<Vec<TextLayout<RichText>>>, block: BlockStyle) -> Self {
+        PageItem::Table {
+            rows,
+            block,
+            margins: Insets::ZERO,
+            column_width: 0.,
+            row_heights: Vec::new(),
+        }
+    }
+
+    pub fn new_rule() -> Self {
+        PageItem::Rule { width: 0. }
+    }
+
+    /**
+     * Fit the margins of a block in the given width.
+     * Horizontal margins never take more than half of the width.
+     */
+    fn fit_margins(block: &BlockStyle, width: f64) -> Insets {
+        let horizontal = block.margin_left + block.margin_right;
+        let scale = if horizontal > width / 2. {
+            width / 2. / horizontal
+        } else {
+            1.
+        };
+        Insets::new(
+            block.margin_left * scale,
+            block.margin_top,
+            block.margin_right * scale,
+            block.margin_bottom,
+        )
+    }
+
+    /**
+     * Layout the element for the given page width and height.
+     *
+     * @param width: the width available for the element
+     * @param max_height: the maximum height of a page
+     */
+    pub fn wrap(&mut self, width: f64, max_height: f64, text: &mut PietText, env: &Env) {
+        match self {
+            PageItem::Text {
+                layout,
+                first_line,
+                marker,
+                block,
+                margins,
+                indent,
+                wrap_width,
+                ..
+            } => {
+                *margins = Self::fit_margins(block, width);
+                let text_width = width - margins.x_value();
+                if *wrap_width != text_width {
+                    *wrap_width = text_width;
+                    *indent = block.text_indent.clamp(0., text_width / 2.);
+                    *first_line = Self::wrap_first_line(layout, *indent, text_width, text, env);
+                }
+                layout.rebuild_if_needed(text, env);
+                if let Some(first_line) = first_line {
+                    first_line.rebuild_if_needed(text, env);
+                }
+                if let Some(marker) = marker {
+                    marker.rebuild_if_needed(text, env);
+                }
+            }
+            PageItem::Image { image, size } => {
+                let image_size = image.size();
+                if image_size.is_empty() || width <= 0. || max_height <= 0. {
+                    *size = Size::ZERO;
+                    return;
+                }
+                let scale = (width / image_size.width).min(max_height / image_size.height);
+                *size = Size::new(image_size.width * scale, image_size.height * scale);
+            }
+            PageItem::Table {
+                rows,
+                block,
+                margins,
+                column_width,
+                row_heights,
+            } => {
+                *margins = Self::fit_margins(block, width);
+                let columns = rows.iter().map(|row| row.len()).max().unwrap_or(1).max(1);
+                *column_width = (width - margins.x_value()) / columns as f64;
+
+                row_heights.clear();
+                for row in rows.iter_mut() {
+                    let mut height: f64 = 0.;
+                    for cell in row.iter_mut() {
+                        cell.set_wrap_width((*column_width - TABLE_CELL_PADDING * 2.).max(0.));
+                        cell.rebuild_if_needed(text, env);
+                        height = height.max(cell.size().height);
+                    }
+                    row_heights.push(height + TABLE_CELL_PADDING * 2.);
+                }
+            }
+            PageItem::Rule { width: rule_width } => *rule_width = width,
+        }
+    }
+
+    /**
+     * Wrap a paragraph to the given width, with its first line in the width left
+     * by the indent. When the first line ends before the first line of the paragraph,
+     * a line break is forced in the paragraph where it ends, so the following lines
+     * are wrapped as after the indented line.
+     *
+     * @return the layout of the indented first line, None without indent or
+     *         when the first line cannot be broken
+     */
+    fn wrap_first_line(
+        layout: &mut TextLayout<ParagraphText>,
+        indent: f64,
+        width: f64,
+        text: &mut PietText,
+        env: &Env,
+    ) -> Option<TextLayout<ParagraphText>> {
+        layout.set_wrap_width(width);
+        // the line break forced for the previous width
+        let paragraph = ParagraphText::new(layout.text()?.text.clone());
+        layout.set_text(paragraph.clone());
+        if indent <= 0. {
+            return None;
+        }
+        layout.rebuild_if_needed(text, env);
+
+        let mut first_line = layout.clone();
+        first_line.set_wrap_width(width - indent);
+        first_line.rebuild_if_needed(text, env);
+        let line_end = |layout: &TextLayout<ParagraphText>| {
+            layout
+                .layout()
+                .and_then(|layout| layout.line_metric(1))
+                .map(|metric| metric.start_offset)
+        };
+        match (line_end(&first_line), line_end(layout)) {
+            (Some(first_end), end) if Some(first_end) != end => {
+                layout.set_text(paragraph.with_break_before(first_end)?);
+            }
+            _ => (),
+        }
+        Some(first_line)
+    }
+
+    pub fn size(&self) -> Size {
+        match self {
+            PageItem::Text {
+                layout,
+                margins,
+                line_height,
+                ..
+            } => {
+                let size = layout.size();
+                Size::new(size.width, size.height * line_height) + margins.size()
+            }
+            PageItem::Image { size, .. } => *size,
+            PageItem::Table {
+                rows,
+                margins,
+                column_width,
+                row_heights,
+                ..
+            } => {
+                let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+                Size::new(
+                    column_width * columns as f64,
+                    row_heights.iter().sum::<f64>(),
+                ) + margins.size()
+            }
+            PageItem::Rule { width } => Size::new(*width, RULE_HEIGHT),
+        }
+    }
+
+    /**
+     * Offset of the content from the origin of the element, given by the margins.
+     */
+    fn content_offset(&self) -> Vec2 {
+        match self {
+            PageItem::Text { margins, .. } | PageItem::Table { margins, .. } => {
+                Vec2::new(margins.x0, margins.y0)
+            }
+            PageItem::Image { .. } | PageItem::Rule { .. } => Vec2::ZERO,
+        }
+    }
+
+    /**
+     * The cells of a table, in reading order, with the rect of each cell
+     * relative to the origin of the element.
+     */
+    fn table_cells(&self) -> Vec<(&TextLayout<RichText>, Rect)> {
+        let mut cells = Vec::new();
+        if let PageItem::Table {
+            rows,
+            column_width,
+            row_heights,
+            ..
+        } = self
+        {
+            let mut origin = self.content_offset().to_point();
+            for (row, height) in rows.iter().zip(row_heights.iter()) {
+                for (i, cell) in row.iter().enumerate() {
+                    let cell_origin = Point::new(origin.x + i as f64 * column_width, origin.y);
+                    let rect =
+                        Rect::from_origin_size(cell_origin, Size::new(*column_width, *height));
+                    cells.push((cell, rect));
+                }
+                origin.y += height;
+            }
+        }
+        cells
+    }
+
+    /**
+     * The vertical distance between a line of a paragraph, as drawn,
+     * and the same line in the text layout.
+     */
+    fn line_shift(&self, line: usize) -> f64 {
+        match self {
+            PageItem::Text {
+                layout,
+                line_height,
+                ..
+            } if *line_height != 1. => layout
+                .layout()
+                .and_then(|layout| layout.line_metric(line))
+                .map(|metric| (line_height - 1.) * (metric.y_offset + metric.height / 2.))
+                .unwrap_or(0.),
+            _ => 0.,
+        }
+    }
+
+    /**
+     * The line of a paragraph at the given height, relative to the top of the text.
+     *
+     * @param drawn: true for the lines as drawn, false for the lines of the text layout
+     */
+    fn line_at(&self, y: f64, drawn: bool) -> usize {
+        let scale = match self {
+            PageItem::Text { line_height, .. } if drawn => *line_height,
+            _ => 1.,
+        };
+        (1..self.line_count())
+            .rev()
+            .find(|line| self.line_y_offset(*line) * scale <= y)
+            .unwrap_or(0)
+    }
+
+    fn line_y_offset(&self, line: usize) -> f64 {
+        match self {
+            PageItem::Text { layout, .. } => layout
+                .layout()
+                .and_then(|layout| layout.line_metric(line))
+                .map(|metric| metric.y_offset)
+                .unwrap_or(0.),
+            _ => 0.,
+        }
+    }
+
+    /**
+     * The layout of a paragraph showing the given point, as drawn, together with
+     * the same point in the layout. The indented first line has a layout of its own.
+     *
+     * @param pos: the position relative to the origin of the element
+     */
+    fn text_layout_at(&self, pos: Point) -> Option<(&TextLayout<ParagraphText>, Point)> {
+        let pos = pos - self.content_offset();
+        let line = self.line_at(pos.y, true);
+        match self {
+            PageItem::Text {
+                first_line: Some(first_line),
+                indent,
+                ..
+            } if line == 0 => Some((first_line, Point::new(pos.x - indent, 0.))),
+            PageItem::Text { layout, .. } => {
+                Some((layout, Point::new(pos.x, pos.y - self.line_shift(line))))
+            }
+            _ => None,
+        }
+    }
+
+    /**
+     * The number of lines of the element, at least one.
+     */
+    pub fn line_count(&self) -> usize {
+        match self {
+            PageItem::Text { layout, .. } => layout
+                .layout()
+                .map(|layout| layout.line_count())
+                .unwrap_or(1),
+            _ => 1,
+        }
+    }
+
+    /**
+     * The vertical position of the top of a line, relative to the origin of the element.
+     * The first line starts at the top of the element, so it includes the top margin,
+     * and the line following the last one starts at the bottom of the element.
+     *
+     * @param line: the index of the line, up to line_count()
+     */
+    pub fn line_top(&self, line: usize) -> f64 {
+        if line >= self.line_count() {
+            return self.size().height;
+        }
+        match self {
+            PageItem::Text {
+                layout,
+                margins,
+                line_height,
+                ..
+            } if line > 0 => layout
+                .layout()
+                .and_then(|layout| layout.line_metric(line))
+                .map(|metric| margins.y0 + metric.y_offset * line_height)
+                .unwrap_or(0.),
+            _ => 0.,
+        }
+    }
+
+    /**
+     * The index of the line containing the given byte offset of the text.
+     */
+    pub fn line_for_offset(&self, offset: usize) -> usize {
+        (0..self.line_count())
+            .rev()
+            .find(|line| self.line_start(*line) <= offset)
+            .unwrap_or(0)
+    }
+
+    /**
+     * The byte offset of the text where the given line starts.
+     */
+    pub fn line_start(&self, line: usize) -> usize {
+        match self {
+            PageItem::Text { layout, .. } => layout
+                .layout()
+                .and_then(|layout| layout.line_metric(line))
+                .map(|metric| metric.start_offset)
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /**
+     * The text of the element. The cells of a table are separated by tabs
+     * and its rows by new lines, images and rules have no text.
+     */
+    pub fn text(&self) -> String {
+        match self {
+            PageItem::Text { layout, .. } => layout
+                .text()
+                .map(|text| text.text.as_str().to_string())
+                .unwrap_or_default(),
+            PageItem::Table { rows, .. } => rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| cell.text().map(|t| t.as_str()).unwrap_or_default())
+                        .collect::<Vec<_>>()
+                        .join("\t")
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            PageItem::Image { .. } | PageItem::Rule { .. } => String::new(),
+        }
+    }
+
+    /**
+     * Convert a byte offset of the text of the element into a number of characters.
+     */
+    pub fn char_offset(&self, byte_offset: usize) -> usize {
+        self.text()
+            .char_indices()
+            .take_while(|(i, _)| *i < byte_offset)
+            .filter(|(_, c)| *c != SOFT_HYPHEN)
+            .count()
+    }
+
+    /**
+     * Convert a number of characters of the text of the element into a byte offset.
+     * Offsets past the end of the text give the length of the text.
+     */
+    pub fn byte_offset(&self, char_offset: usize) -> usize {
+        let text = self.text();
+        text.char_indices()
+            .filter(|(_, c)| *c != SOFT_HYPHEN)
+            .nth(char_offset)
+            .map(|(i, _)| i)
+            .unwrap_or(text.len())
+    }
+
+    /**
+     * Return the byte offset of the text closest to the given position.
+     *
+     * @param pos: the position relative to the origin of the element
+     */
+    pub fn offset_for_pos(&self, pos: Point) -> usize {
+        match self {
+            PageItem::Text { .. } => self
+                .text_layout_at(pos)
+                .and_then(|(layout, pos)| layout.layout().map(|l| l.hit_test_point(pos).idx))
+                .unwrap_or(0),
+            PageItem::Table { .. } => {
+                let cells = self.table_cells();
+                let mut cell_start = 0;
+                for (cell, rect) in cells.iter() {
+                    let cell_len = cell.text().map(|t| t.as_str().len()).unwrap_or(0);
+                    if rect.contains(pos) {
+                        let offset = rect.origin().to_vec2()
+                            + Vec2::new(TABLE_CELL_PADDING, TABLE_CELL_PADDING);
+                        let idx = cell
+                            .layout()
+                            .map(|layout| layout.hit_test_point(pos - offset).idx)
+                            .unwrap_or(0);
+                        return cell_start + idx;
+                    }
+                    cell_start += cell_len + 1;
+                }
+                // outside of the cells, the closest end of the table
+                match cells.first() {
+                    Some((_, rect)) if pos.y < rect.y0 => 0,
+                    _ => cell_start.saturating_sub(1),
+                }
+            }
+            PageItem::Image { .. } | PageItem::Rule { .. } => 0,
+        }
+    }
+
+    /**
+     * Return the rects covering the given range of text.
+     * Images and rules do not contain text, so they return no rects.
+     * The text of a table is the text of its cells, each followed by a separator.
+     */
+    pub fn rects_for_range(&self, range: Range<usize>) -> Vec<Rect> {
+        match self {
+            PageItem::Text {
+                layout,
+                first_line,
+                indent,
+                ..
+            } => {
+                let offset = self.content_offset();
+                let mut rects: Vec<Rect> = layout
+                    .rects_for_range(range.clone())
+                    .into_iter()
+                    .map(|rect| (self.line_at(rect.center().y, false), rect))
+                    .filter(|(line, _)| first_line.is_none() || *line > 0)
+                    .map(|(line, rect)| rect + offset + Vec2::new(0., self.line_shift(line)))
+                    .collect();
+                if let Some(first_line) = first_line {
+                    let first_end = if self.line_count() > 1 {
+                        self.line_start(1)
+                    } else {
+                        usize::MAX
+                    };
+                    let (start, end) = (range.start.min(first_end), range.end.min(first_end));
+                    if start < end {
+                        let shift = offset + Vec2::new(*indent, self.line_shift(0));
+                        rects.extend(
+                            first_line
+                                .rects_for_range(start..end)
+                                .into_iter()
+                                .map(|rect| rect + shift),
+                        );
+                    }
+                }
+                rects
+            }
+            PageItem::Table { .. } => {
+                let mut rects = Vec::new();
+                let mut cell_start = 0;
+                for (cell, rect) in self.table_cells() {
+                    let cell_end = cell_start + cell.text().map(|t| t.as_str().len()).unwrap_or(0);
+                    let start = range.start.max(cell_start);
+                    let end = range.end.min(cell_end);
+                    if start < end {
+                        let offset = rect.origin().to_vec2()
+                            + Vec2::new(TABLE_CELL_PADDING, TABLE_CELL_PADDING);
+                        rects.extend(
+                            cell.rects_for_range(start - cell_start..end - cell_start)
+                                .into_iter()
+                                .map(|r| r + offset),
+                        );
+                    }
+                    cell_start = cell_end + 1;
+                }
+                rects
+            }
+            PageItem::Image { .. } | PageItem::Rule { .. } => Vec::new(),
+        }
+    }
+
+    /**
+     * Return the command of the link at the given position, if any.
+     *
+     * @param pos: the position relative to the origin of the element
+     */
+    pub fn link_for_pos(&self, pos: Point) -> Option<Command> {
+        match self {
+            PageItem::Text { .. } => self
+                .text_layout_at(pos)
+                .and_then(|(layout, pos)| layout.link_for_pos(pos))
+                .map(|link| link.command.clone()),
+            PageItem::Table { .. } => self.table_cells().into_iter().find_map(|(cell, rect)| {
+                if !rect.contains(pos) {
+                    return None;
+                }
+                let offset =
+                    rect.origin().to_vec2() + Vec2::new(TABLE_CELL_PADDING, TABLE_CELL_PADDING);
+                cell.link_for_pos(pos - offset)
+                    .map(|link| link.command.clone())
+            }),
+            PageItem::Image { .. } | PageItem::Rule { .. } => None,
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut PaintCtx, origin: Point) {
+        match self {
+            PageItem::Text {
+                layout,
+                first_line,
+                marker,
+                indent,
+                line_height,
+                ..
+            } => {
+                let text_origin = origin + self.content_offset();
+                if *line_height == 1. && first_line.is_none() {
+                    layout.draw(ctx, text_origin);
+                } else {
+                    // each line is drawn on its own, cut from the whole text
+                    // or from the indented first line
+                    let width = layout.size().width;
+                    for line in 0..self.line_count() {
+                        let top = self.line_y_offset(line);
+                        let bottom = if line + 1 < self.line_count() {
+                            self.line_y_offset(line + 1)
+                        } else {
+                            layout.size().height
+                        };
+                        let shifted_origin = text_origin + Vec2::new(0., self.line_shift(line));
+                        let (line_layout, line_indent) = match first_line {
+                            Some(first_line) if line == 0 => (first_line, *indent),
+                            _ => (layout, 0.),
+                        };
+                        ctx.with_save(|ctx| {
+                            // glyphs can go past the sides of the text
+                            ctx.clip(
+                                Rect::new(-width, top, width * 2., bottom)
+                                    + shifted_origin.to_vec2(),
+                            );
+                            line_layout.draw(ctx, shifted_origin + Vec2::new(line_indent, 0.));
+                        });
+                    }
+                }
+                if let Some(marker) = marker {
+                    let marker_width = marker.size().width + MARKER_SPACING;
+                    let marker_origin = text_origin + Vec2::new(-marker_width, self.line_shift(0));
+                    marker.draw(ctx, marker_origin);
+                }
+            }
+            PageItem::Image { image, size } => {
+                if size.is_empty() {
+                    return;
+                }
+                let piet_image = image.to_image(ctx.render_ctx);
+                ctx.draw_image(
+                    &piet_image,
+                    Rect::from_origin_size(origin, *size),
+                    InterpolationMode::Bilinear,
+                );
+            }
+            PageItem::Table { .. } => {
+                for (cell, rect) in self.table_cells() {
+                    let rect = rect + origin.to_vec2();
+                    ctx.stroke(rect, &BORDER_COLOR, 1.);
+                    cell.draw(
+                        ctx,
+                        rect.origin() + Vec2::new(TABLE_CELL_PADDING, TABLE_CELL_PADDING),
+                    );
+                }
+            }
+            PageItem::Rule { width } => {
+                let y = origin.y + RULE_HEIGHT / 2.;
+                let line = druid::kurbo::Line::new((origin.x, y), (origin.x + width, y));
+                ctx.stroke(line, &BORDER_COLOR, 1.);
+            }
+        }
+    }
+}
+
+/**
+ * The text of a paragraph given to its layout.
+ * To wrap the lines following an indented first line, a line break is forced
+ * where the first line ends, in place of the character preceding it: the line
+ * ending there is drawn from the layout of the first line, and the offsets
+ * of the text are kept since the replacement has the same length.
+ */
+#[derive(Clone, Data)]
+pub struct ParagraphText {
+    text: RichText,
+    layout_text: ArcStr,
+}
+
+impl ParagraphText {
+    pub fn new(text: RichText) -> Self {
+        let layout_text = ArcStr::from(text.as_str());
+        ParagraphText { text, layout_text }
+    }
+
+    /**
+     * The same text with a line break forced before the given byte offset.
+     */
+    fn with_break_before(&self, offset: usize) -> Option<Self> {
+        let text = self.text.as_str();
+        let replaced = text.get(..offset)?.chars().next_back()?;
+        let start = offset - replaced.len_utf8();
+        let line_break = format!("{}\n", " ".repeat(replaced.len_utf8() - 1));
+        Some(ParagraphText {
+            text: self.text.clone(),
+            layout_text: format!("{}{}{}", &text[..start], line_break, &text[offset..]).into(),
+        })
+    }
+}
+
+impl PietTextStorage for ParagraphText {
+    fn as_str(&self) -> &str {
+        &self.layout_text
+    }
+}
+
+impl TextStorage for ParagraphText {
+    fn add_attributes(&self, builder: PietTextLayoutBuilder, env: &Env) -> PietTextLayoutBuilder {
+        self.text.add_attributes(builder, env)
+    }
+
+    fn links(&self) -> &[Link] {
+        self.text.links()
+    }
+}
@@ -0,0 +1,446 @@
+use std::ops::Range;
+
+use druid::{Data, Size};
+
+use crate::data::epub::settings::EpubSettings;
+
+use super::page_item::PageItem;
+
+/**
+ * Module splitting the chapters of a book in pages.
+ *
+ * A page is filled line by line with the elements of the chapter, so long
+ * paragraphs continue on the next page. The same functions count the pages
+ * of the whole book, which are cached for each book, settings and window size.
+ */
+
+// paginations kept for the settings and window sizes used last
+const MAX_CACHED_PAGINATIONS: usize = 8;
+
+/**
+ * A part of an element shown in a page: all of its lines, or only some of them
+ * when the element starts on a previous page or continues on the next one.
+ */
+#[derive(Clone, Debug)]
+pub struct PageSlice {
+    pub item: usize,
+    pub lines: Range<usize>,
+}
+
+/**
+ * Fill a page with the elements of a chapter following the given position.
+ * When an element does not fit in the rest of the page, the page ends
+ * with the lines which fit, and the element continues on the next page.
+ *
+ * @param text: the elements of the chapter, wrapped to the width of the page
+ * @param start: the element and the line where the page starts
+ * @param height: the height of the page
+ * @param spacing: the space left after each element
+ *
+ * @return the parts of the elements shown in the page,
+ *         and the element and the line following the page
+ */
+pub fn fill_page(
+    text: &[PageItem],
+    start: (usize, usize),
+    height: f64,
+    spacing: f64,
+) -> (Vec<PageSlice>, (usize, usize)) {
+    let (mut item, mut line) = start;
+    let mut slices = Vec::new();
+    let mut used_height = 0.;
+    while let Some(element) = text.get(item) {
+        let line_count = element.line_count();
+        let top = element.line_top(line);
+        let lines_end = if used_height + element.line_top(line_count) - top + spacing <= height {
+            line_count
+        } else {
+            line + (line..line_count)
+                .take_while(|l| used_height + element.line_top(l + 1) - top <= height)
+                .count()
+        };
+
+        // a page shows at least a line
+        let lines_end = if lines_end == line && slices.is_empty() {
+            line + 1
+        } else {
+            lines_end
+        };
+        if lines_end == line {
+            break;
+        }
+
+        slices.push(PageSlice {
+            item,
+            lines: line..lines_end,
+        });
+        if lines_end < line_count {
+            line = lines_end;
+            break;
+        }
+        used_height += element.line_top(line_count) - top + spacing;
+        item += 1;
+        line = 0;
+    }
+    (slices, (item, line))
+}
+
+/**
+ * Find where the page ending at the given position starts, filling it backwards
+ * line by line.
+ *
+ * @param text: the elements of the chapter, wrapped to the width of the page
+ * @param end: the element and the line following the page
+ * @param height: the height of the page
+ * @param spacing: the space left after each element
+ *
+ * @return the element and the line where the page starts
+ */
+pub fn page_before(
+    text: &[PageItem],
+    end: (usize, usize),
+    height: f64,
+    spacing: f64,
+) -> (usize, usize) {
+    let (mut item, mut line) = end;
+    let mut used_height = 0.;
+    loop {
+        // the element preceding the position, with the end of its lines before it
+        let (previous, lines_end) = match (item, line) {
+            (_, line) if line > 0 => (item, line),
+            (0, _) => break,
+            _ => match text.get(item - 1) {
+                Some(element) => (item - 1, element.line_count()),
+                None => break,
+            },
+        };
+        let element = match text.get(previous) {
+            Some(element) => element,
+            None => break,
+        };
+        // the spacing follows the element only when it ends in the page
+        let spacing = if lines_end == element.line_count() {
+            spacing
+        } else {
+            0.
+        };
+        let bottom = element.line_top(lines_end);
+
+        let mut start = lines_end;
+        while start > 0 && used_height + bottom - element.line_top(start - 1) + spacing <= height {
+            start -= 1;
+        }
+        if start == lines_end {
+            if (item, line) != end {
+                break;
+            }
+            start -= 1;
+        }
+        used_height += bottom - element.line_top(start) + spacing;
+        item = previous;
+        line = start;
+        // the page is full when the element starts on a previous page
+        if start > 0 {
+            break;
+        }
+    }
+    (item, line)
+}
+
+/**
+ * Count the pages of a chapter. A chapter without elements is a single empty page.
+ */
+pub fn count_pages(text: &[PageItem], height: f64, spacing: f64) -> usize {
+    page_index(text, (text.len(), 0), height, spacing) + 1
+}
+
+/**
+ * The index of the page containing the given position, when the chapter is
+ * paginated from its start.
+ *
+ * @param text: the elements of the chapter, wrapped to the width of the page
+ * @param position: the element and the line to find
+ */
+pub fn page_index(text: &[PageItem], position: (usize, usize), height: f64, spacing: f64) -> usize {
+    let mut start = (0, 0);
+    let mut index = 0;
+    loop {
+        let (_, next) = fill_page(text, start, height, spacing);
+        if next > position || next.0 >= text.len() {
+            return index;
+        }
+        start = next;
+        index += 1;
+    }
+}
+
+/**
+ * The book, settings and window size a pagination is made for.
+ */
+#[derive(Clone, Data, Debug)]
+pub struct PaginationKey {
+    pub book: String,
+    pub settings: EpubSettings,
+    pub size: Size,
+}
+
+/**
+ * The number of pages of each chapter of a book.
+ * The chapters are paginated one at a time in background, while the book is read,
+ * so the number of pages of the book is known only at the end.
+ */
+pub struct BookPagination {
+    key: PaginationKey,
+    chapter_pages: Vec<Option<usize>>,
+}
+
+impl BookPagination {
+    pub fn key(&self) -> &PaginationKey {
+        &self.key
+    }
+
+    /**
+     * The first chapter whose pages are not counted yet, if any.
+     */
+    pub fn next_chapter(&self) -> Option<usize> {
+        self.chapter_pages.iter().position(|pages| pages.is_none())
+    }
+
+    /**
+     * The chapters whose pages are not counted yet.
+     */
+    pub fn pending_chapters(&self) -> Vec<usize> {
+        self.chapter_pages
+            .iter()
+            .enumerate()
+            .filter(|(_, pages)| pages.is_none())
+            .map(|(chapter, _)| chapter)
+            .collect()
+    }
+
+    pub fn set_chapter_pages(&mut self, chapter: usize, pages: usize) {
+        if let Some(chapter_pages) = self.chapter_pages.get_mut(chapter) {
+            *chapter_pages = Some(pages);
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next_chapter().is_none()
+    }
+
+    /**
+     * The pages of the book preceding a chapter, and the pages of the whole book.
+     * The pages of the chapter are given, since it can be edited while reading.
+     *
+     * @param chapter: the index of the chapter in the spine
+     * @param chapter_pages: the pages of the chapter, as it is shown
+     *
+     * @return None until all the chapters are paginated
+     */
+    pub fn book_pages(&self, chapter: usize, chapter_pages: usize) -> Option<(usize, usize)> {
+        let pages = self
+            .chapter_pages
+            .iter()
+            .enumerate()
+            .map(|(i, pages)| {
+                if i == chapter {
+                    Some(chapter_pages)
+                } else {
+                    *pages
+                }
+            })
+            .collect::<Option<Vec<usize>>>()?;
+        let before = pages.iter().take(chapter).sum();
+        Some((before, pages.iter().sum()))
+    }
+}
+
+/**
+ * The paginations of the books read in the session.
+ * Changing the settings or the size of the window starts a new pagination,
+ * the previous ones are kept in case they are used again.
+ */
+#[derive(Default)]
+pub struct PaginationCache {
+    // the current pagination is the last one
+    paginations: Vec<BookPagination>,
+}
+
+impl PaginationCache {
+    /**
+     * Make the pagination for the given key the current one,
+     * starting it if it is not cached.
+     *
+     * @param key: the book, settings and window size to paginate
+     * @param chapters: the number of chapters of the book
+     */
+    pub fn select(&mut self, key: PaginationKey, chapters: usize) -> &mut BookPagination {
+        match self.paginations.iter().position(|p| p.key.same(&key)) {
+            Some(i) => {
+                let pagination = self.paginations.remove(i);
+                self.paginations.push(pagination);
+            }
+            None => {
+                if self.paginations.len() >= MAX_CACHED_PAGINATIONS {
+                    self.paginations.remove(0);
+                }
+                self.paginations.push(BookPagination {
+                    key,
+                    chapter_pages: vec![None; chapters],
+                });
+            }
+        }
+        self.paginations.last_mut().unwrap()
+    }
+
+    /**
+     * The pagination for the given key, if it is still cached.
+     */
+    pub fn get_mut(&mut self, key: &PaginationKey) -> Option<&mut BookPagination> {
+        self.paginations.iter_mut().find(|p| p.key.same(key))
+    }
+
+    pub fn current(&self) -> Option<&BookPagination> {
+        self.paginations.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::BlockStyle;
+    use crate::widgets::epub_page::page_item::ParagraphText;
+    use druid::{
+        piet::Device, text::RichText, Color, Env, FontDescriptor, FontFamily, ImageBuf,
+        RenderContext, TextLayout,
+    };
+
+    const PAGE_WIDTH: f64 = 200.;
+
+    fn image(height: f64) -> PageItem {
+        PageItem::Image {
+            image: ImageBuf::empty(),
+            size: Size::new(PAGE_WIDTH, height),
+        }
+    }
+
+    /**
+     * A paragraph of many lines, laid out with the text backend of a bitmap.
+     */
+    fn paragraph(words: usize) -> PageItem {
+        let mut layout = TextLayout::new();
+        layout.set_text(ParagraphText::new(RichText::new(
+            vec!["word"; words].join(" ").into(),
+        )));
+        layout.set_font(FontDescriptor::new(FontFamily::SERIF));
+        layout.set_text_size(14.);
+        layout.set_text_color(Color::BLACK);
+        let mut item = PageItem::new_text(layout, None, BlockStyle::default());
+
+        let mut device = Device::new().unwrap();
+        let mut bitmap = device.bitmap_target(1, 1, 1.).unwrap();
+        let mut piet = bitmap.render_context();
+        item.wrap(PAGE_WIDTH, 1000., piet.text(), &Env::empty());
+        piet.finish().unwrap();
+        item
+    }
+
+    fn slices(page: &[PageSlice]) -> Vec<(usize, Range<usize>)> {
+        page.iter()
+            .map(|slice| (slice.item, slice.lines.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_fill_page_with_paragraph_taller_than_page() {
+        let text = vec![paragraph(200)];
+        assert!(text[0].line_count() > 6);
+        // three lines fit in the page
+        let height = text[0].line_top(3) + 0.5;
+
+        let (page, next) = fill_page(&text, (0, 0), height, 10.);
+        assert_eq!(slices(&page), vec![(0, 0..3)]);
+        assert_eq!(next, (0, 3));
+        let (page, next) = fill_page(&text, next, height, 10.);
+        assert_eq!(slices(&page), vec![(0, 3..6)]);
+        assert_eq!(next, (0, 6));
+
+        // a page shows at least a line
+        let (page, next) = fill_page(&text, (0, 0), 1., 10.);
+        assert_eq!(slices(&page), vec![(0, 0..1)]);
+        assert_eq!(next, (0, 1));
+    }
+
+    #[test]
+    fn test_fill_page_with_images_and_rules() {
+        let text = vec![image(100.), PageItem::new_rule(), image(300.)];
+
+        let (page, next) = fill_page(&text, (0, 0), 200., 10.);
+        assert_eq!(slices(&page), vec![(0, 0..1), (1, 0..1)]);
+        assert_eq!(next, (2, 0));
+        // an element taller than the page is shown alone
+        let (page, next) = fill_page(&text, next, 200., 10.);
+        assert_eq!(slices(&page), vec![(2, 0..1)]);
+        assert_eq!(next, (3, 0));
+    }
+
+    #[test]
+    fn test_two_pages_split_paragraph() {
+        let text = vec![image(100.), paragraph(200)];
+        let height = 100. + 10. + text[1].line_top(2) + 0.5;
+
+        // the paragraph starts in the first page and continues in the second one
+        let (first, next) = fill_page(&text, (0, 0), height, 10.);
+        assert_eq!(slices(&first), vec![(0, 0..1), (1, 0..2)]);
+        let (second, _) = fill_page(&text, next, height, 10.);
+        assert_eq!((second[0].item, second[0].lines.start), (1, 2));
+    }
+
+    #[test]
+    fn test_page_before_fill_page() {
+        let text = vec![paragraph(200)];
+        let height = text[0].line_top(3) + 0.5;
+        for start in [(0, 0), (0, 3)] {
+            let (_, next) = fill_page(&text, start, height, 10.);
+            assert_eq!(page_before(&text, next, height, 10.), start);
+        }
+
+        let text = vec![image(100.), image(100.), image(100.)];
+        let (_, next) = fill_page(&text, (0, 0), 250., 10.);
+        assert_eq!(next, (2, 0));
+        assert_eq!(page_before(&text, next, 250., 10.), (0, 0));
+        // the last page, as when going to the end of the chapter
+        assert_eq!(page_before(&text, (3, 0), 250., 10.), (1, 0));
+    }
+
+    fn key(font_size: f64) -> PaginationKey {
+        let mut settings = EpubSettings::default();
+        settings.font_size = font_size;
+        PaginationKey {
+            book: "book.epub".to_string(),
+            settings,
+            size: Size::new(800., 600.),
+        }
+    }
+
+    #[test]
+    fn test_book_pagination() {
+        let mut cache = PaginationCache::default();
+        let pagination = cache.select(key(14.), 3);
+        pagination.set_chapter_pages(0, 4);
+        pagination.set_chapter_pages(2, 5);
+        assert_eq!(pagination.next_chapter(), Some(1));
+        assert_eq!(pagination.pending_chapters(), vec![1]);
+        assert_eq!(pagination.book_pages(0, 4), None);
+
+        pagination.set_chapter_pages(1, 2);
+        assert!(pagination.is_complete());
+        assert_eq!(pagination.book_pages(2, 5), Some((6, 11)));
+        // the pages of the current chapter are the ones shown
+        assert_eq!(pagination.book_pages(1, 3), Some((4, 12)));
+
+        // changing the font size starts a new pagination, the previous one is kept
+        assert_eq!(cache.select(key(18.), 3).next_chapter(), Some(0));
+        assert!(cache.select(key(14.), 3).is_complete());
+    }
+}
@@ -1,6 +1,10 @@
 use druid::{
-    widget::{Flex, Label, List, Scroll, Slider, TextBox},
-    ArcStr, BoxConstraints, Color, Data, Env, Event, EventCtx, LayoutCtx, LensExt, LifeCycle,
+    lens,
+    widget::{
+        Button, Checkbox, Controller, CrossAxisAlignment, Flex, Label, List, Radio, Scroll, Slider,
+        Switch, TextBox,
+    },
+    ArcStr, BoxConstraints, Color, Data, Env, Event, EventCtx, LayoutCtx, Lens, LensExt, LifeCycle,
     LifeCycleCtx, PaintCtx, Point, RenderContext, Size, TextLayout, UpdateCtx, Widget, WidgetExt,
     WidgetPod,
 };
@@ -8,18 +12,17 @@ use druid_material_icons::IconPaths;
 
 use crate::{
     core::{
-        constants::{
-            commands::{InternalUICommand, INTERNAL_COMMAND},
-            epub_settings::{
-                MAX_FONT_SIZE, MAX_MARGIN, MAX_PARAGRAPH_SPACING, MIN_FONT_SIZE, MIN_MARGIN,
-                MIN_PARAGRAPH_SPACING,
-            },
-        },
-        style::{self, PRIMARY_DARK},
+        constants::commands::{InternalUICommand, INTERNAL_COMMAND},
+        style,
     },
-    data::epub::{EpubData,
-        settings::{EpubSettings, VisualizationMode},
-        SidebarData,
+    css::TextAlign,
+    data::{
+        config::SettingsRanges,
+        epub::{
+            settings::{EpubSettings, VisualizationMode},
+            theme::Theme,
+            EpubData, SearchOptions, SidebarData,
+        },
     },
     widgets::{
         common::{
@@ -30,6 +33,8 @@ use crate::{
     },
 };
 
+const THEME_LABEL_WIDTH: f64 = 110.;
+const FONT_LIST_HEIGHT: f64 = 150.;
 
 /**
  * Panel represents a side widget displayed. 
@@ -39,6 +44,8 @@ use crate::{
 pub struct Panel {
     header: TextLayout<ArcStr>,
     input_widget: Option<WidgetPod<EpubData, Box<dyn Widget<EpubData>>>>,
+    // action performed when Enter is pressed in the input widget
+    on_submit: fn(&mut EventCtx, &mut EpubData),
     widget: WidgetPod<EpubData, Box<dyn Widget<EpubData>>>,
 }
 impl Panel {
@@ -46,15 +53,30 @@ impl Panel {
         Self {
             header: TextLayout::from_text(title.to_string()),
             input_widget: None,
+            on_submit: |_, _| {},
             widget: WidgetPod::new(widget),
         }
     }
 
-    pub fn with_input_widget(mut self) -> Self {
+    /**
+     * Add a text input above the widget of the panel.
+     *
+     * @param input: the lens to the text of the input
+     * @param placeholder: the text shown while the input is empty
+     * @param on_submit: the action performed when Enter is pressed
+     */
+    pub fn with_input_widget(
+        mut self,
+        input: impl Lens<EpubData, String> + 'static,
+        placeholder: &str,
+        on_submit: fn(&mut EventCtx, &mut EpubData),
+    ) -> Self {
         let input_widget = TextBox::new()
-            .lens(EpubData::sidebar_data.then(SidebarData::search_input))
+            .with_placeholder(placeholder.to_string())
+            .lens(input)
             .boxed();
         self.input_widget = Some(WidgetPod::new(input_widget));
+        self.on_submit = on_submit;
         self
     }
 }
@@ -62,11 +84,18 @@ impl Widget<EpubData> for Panel {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut EpubData, env: &Env) {
         if let Some(input_widget) = &mut self.input_widget {
             match event {
+                Event::Command(cmd)
+                    if matches!(
+                        cmd.get(INTERNAL_COMMAND),
+                        Some(InternalUICommand::FocusInput)
+                    ) =>
+                {
+                    ctx.set_focus(input_widget.id());
+                    ctx.set_handled();
+                }
                 Event::KeyUp(key) => {
                     if key.code == druid::Code::Enter {
-                        data.search_string_in_book();
-                        ctx.request_update();
-                        ctx.request_layout();
+                        (self.on_submit)(ctx, data);
                     }
                 }
                 _ => {}
@@ -85,6 +114,10 @@ impl Widget<EpubData> for Panel {
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, old_data: &EpubData, data: &EpubData, env: &Env) {
+        // the color of the header follows the theme
+        if self.header.needs_rebuild_after_update(ctx) {
+            ctx.request_layout();
+        }
         if !old_data.same(data) {
             if let Some(input_widget) = &mut self.input_widget {
                 input_widget.update(ctx, data, env);
@@ -137,10 +170,7 @@ impl Widget<EpubData> for Panel {
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &EpubData, env: &Env) {
         let size = ctx.size();
-        ctx.fill(
-            size.to_rect(),
-            &style::get_color_unchecked(style::PRIMARY_LIGHT),
-        ); 
+        ctx.fill(size.to_rect(), &env.get(style::CHROME_LIGHT));
         self.header.draw(ctx, (5., 5.));
         if let Some(input_widget) = &mut self.input_widget {
             input_widget.paint(ctx, data, env);
@@ -164,23 +194,56 @@ pub struct Sidebar {
 }
 
 impl Sidebar {
-    pub fn new() -> Sidebar {
+    /**
+     * Build the sidebar with all its panels.
+     *
+     * @param ranges: the ranges of the sliders of the Settings panel
+     */
+    pub fn new(ranges: &SettingsRanges) -> Sidebar {
         let mut side_buttons = Vec::new();
         let mut action_buttons = Vec::new();
 
         let mut panels = Vec::new();
 
-        for kind in vec![PanelButton::Toc, PanelButton::Search, PanelButton::Settings] {
-            if let PanelButton::Search = kind {
-                panels.push(WidgetPod::new(
-                    (Panel::new(&&kind.title(), kind.to_widget()))
-                        .with_input_widget()
+        for kind in vec![
+            PanelButton::Toc,
+            PanelButton::Search,
+            PanelButton::Bookmarks,
+            PanelButton::Notes,
+            PanelButton::Settings,
+        ] {
+            match kind {
+                PanelButton::Toc => panels.push(WidgetPod::new(
+                    (Panel::new(&kind.title(), kind.to_widget(ranges)))
+                        .with_input_widget(
+                            EpubData::sidebar_data.then(SidebarData::print_page_input),
+                            "Go to print page",
+                            |ctx, data| {
+                                if let Some(pos) = data.print_page_position() {
+                                    ctx.submit_command(
+                                        INTERNAL_COMMAND.with(InternalUICommand::EpubGoToPos(pos)),
+                                    );
+                                }
+                            },
+                        )
                         .boxed(),
-                ))
-            } else {
-                panels.push(WidgetPod::new(
-                    (Panel::new(&kind.title(), kind.to_widget())).boxed(),
-                ))
+                )),
+                PanelButton::Search => panels.push(WidgetPod::new(
+                    (Panel::new(&kind.title(), kind.to_widget(ranges)))
+                        .with_input_widget(
+                            EpubData::sidebar_data.then(SidebarData::search_input),
+                            "Search",
+                            |ctx, data| {
+                                data.search_string_in_book();
+                                ctx.request_update();
+                                ctx.request_layout();
+                            },
+                        )
+                        .boxed(),
+                )),
+                _ => panels.push(WidgetPod::new(
+                    (Panel::new(&kind.title(), kind.to_widget(ranges))).boxed(),
+                )),
             }
 
             let other_but = IconButton::new(kind).boxed();
@@ -191,6 +254,9 @@ impl Sidebar {
             ActionButton::CloseBook,
             ActionButton::EditBook,
             ActionButton::OCROpen,
+            ActionButton::AddBookmark,
+            ActionButton::ExportAnnotations,
+            ActionButton::ImportAnnotations,
         ] {
             let other_but = IconButton::new(actions).boxed();
             action_buttons.push(WidgetPod::new(other_but));
@@ -234,6 +300,27 @@ impl Widget<EpubData> for Sidebar {
 
                             ctx.request_layout();
                         }
+                        InternalUICommand::ClosePanel => {
+                            if self.opened_tab.take().is_some() {
+                                // the focus may have been in the input of the panel
+                                ctx.submit_command(
+                                    INTERNAL_COMMAND.with(InternalUICommand::FocusPage),
+                                );
+                                ctx.request_layout();
+                            }
+                            ctx.set_handled();
+                        }
+                        InternalUICommand::FocusSearch => {
+                            self.opened_tab = Some(PanelButton::Search);
+                            let search_panel = self.panels[PanelButton::Search as usize].id();
+                            ctx.submit_command(
+                                INTERNAL_COMMAND
+                                    .with(InternalUICommand::FocusInput)
+                                    .to(search_panel),
+                            );
+                            ctx.set_handled();
+                            ctx.request_layout();
+                        }
                         _ => {}
                     }
                 }
@@ -344,7 +431,7 @@ impl Widget<EpubData> for Sidebar {
 
     fn paint(&mut self, ctx: &mut druid::PaintCtx, data: &EpubData, env: &druid::Env) {
         let rect = Size::new(40., ctx.size().height).to_rect();
-        ctx.fill(rect, &style::get_color_unchecked(PRIMARY_DARK));
+        ctx.fill(rect, &env.get(style::CHROME));
 
         for side in self.side_buttons.iter_mut() {
             side.paint(ctx, data, env);
@@ -361,16 +448,270 @@ impl Widget<EpubData> for Sidebar {
             let num = self.opened_tab.clone().unwrap() as usize;
             rect.y0 = num as f64 * ICON_SIZE + 7.;
             rect.y1 = rect.y0 + ICON_SIZE + 7.;
-            ctx.fill(rect, &Color::WHITE);
+            ctx.fill(rect, &env.get(style::CHROME_TEXT));
         }
     }
 }
 
 
+/**
+ * A checkbox toggling an option of the search.
+ */
+fn search_option(
+    label: &str,
+    option: impl Lens<SearchOptions, bool> + 'static,
+) -> impl Widget<EpubData> {
+    Checkbox::new(label.to_string()).lens(
+        EpubData::sidebar_data
+            .then(SidebarData::search_options)
+            .then(option),
+    )
+}
+
+/**
+ * Settings of the text: the font, the line height, the alignment,
+ * the first line indent of the paragraphs and the hyphenation.
+ */
+fn text_settings(ranges: &SettingsRanges) -> impl Widget<EpubData> {
+    // each row sees the font chosen and one of the fonts available
+    let fonts = List::new(|| {
+        Label::dynamic(|(font, family): &(Option<String>, String), _| {
+            if font.as_ref() == Some(family) {
+                format!("\u{2022} {}", family)
+            } else {
+                family.clone()
+            }
+        })
+        .expand_width()
+        .on_click(|_, (font, family): &mut (Option<String>, String), _| {
+            *font = Some(family.clone());
+        })
+    })
+    .lens(lens::Map::new(
+        |data: &EpubData| {
+            (
+                data.epub_settings.font_family.clone(),
+                data.font_families.clone(),
+            )
+        },
+        |data: &mut EpubData, (font, _)| data.epub_settings.font_family = font,
+    ));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_child(Label::new(|data: &EpubData, _env: &_| {
+                    format!(
+                        "Font: {}",
+                        data.epub_settings
+                            .font_family
+                            .as_deref()
+                            .unwrap_or("default")
+                    )
+                }))
+                .with_flex_spacer(1.)
+                .with_child(
+                    Button::new("Default").on_click(|_, data: &mut EpubData, _| {
+                        data.epub_settings.font_family = None;
+                    }),
+                )
+                .expand_width(),
+        )
+        .with_child(
+            Scroll::new(fonts)
+                .vertical()
+                .fix_height(FONT_LIST_HEIGHT)
+                .expand_width(),
+        )
+        .with_spacer(10.)
+        .with_child(Label::new(|data: &EpubData, _env: &_| {
+            format!(
+                "Line height: {number:.prec$}",
+                prec = 2,
+                number = data.epub_settings.line_height
+            )
+        }))
+        .with_child(
+            Slider::new()
+                .with_range(ranges.line_height.min, ranges.line_height.max)
+                .lens(EpubData::epub_settings.then(EpubSettings::line_height))
+                .expand_width(),
+        )
+        .with_spacer(10.)
+        .with_child(Label::new("Alignment"))
+        .with_child(
+            Flex::row()
+                .with_child(Radio::new("Book", None))
+                .with_child(Radio::new("Left", Some(TextAlign::Start)))
+                .with_child(Radio::new("Justified", Some(TextAlign::Justified)))
+                .lens(EpubData::epub_settings.then(EpubSettings::text_align)),
+        )
+        .with_spacer(10.)
+        .with_child(
+            Flex::row()
+                .with_child(Label::new(|data: &EpubData, _env: &_| {
+                    match data.epub_settings.first_line_indent {
+                        Some(indent) => format!("First line indent: {:.2} em", indent),
+                        None => "First line indent: book".to_string(),
+                    }
+                }))
+                .with_flex_spacer(1.)
+                .with_child(Button::new("Book").on_click(|_, data: &mut EpubData, _| {
+                    data.set_first_line_indent(None);
+                }))
+                .expand_width(),
+        )
+        .with_child(
+            Slider::new()
+                .with_range(ranges.first_line_indent.min, ranges.first_line_indent.max)
+                .lens(lens::Map::new(
+                    |data: &EpubData| data.epub_settings.first_line_indent.unwrap_or(0.),
+                    |data: &mut EpubData, indent| data.set_first_line_indent(Some(indent)),
+                ))
+                .expand_width(),
+        )
+        .with_spacer(10.)
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Hyphenation"))
+                .with_flex_spacer(1.)
+                .with_child(Switch::new().lens(lens::Map::new(
+                    |data: &EpubData| data.epub_settings.hyphenation,
+                    |data: &mut EpubData, hyphenation| data.set_hyphenation(hyphenation),
+                )))
+                .expand_width(),
+        )
+}
+
+/**
+ * Choice of the theme among the presets and the custom themes,
+ * and edition of the colors of the current theme, which can be saved as a custom theme.
+ */
+fn theme_settings() -> impl Widget<EpubData> {
+    let mut presets = Flex::row();
+    for preset in Theme::presets() {
+        presets.add_flex_child(
+            Button::new(preset.name.clone())
+                .on_click(move |_, data: &mut EpubData, _| {
+                    data.epub_settings.theme = preset.clone();
+                })
+                .expand_width(),
+            1.,
+        );
+    }
+
+    // each row sees the current theme and one of the custom themes
+    let custom_themes = List::new(|| {
+        Button::new(|(_, theme): &(Theme, Theme), _: &Env| theme.name.clone())
+            .on_click(|_, (current, theme): &mut (Theme, Theme), _| {
+                *current = theme.clone();
+            })
+            .expand_width()
+    })
+    .lens(EpubData::epub_settings.then(lens::Map::new(
+        |settings: &EpubSettings| (settings.theme.clone(), settings.custom_themes.clone()),
+        |settings: &mut EpubSettings, (theme, custom_themes)| {
+            settings.theme = theme;
+            settings.custom_themes = custom_themes;
+        },
+    )));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::new("Theme"))
+        .with_child(presets.expand_width())
+        .with_child(custom_themes)
+        .with_spacer(5.)
+        .with_child(theme_field("Name", Theme::name))
+        .with_child(theme_field("Background", Theme::background))
+        .with_child(theme_field("Text", Theme::text))
+        .with_child(theme_field("Links", Theme::link))
+        .with_child(theme_field("Search results", Theme::highlight))
+        .with_child(theme_field("Sidebar", Theme::chrome))
+        .with_child(theme_field("Panels", Theme::chrome_light))
+        .with_child(theme_field("Sidebar text", Theme::chrome_text))
+        .with_child(
+            Label::dynamic(|data: &EpubData, _| {
+                if data.epub_settings.theme.is_valid() {
+                    String::new()
+                } else {
+                    "Invalid color".to_string()
+                }
+            })
+            .with_text_color(Color::RED),
+        )
+        .with_child(
+            Flex::row()
+                .with_flex_child(
+                    Button::new("Save theme")
+                        .on_click(|_, data: &mut EpubData, _| {
+                            data.epub_settings.save_custom_theme();
+                        })
+                        .expand_width(),
+                    1.,
+                )
+                .with_flex_child(
+                    Button::new("Delete theme")
+                        .on_click(|_, data: &mut EpubData, _| {
+                            data.epub_settings.remove_custom_theme();
+                        })
+                        .expand_width(),
+                    1.,
+                )
+                .expand_width(),
+        )
+}
+
+/**
+ * A text input for a field of the current theme, like a color in hex.
+ */
+fn theme_field(label: &str, field: impl Lens<Theme, String> + 'static) -> impl Widget<EpubData> {
+    Flex::row()
+        .with_child(Label::new(label.to_string()).fix_width(THEME_LABEL_WIDTH))
+        .with_flex_child(
+            TextBox::new()
+                .lens(
+                    EpubData::epub_settings
+                        .then(EpubSettings::theme)
+                        .then(field),
+                )
+                .expand_width(),
+            1.,
+        )
+        .expand_width()
+}
+
+/**
+ * Search again when an option of the search changes.
+ */
+struct SearchOptionsController;
+
+impl<W: Widget<EpubData>> Controller<EpubData, W> for SearchOptionsController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut EpubData,
+        env: &Env,
+    ) {
+        let options = EpubData::sidebar_data.then(SidebarData::search_options);
+        let old_options = options.get(data);
+        child.event(ctx, event, data, env);
+        if options.get(data) != old_options {
+            data.search_string_in_book();
+            ctx.request_layout();
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum PanelButton {
     Toc,
     Search,
+    Bookmarks,
+    Notes,
     Settings,
 }
 impl PanelButton {
@@ -378,11 +719,13 @@ impl PanelButton {
         match self {
             PanelButton::Toc => "Table of Contents".to_string(),
             PanelButton::Search => "Search".to_string(),
+            PanelButton::Bookmarks => "Bookmarks".to_string(),
+            PanelButton::Notes => "Notes".to_string(),
             PanelButton::Settings => "Settings".to_string(),
         }
     }
 
-    pub fn to_widget(&self) -> Box<dyn Widget<EpubData>> {
+    pub fn to_widget(&self, ranges: &SettingsRanges) -> Box<dyn Widget<EpubData>> {
         match self {
             PanelButton::Toc => Scroll::new(
                 List::new(|| ClickableLabel::new())
@@ -390,14 +733,91 @@ impl PanelButton {
             )
             .vertical()
             .boxed(),
-            PanelButton::Search => Box::new(
-                Scroll::new(
-                    List::new(|| ClickableLabel::new())
-                        .lens(EpubData::sidebar_data.then(SidebarData::search_results)),
-                )
+            PanelButton::Search => {
+                // the results are ranked, and shown a page at a time
+                let pages = Flex::row()
+                    .with_child(Button::new("<").on_click(|ctx, data: &mut EpubData, _| {
+                        data.previous_search_page();
+                        ctx.request_layout();
+                    }))
+                    .with_flex_child(
+                        Label::dynamic(|data: &EpubData, _| data.search_page_label()).center(),
+                        1.,
+                    )
+                    .with_child(Button::new(">").on_click(|ctx, data: &mut EpubData, _| {
+                        data.next_search_page();
+                        ctx.request_layout();
+                    }));
+                let options = Flex::column()
+                    .cross_axis_alignment(CrossAxisAlignment::Start)
+                    .with_child(
+                        Flex::row()
+                            .with_child(search_option("Match case", SearchOptions::case_sensitive))
+                            .with_default_spacer()
+                            .with_child(search_option("Whole word", SearchOptions::whole_word)),
+                    )
+                    .with_child(
+                        Flex::row()
+                            .with_child(search_option("Regex", SearchOptions::regex))
+                            .with_default_spacer()
+                            .with_child(search_option(
+                                "Ignore accents",
+                                SearchOptions::ignore_accents,
+                            )),
+                    )
+                    .controller(SearchOptionsController);
+                let find = Flex::row()
+                    .with_flex_child(
+                        Button::new("Previous").on_click(|ctx, _, _| {
+                            ctx.submit_command(
+                                INTERNAL_COMMAND
+                                    .with(InternalUICommand::EpubGoToSearchResult(false)),
+                            );
+                        }),
+                        1.,
+                    )
+                    .with_default_spacer()
+                    .with_flex_child(
+                        Button::new("Next").on_click(|ctx, _, _| {
+                            ctx.submit_command(
+                                INTERNAL_COMMAND
+                                    .with(InternalUICommand::EpubGoToSearchResult(true)),
+                            );
+                        }),
+                        1.,
+                    );
+                Flex::column()
+                    .with_child(options)
+                    .with_child(find)
+                    .with_flex_child(
+                        Scroll::new(
+                            List::new(|| ClickableLabel::new())
+                                .lens(EpubData::sidebar_data.then(SidebarData::search_results)),
+                        )
+                        .vertical(),
+                        1.,
+                    )
+                    .with_child(pages)
+                    .boxed()
+            }
+            PanelButton::Bookmarks => {
+                Scroll::new(List::new(|| ClickableLabel::new()).lens(lens::Map::new(
+                    |data: &EpubData| data.bookmark_entries(),
+                    // the entries are only shown, bookmarks are added with the action button
+                    |_: &mut EpubData, _| {},
+                )))
+                .vertical()
+                .boxed()
+            }
+            PanelButton::Notes => {
+                Scroll::new(List::new(|| ClickableLabel::new()).lens(lens::Map::new(
+                    |data: &EpubData| data.note_entries(),
+                    // notes are edited from the page
+                    |_: &mut EpubData, _| {},
+                )))
                 .vertical()
-                .boxed(),
-            ),
+                .boxed()
+            }
             
             PanelButton::Settings => Scroll::new(
                 Flex::column()
@@ -433,7 +853,7 @@ impl PanelButton {
                             }))
                             .with_child(
                                 Slider::new()
-                                    .with_range(MIN_FONT_SIZE, MAX_FONT_SIZE)
+                                    .with_range(ranges.font_size.min, ranges.font_size.max)
                                     .lens(EpubData::epub_settings.then(EpubSettings::font_size))
                                     .expand_width(),
                             ),
@@ -450,7 +870,7 @@ impl PanelButton {
                             }))
                             .with_child(
                                 Slider::new()
-                                    .with_range(MIN_MARGIN, MAX_MARGIN)
+                                    .with_range(ranges.margin.min, ranges.margin.max)
                                     .lens(EpubData::epub_settings.then(EpubSettings::margin))
                                     .expand_width(),
                             ),
@@ -467,13 +887,43 @@ impl PanelButton {
                             }))
                             .with_child(
                                 Slider::new()
-                                    .with_range(MIN_PARAGRAPH_SPACING, MAX_PARAGRAPH_SPACING)
+                                    .with_range(
+                                        ranges.paragraph_spacing.min,
+                                        ranges.paragraph_spacing.max,
+                                    )
                                     .lens(
                                         EpubData::epub_settings
                                             .then(EpubSettings::paragraph_spacing),
                                     )
                                     .expand_width(),
                             ),
+                    )
+                    .with_spacer(10.)
+                    .with_child(text_settings(ranges))
+                    .with_spacer(10.)
+                    .with_child(theme_settings())
+                    .with_spacer(10.)
+                    .with_child(
+                        Button::new("Keyboard shortcuts")
+                            .on_click(|ctx, _, _| {
+                                ctx.submit_command(
+                                    INTERNAL_COMMAND.with(InternalUICommand::OpenKeymapEditor),
+                                );
+                            })
+                            .expand_width(),
+                    )
+                    .with_spacer(10.)
+                    .with_child(
+                        Flex::row()
+                            .with_child(Label::new("Publisher styles"))
+                            .with_flex_spacer(1.)
+                            .with_child(Switch::new().lens(lens::Map::new(
+                                |data: &EpubData| data.epub_settings.publisher_styles,
+                                |data: &mut EpubData, publisher_styles| {
+                                    data.set_publisher_styles(publisher_styles)
+                                },
+                            )))
+                            .expand_width(),
                     ),
             )
             .vertical()
@@ -486,6 +936,8 @@ impl ButtonTrait for PanelButton {
         match self {
             PanelButton::Toc => druid_material_icons::normal::communication::LIST_ALT,
             PanelButton::Search => druid_material_icons::normal::action::FIND_IN_PAGE,
+            PanelButton::Bookmarks => druid_material_icons::normal::action::BOOKMARKS,
+            PanelButton::Notes => druid_material_icons::normal::communication::COMMENT,
             PanelButton::Settings => druid_material_icons::normal::action::SETTINGS,
         }
     }
@@ -493,6 +945,8 @@ impl ButtonTrait for PanelButton {
         match self {
             PanelButton::Toc => "Table of Contents".to_string(),
             PanelButton::Search => "Search".to_string(),
+            PanelButton::Bookmarks => "Bookmarks".to_string(),
+            PanelButton::Notes => "Notes".to_string(),
             PanelButton::Settings => "Settings".to_string(),
         }
     }
@@ -505,6 +959,9 @@ pub enum ActionButton {
     CloseBook,
     EditBook,
     OCROpen,
+    AddBookmark,
+    ExportAnnotations,
+    ImportAnnotations,
 }
 
 impl ButtonTrait for ActionButton {
@@ -513,6 +970,9 @@ impl ButtonTrait for ActionButton {
             ActionButton::CloseBook => druid_material_icons::normal::action::EXIT_TO_APP,
             ActionButton::EditBook => druid_material_icons::normal::editor::EDIT_NOTE,
             ActionButton::OCROpen => druid_material_icons::normal::image::IMAGE_SEARCH,
+            ActionButton::AddBookmark => druid_material_icons::normal::action::BOOKMARK_BORDER,
+            ActionButton::ExportAnnotations => druid_material_icons::normal::file::FILE_DOWNLOAD,
+            ActionButton::ImportAnnotations => druid_material_icons::normal::file::FILE_UPLOAD,
         }
     }
     fn hint(&self) -> String {
@@ -520,6 +980,9 @@ impl ButtonTrait for ActionButton {
             ActionButton::CloseBook => "Close Book".to_string(),
             ActionButton::EditBook => "Edit Book".to_string(),
             ActionButton::OCROpen => "Search using OCR".to_string(),
+            ActionButton::AddBookmark => "Add Bookmark".to_string(),
+            ActionButton::ExportAnnotations => "Export Annotations".to_string(),
+            ActionButton::ImportAnnotations => "Import Annotations".to_string(),
         }
     }
     fn command(&self) -> InternalUICommand {
@@ -527,6 +990,9 @@ impl ButtonTrait for ActionButton {
             ActionButton::CloseBook => InternalUICommand::GoToMenu,
             ActionButton::EditBook => InternalUICommand::OpenEditDialog,
             ActionButton::OCROpen => InternalUICommand::OpenOCRDialog,
+            ActionButton::AddBookmark => InternalUICommand::AddBookmark,
+            ActionButton::ExportAnnotations => InternalUICommand::ExportAnnotations,
+            ActionButton::ImportAnnotations => InternalUICommand::ImportAnnotations,
         }
     }
 }
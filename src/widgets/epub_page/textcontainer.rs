@@ -1,37 +1,164 @@
 use std::ops::Range;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
 
 use druid::im::Vector;
-use druid::piet::{CairoText, Text, TextLayoutBuilder};
+use druid::piet::{Device, PietText, Text, TextLayout as _, TextLayoutBuilder};
 use druid::{
-    BoxConstraints, Color, Data, Env, Event, EventCtx, FontDescriptor, FontFamily, LayoutCtx,
-    LifeCycle, LifeCycleCtx, LinearGradient, PaintCtx, Point, Rect, RenderContext, Size,
-    TextLayout, UnitPoint, UpdateCtx, Widget, WidgetExt, WidgetPod,
+    Application, BoxConstraints, Color, Command, Data, Env, Event, EventCtx, ExtEventSink,
+    FontDescriptor, FontFamily, HotKey, ImageBuf, LayoutCtx, LifeCycle, LifeCycleCtx,
+    LinearGradient, PaintCtx, Point, Rect, RenderContext, Size, SysMods, Target, TextLayout,
+    TimerToken, UnitPoint, UpdateCtx, Widget, WidgetExt, WidgetId, WidgetPod, WindowConfig,
+    WindowId,
 };
+use epub::doc::EpubDoc;
 
 use crate::{
-    core::constants::commands::{InternalUICommand, INTERNAL_COMMAND},
-    data::epub::{
-        settings::{EpubSettings, VisualizationMode},
-        EpubData,
+    core::{
+        constants::commands::{InternalUICommand, INTERNAL_COMMAND},
+        style,
+    },
+    css::{BlockStyle, TextAlign},
+    data::{
+        epub::{
+            settings::{EpubSettings, VisualizationMode},
+            theme::Theme,
+            EpubData,
+        },
+        keymap::is_text_editing_key,
+        Highlight, HighlightColor,
     },
     dom::Renderable,
-    widgets::RoundButton,
+    hyphenate::remove_soft_hyphens,
+    widgets::{build_note_ui, common::open_floating_window, RoundButton},
+};
+
+use super::page_item::{PageItem, ParagraphText};
+use super::pagination::{
+    count_pages, fill_page, page_before, page_index, PageSlice, PaginationCache, PaginationKey,
 };
 
-use druid::text::{RichText, Selection};
+use druid::commands::CLOSE_WINDOW;
+use druid::text::{RichText, TextStorage};
+use druid::widget::{Flex, LineBreaking, RawLabel};
 
 const TEXT_Y_PADDING: f64 = 15.0;
+// space left at the bottom of the page for the page label
+const PAGE_BOTTOM_PADDING: f64 = 50.0;
 
 // constants for Page Label in PageSplitter
-const PAGE_LABEL_DISTANCE_FROM_CENTER: f64 = 15.;
 const PAGE_LABEL_Y_PADDING: f64 = 20.;
 
+// delay before the pagination in background starts, so resizing the window
+// does not start a pagination for each size
+const PAGINATION_DELAY: Duration = Duration::from_millis(300);
+
 use druid_material_icons::normal::action::{ARROW_CIRCLE_LEFT, ARROW_CIRCLE_RIGHT};
+use druid_material_icons::normal::communication::COMMENT;
+use druid_material_icons::normal::content::CONTENT_COPY;
+use druid_material_icons::normal::image::LENS;
+use druid_material_icons::normal::navigation::ARROW_BACK;
+
+// schemes of the links opened outside of the reader
+const EXTERNAL_LINK_SCHEMES: [&str; 3] = ["http://", "https://", "mailto:"];
+
+const NOTE_POPOVER_WIDTH: f64 = 350.;
+
+const SELECTION_COLOR: Color = Color::rgba8(0, 120, 215, 80);
+const NOTE_MARKER_RADIUS: f64 = 5.;
+const NOTE_EDITOR_SIZE: Size = Size::new(400., 300.);
 
 pub struct PageSplitter {
-    text: Vec<TextLayout<RichText>>,
+    text: Vec<PageItem>,
     visualized_range: Range<usize>,
-    search_selection: Option<(usize, Selection)>,
+    // characters of the first element of the page preceding the start of the page
+    start_offset: usize,
+    // the richtext and the range of characters of the search result shown
+    search_selection: Option<(usize, Range<usize>)>,
+    // index and origin of the elements drawn in the last paint, with their visible part
+    drawn_items: Vec<(usize, Point, Rect)>,
+    // center of the markers of the notes drawn in the last paint, with the index of the highlight
+    note_markers: Vec<(Point, usize)>,
+
+    // popover shown over the page (a note or the actions on the selected text),
+    // with the position of the click which opened it
+    popover: Option<WindowId>,
+    last_click_window_pos: Point,
+    // text selected with the mouse, from where the drag started to where it is:
+    // each end is the index of the element and a byte offset of its text
+    selection: Option<((usize, usize), (usize, usize))>,
+
+    // pages of the whole book, counted one chapter at a time by a background thread,
+    // with the key of the pagination running and the flag stopping it
+    page_size: Size,
+    paginations: PaginationCache,
+    pagination_timer: Option<TimerToken>,
+    pagination_worker: Option<(PaginationKey, Arc<AtomicBool>)>,
+
+    // the book whose embedded fonts are loaded in the text backend
+    fonts_book: String,
+}
+
+/**
+ * Popover with the actions on the selected text: copy it, annotate it or highlight it.
+ * The commands are sent to every window, since the popover is a window itself.
+ */
+fn selection_popover() -> impl Widget<()> {
+    let mut actions = Flex::row().with_child(
+        RoundButton::new(CONTENT_COPY)
+            .with_click_handler(|ctx, _, _| {
+                ctx.submit_command(
+                    INTERNAL_COMMAND
+                        .with(InternalUICommand::CopySelection)
+                        .to(Target::Global),
+                );
+            })
+            .with_color(style::TEXT),
+    );
+    actions.add_child(
+        RoundButton::new(COMMENT)
+            .with_click_handler(|ctx, _, _| {
+                ctx.submit_command(
+                    INTERNAL_COMMAND
+                        .with(InternalUICommand::AnnotateSelection)
+                        .to(Target::Global),
+                );
+            })
+            .with_color(style::TEXT),
+    );
+    for color in HighlightColor::ALL {
+        actions.add_child(
+            RoundButton::new(LENS)
+                .with_click_handler(move |ctx, _, _| {
+                    ctx.submit_command(
+                        INTERNAL_COMMAND
+                            .with(InternalUICommand::HighlightSelection(color))
+                            .to(Target::Global),
+                    );
+                })
+                .with_color(color.color().with_alpha(1.)),
+        );
+    }
+    actions
+        .padding(5.)
+        .background(style::BACKGROUND)
+        .border(style::TEXT, 1.)
+}
+
+/**
+ * Popover showing the text of a footnote over the page.
+ */
+fn note_popover() -> impl Widget<RichText> {
+    RawLabel::new()
+        .with_line_break_mode(LineBreaking::WordWrap)
+        .with_text_color(style::TEXT)
+        .fix_width(NOTE_POPOVER_WIDTH)
+        .padding(10.)
+        .background(style::BACKGROUND)
+        .border(style::TEXT, 1.)
 }
 
 impl PageSplitter {
@@ -39,180 +166,938 @@ impl PageSplitter {
         Self {
             text: Vec::new(),
             visualized_range: 0..0,
+            start_offset: 0,
             search_selection: None,
+            drawn_items: Vec::new(),
+            note_markers: Vec::new(),
+            popover: None,
+            last_click_window_pos: Point::ZERO,
+            selection: None,
+            page_size: Size::ZERO,
+            paginations: PaginationCache::default(),
+            pagination_timer: None,
+            pagination_worker: None,
+            fonts_book: String::new(),
+        }
+    }
+
+    fn show_note(&mut self, ctx: &mut EventCtx, note: RichText, env: &Env) {
+        self.close_popover(ctx);
+        let position = self.last_click_window_pos + druid::Vec2::new(0., TEXT_Y_PADDING);
+        self.popover = Some(open_floating_window(
+            ctx,
+            note_popover(),
+            note,
+            position,
+            env,
+        ));
+    }
+
+    fn close_popover(&mut self, ctx: &mut EventCtx) {
+        if let Some(win_id) = self.popover.take() {
+            ctx.submit_command(CLOSE_WINDOW.to(win_id));
+        }
+    }
+
+    fn show_selection_actions(&mut self, ctx: &mut EventCtx, env: &Env) {
+        self.close_popover(ctx);
+        let position = self.last_click_window_pos + druid::Vec2::new(0., TEXT_Y_PADDING);
+        self.popover = Some(open_floating_window(
+            ctx,
+            selection_popover(),
+            (),
+            position,
+            env,
+        ));
+    }
+
+    /**
+     * Open the popup to write the note of a highlight.
+     * The popup edits the data of the book, which is saved when it is closed.
+     *
+     * @param highlight: the index of the highlight
+     */
+    fn open_note_editor(
+        &mut self,
+        ctx: &mut EventCtx,
+        data: &EpubData,
+        highlight: usize,
+        env: &Env,
+    ) {
+        self.close_popover(ctx);
+        // the highlighted text, if the highlight belongs to the chapter shown
+        let passage = data
+            .highlights
+            .get(highlight)
+            .filter(|h| h.chapter == data.page_position.chapter())
+            .and_then(|h| {
+                let element = self.text.get(h.richtext_number)?;
+                let range = element.byte_offset(h.start)..element.byte_offset(h.end);
+                element.text().get(range).map(remove_soft_hyphens)
+            })
+            .unwrap_or_default();
+
+        ctx.new_sub_window(
+            WindowConfig::default().window_size(NOTE_EDITOR_SIZE),
+            build_note_ui(highlight, passage),
+            data.clone(),
+            env.clone(),
+        );
+    }
+
+    /**
+     * Find the position of the text closest to the given point of the widget,
+     * among the elements drawn in the last paint.
+     *
+     * @return the index of the element and the byte offset of its text
+     */
+    fn text_position_at(&self, pos: Point) -> Option<(usize, usize)> {
+        let distance = |visible: &Rect| {
+            let dx = (visible.x0 - pos.x).max(pos.x - visible.x1).max(0.);
+            let dy = (visible.y0 - pos.y).max(pos.y - visible.y1).max(0.);
+            dx + dy
+        };
+        let (item, origin, visible) = self
+            .drawn_items
+            .iter()
+            .min_by(|(_, _, a), (_, _, b)| distance(a).total_cmp(&distance(b)))?;
+
+        // the lines of the element shown in other pages can not be selected
+        let pos = Point::new(
+            pos.x.clamp(visible.x0, visible.x1),
+            pos.y.clamp(visible.y0, visible.y1),
+        );
+        let element = self.text.get(*item)?;
+        Some((*item, element.offset_for_pos(pos - origin.to_vec2())))
+    }
+
+    /**
+     * The selected range of text of each element, in reading order.
+     */
+    fn selected_ranges(&self) -> Vec<(usize, Range<usize>)> {
+        let (start, end) = match self.selection {
+            Some((anchor, active)) if anchor <= active => (anchor, active),
+            Some((anchor, active)) => (active, anchor),
+            None => return Vec::new(),
+        };
+        (start.0..=end.0)
+            .filter_map(|item| {
+                let len = self.text.get(item)?.text().len();
+                let from = if item == start.0 { start.1 } else { 0 };
+                let to = if item == end.0 { end.1 } else { len };
+                (from < to).then(|| (item, from..to))
+            })
+            .collect()
+    }
+
+    /**
+     * Copy the selected text to the clipboard, one element per line.
+     */
+    fn copy_selection(&self) {
+        let text = self
+            .selected_ranges()
+            .into_iter()
+            .filter_map(|(item, range)| self.text[item].text().get(range).map(remove_soft_hyphens))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !text.is_empty() {
+            Application::global().clipboard().put_string(text);
+        }
+    }
+
+    /**
+     * Highlight the selected text, saving a highlight for each element selected.
+     *
+     * @return true if some text was highlighted
+     */
+    fn highlight_selection(&mut self, data: &mut EpubData, color: HighlightColor) -> bool {
+        let chapter = data.page_position.chapter();
+        let highlights: Vec<Highlight> = self
+            .selected_ranges()
+            .into_iter()
+            .map(|(item, range)| {
+                let element = &self.text[item];
+                let range = element.char_offset(range.start)..element.char_offset(range.end);
+                Highlight::new(chapter, item, range, color)
+            })
+            .collect();
+        self.selection = None;
+        data.highlights.extend(highlights.iter().cloned());
+        !highlights.is_empty()
+    }
+
+    /**
+     * Find the link under the given point of the widget.
+     *
+     * @return the command associated to the link, if any
+     */
+    fn link_at(&self, pos: Point) -> Option<Command> {
+        self.drawn_items.iter().find_map(|(i, origin, visible)| {
+            let item = self.text.get(*i)?;
+            if Rect::from_origin_size(*origin, item.size())
+                .intersect(*visible)
+                .contains(pos)
+            {
+                item.link_for_pos(pos - origin.to_vec2())
+            } else {
+                None
+            }
+        })
+    }
+    /**
+     * Create the layout of a text of the chapter, with the font chosen by the user.
+     */
+    fn text_layout<T: TextStorage>(text: T, data: &EpubData) -> TextLayout<T> {
+        let family = match &data.epub_settings.font_family {
+            Some(family) => FontFamily::new_unchecked(family.as_str()),
+            None => FontFamily::SERIF,
+        };
+        let mut text_layout = TextLayout::new();
+        text_layout.set_text(text);
+        text_layout.set_font(FontDescriptor::new(family));
+        text_layout.set_text_size(data.epub_settings.font_size);
+        text_layout.set_text_color(style::TEXT);
+        text_layout
+    }
+
+    /**
+     * The layout of a paragraph, with the alignment chosen by the user if any.
+     * Only the paragraphs aligned at the start or justified follow it,
+     * so centered titles stay centered.
+     */
+    fn block_style(block: &BlockStyle, data: &EpubData) -> BlockStyle {
+        let mut block = block.clone();
+        if let Some(text_align) = data.epub_settings.text_align {
+            if matches!(block.text_align, TextAlign::Start | TextAlign::Justified) {
+                block.text_align = text_align;
+            }
+        }
+        block
+    }
+
+    /**
+     * Load the fonts embedded in the book in the text backend, once for each book,
+     * so they can be chosen in the settings and used by the styles of the book.
+     * The text of a font which cannot be loaded is shown with the default font.
+     */
+    fn load_embedded_fonts(&mut self, text: &mut PietText, data: &EpubData) {
+        let book = data.get_epub_path();
+        if self.fonts_book == book {
+            return;
         }
+        for font in data.embedded_fonts().iter() {
+            let _ = text.load_font(&font.data);
+        }
+        self.fonts_book = book;
+    }
+
+    fn generate_text(&mut self, chapter: &Vector<Renderable>, data: &EpubData) {
+        self.text = Self::page_items(chapter, data, |src| data.get_image(src));
     }
-    fn generate_text(&mut self, chapter: &Vector<Renderable>, font_size: f64) {
-        self.text.clear();
+
+    /**
+     * Create the elements of a page for the content of a chapter.
+     *
+     * @param chapter: the content of the chapter
+     * @param load_image: the function loading the images of the chapter
+     */
+    fn page_items(
+        chapter: &Vector<Renderable>,
+        data: &EpubData,
+        load_image: impl Fn(&str) -> Option<ImageBuf>,
+    ) -> Vec<PageItem> {
+        let mut items = Vec::with_capacity(chapter.len());
 
         for renderable in chapter.iter() {
-            match renderable {
-                Renderable::Image(_) => {}
-                Renderable::Text(text) => {
-                    let mut text_layout = TextLayout::new();
-                    text_layout.set_text(text.clone());
-                    text_layout.set_font(FontDescriptor::new(FontFamily::SERIF));
-                    text_layout.set_text_size(font_size);
-                    text_layout.set_text_color(Color::BLACK);
-                    self.text.push(text_layout);
+            let item = match renderable {
+                Renderable::Image(src) => {
+                    // Images which cannot be loaded are kept as empty elements,
+                    // so the indexes of the chapter are preserved
+                    let image = load_image(src).unwrap_or_else(ImageBuf::empty);
+                    PageItem::new_image(image)
                 }
-            }
+                Renderable::Text(text, block) => PageItem::new_text(
+                    Self::text_layout(ParagraphText::new(text.clone()), data),
+                    None,
+                    Self::block_style(block, data),
+                )
+                .with_line_height(data.epub_settings.line_height),
+                Renderable::ListItem(marker, text, block) => PageItem::new_text(
+                    Self::text_layout(ParagraphText::new(text.clone()), data),
+                    Some(Self::text_layout(marker.clone(), data)),
+                    Self::block_style(block, data),
+                )
+                .with_line_height(data.epub_settings.line_height),
+                Renderable::Table(rows, block) => {
+                    let rows = rows
+                        .iter()
+                        .map(|row| {
+                            row.iter()
+                                .map(|cell| Self::text_layout(cell.clone(), data))
+                                .collect()
+                        })
+                        .collect();
+                    PageItem::new_table(rows, block.clone())
+                }
+                Renderable::Rule => PageItem::new_rule(),
+            };
+            items.push(item);
         }
+        items
     }
 
     fn wrap_label_size(
         &mut self,
         size: &Size,
-        text: &mut CairoText,
+        text: &mut PietText,
+        margin: f64,
+        env: &Env,
+    ) -> Size {
+        Self::wrap_items(&mut self.text, size, text, margin, env)
+    }
+
+    fn wrap_items(
+        items: &mut [PageItem],
+        size: &Size,
+        text: &mut PietText,
         margin: f64,
         env: &Env,
     ) -> Size {
         let mut ret_size = Size::ZERO;
         // wrap text as half of the page width
         let width = size.width / 2.;
+        let max_height = size.height - PAGE_BOTTOM_PADDING - TEXT_Y_PADDING;
 
-        for t in self.text.iter_mut() {
-            t.set_wrap_width(width - margin * 2.);
-            t.rebuild_if_needed(text, env);
+        for t in items.iter_mut() {
+            t.wrap(width - margin * 2., max_height, text, env);
             ret_size += t.size();
         }
         ret_size
     }
 
-    fn next_page(&mut self, mut current_height: f64, epub_settings: &EpubSettings) -> bool {
-        let mut i = self.visualized_range.start;
-        current_height = if epub_settings.visualization_mode == VisualizationMode::TwoPage {
-            current_height * 2.
+    /**
+     * Count the pages of a chapter, laying it out like the chapter shown.
+     *
+     * @param data: the book, with the settings of the pagination
+     * @param chapter: the index of the chapter in the spine
+     * @param page_size: the size of the page
+     */
+    fn chapter_pages(
+        data: &EpubData,
+        chapter: usize,
+        page_size: Size,
+        text: &mut PietText,
+        env: &Env,
+    ) -> usize {
+        // chapters which cannot be read are shown as an empty page
+        match data.get_chapter(chapter) {
+            Some((path, renderables)) => {
+                let mut items =
+                    Self::page_items(&renderables, data, |src| data.get_chapter_image(&path, src));
+                Self::wrap_items(&mut items, &page_size, text, data.epub_settings.margin, env);
+                count_pages(
+                    &items,
+                    page_size.height - PAGE_BOTTOM_PADDING,
+                    data.epub_settings.paragraph_spacing,
+                )
+            }
+            None => 1,
+        }
+    }
+
+    fn stop_pagination(&mut self) {
+        if let Some((_, stop)) = self.pagination_worker.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /**
+     * The labels of the pages shown, with their number in the whole book,
+     * the progress of the reading and the print page, if known.
+     * Until the whole book is paginated, the pages of the chapter are shown instead.
+     *
+     * @return one label for each page shown
+     */
+    fn page_labels(&self, data: &EpubData) -> Vec<String> {
+        let height = self.page_size.height - PAGE_BOTTOM_PADDING;
+        let spacing = data.epub_settings.paragraph_spacing;
+        // the current chapter is counted as shown, since it can be edited
+        let chapter_pages = count_pages(&self.text, height, spacing);
+        let page = page_index(&self.text, self.page_start(), height, spacing);
+        let book_pages = self.paginations.current().and_then(|pagination| {
+            pagination.book_pages(data.page_position.chapter(), chapter_pages)
+        });
+
+        let pages_shown = if data.epub_settings.visualization_mode == VisualizationMode::TwoPage {
+            2
         } else {
-            current_height
+            1
         };
-        while i < self.text.len()
-            && current_height - self.text[i].size().height + epub_settings.paragraph_spacing > 0.
-        {
-            current_height -= self.text[i].size().height + epub_settings.paragraph_spacing;
-            i += 1;
+        let mut labels: Vec<String> = (page..page + pages_shown)
+            .map(|page| match book_pages {
+                Some((before, total)) => {
+                    let page = (before + page + 1).min(total);
+                    format!("Page {} of {} ({}%)", page, total, page * 100 / total)
+                }
+                None => format!(
+                    "Page {} of {} of the chapter",
+                    (page + 1).min(chapter_pages),
+                    chapter_pages
+                ),
+            })
+            .collect();
+
+        // the page of the printed edition, if the book lists them
+        if let Some(print_page) = data.print_page() {
+            labels[0] += &format!(" - Print page {}", print_page);
+        }
+        labels
+    }
+
+    /**
+     * Move to the page starting at the given position of the chapter.
+     *
+     * @param item: the index of the element
+     * @param offset: the characters of the element preceding the position
+     */
+    fn go_to(&mut self, item: usize, offset: usize) {
+        self.visualized_range = item..item;
+        self.start_offset = offset;
+    }
+
+    /**
+     * The element and the line where the page starts.
+     * The line is the one containing the offset of the page, so the same text
+     * is found again when the element is laid out with another width.
+     */
+    fn page_start(&self) -> (usize, usize) {
+        let item = self.visualized_range.start;
+        let line = self
+            .text
+            .get(item)
+            .map(|element| element.line_for_offset(element.byte_offset(self.start_offset)))
+            .unwrap_or(0);
+        (item, line)
+    }
+
+    fn set_page_start(&mut self, (item, line): (usize, usize)) {
+        let offset = self
+            .text
+            .get(item)
+            .map(|element| element.char_offset(element.line_start(line)))
+            .unwrap_or(0);
+        self.go_to(item, offset);
+    }
+
+    fn next_page(&mut self, height: f64, epub_settings: &EpubSettings) -> bool {
+        let spacing = epub_settings.paragraph_spacing;
+        let (_, mut next) = fill_page(&self.text, self.page_start(), height, spacing);
+        if epub_settings.visualization_mode == VisualizationMode::TwoPage {
+            next = fill_page(&self.text, next, height, spacing).1;
         }
 
-        // If we reached the end of the text elements, don't update the start_range
-        if i >= self.text.len() {
+        // If we reached the end of the text elements, don't update the start of the page
+        if next.0 >= self.text.len() {
             return false;
         }
-        // Update the start_range to the next page
-        self.visualized_range = i..i;
+        // Update the start of the page to the next page
+        self.set_page_start(next);
         true
     }
 
-    fn prev_page(&mut self, mut current_height: f64, epub_settings: &EpubSettings) -> bool {
-        if self.visualized_range.start == 0 {
+    fn prev_page(&mut self, height: f64, epub_settings: &EpubSettings) -> bool {
+        let start = self.page_start();
+        if start == (0, 0) {
             return false;
         }
-        current_height = if epub_settings.visualization_mode == VisualizationMode::TwoPage {
-            current_height * 2.
-        } else {
-            current_height
-        };
-        // Calculate the total size of the text elements starting from the current start_range
-        let mut i = self.visualized_range.start;
-        while i > 0
-            && current_height - self.text[i].size().height + epub_settings.paragraph_spacing > 0.
-        {
-            current_height -= self.text[i].size().height + epub_settings.paragraph_spacing;
-            i -= 1;
+        let spacing = epub_settings.paragraph_spacing;
+        let mut previous = page_before(&self.text, start, height, spacing);
+        if epub_settings.visualization_mode == VisualizationMode::TwoPage {
+            previous = page_before(&self.text, previous, height, spacing);
         }
 
-        // Update the start_range to the previous page
-        self.visualized_range = i..i;
-        return true;
+        // Update the start of the page to the previous page
+        self.set_page_start(previous);
+        true
+    }
+
+    /**
+     * Move to the last page of the chapter, or to the last two pages
+     * when two pages are shown.
+     */
+    fn go_to_last_page(&mut self, height: f64, epub_settings: &EpubSettings) {
+        let spacing = epub_settings.paragraph_spacing;
+        let mut start = page_before(&self.text, (self.text.len(), 0), height, spacing);
+        if epub_settings.visualization_mode == VisualizationMode::TwoPage {
+            start = page_before(&self.text, start, height, spacing);
+        }
+        self.set_page_start(start);
+    }
+
+    /**
+     * Whether the given position of the chapter is shown in the pages,
+     * comparing the line containing it with the lines of the visible elements.
+     *
+     * @param item: the index of the element
+     * @param offset: the characters of the element preceding the position
+     * @param height: the height of a page
+     * @param epub_settings: the settings of the book
+     */
+    fn is_visible(
+        &self,
+        item: usize,
+        offset: usize,
+        height: f64,
+        epub_settings: &EpubSettings,
+    ) -> bool {
+        let element = match self.text.get(item) {
+            Some(element) => element,
+            None => return false,
+        };
+        let line = element.line_for_offset(element.byte_offset(offset));
+        let spacing = epub_settings.paragraph_spacing;
+        let (mut slices, next) = fill_page(&self.text, self.page_start(), height, spacing);
+        if epub_settings.visualization_mode == VisualizationMode::TwoPage {
+            slices.extend(fill_page(&self.text, next, height, spacing).0);
+        }
+        slices
+            .iter()
+            .any(|slice| slice.item == item && slice.lines.contains(&line))
     }
 
     fn get_visible_elements(
         &mut self,
         window_size: f64,
         epub_settings: &EpubSettings,
-    ) -> (Vec<TextLayout<RichText>>, Vec<TextLayout<RichText>>) {
-        let mut visible_elements = Vec::new();
-        let mut second_page = Vec::new();
-        let mut total_size = 0.0;
-        let mut i = self.visualized_range.start;
-        for element in self.text[self.visualized_range.start..].iter() {
-            if total_size + element.size().height + epub_settings.paragraph_spacing > window_size {
-                break;
-            }
-            visible_elements.push(element.clone());
-            total_size += element.size().height + epub_settings.paragraph_spacing;
-            i += 1;
-        }
+    ) -> (Vec<PageSlice>, Vec<PageSlice>) {
+        let spacing = epub_settings.paragraph_spacing;
+        let (visible_elements, next) =
+            fill_page(&self.text, self.page_start(), window_size, spacing);
+        let second_page = if epub_settings.visualization_mode == VisualizationMode::TwoPage {
+            fill_page(&self.text, next, window_size, spacing).0
+        } else {
+            Vec::new()
+        };
 
-        total_size = 0.0;
-        if epub_settings.visualization_mode == VisualizationMode::TwoPage {
-            for element in self.text[i..].iter() {
-                if total_size + element.size().height + epub_settings.paragraph_spacing
-                    > window_size
-                {
-                    break;
+        let end = second_page
+            .last()
+            .or_else(|| visible_elements.last())
+            .map(|slice| slice.item + 1)
+            .unwrap_or(self.visualized_range.start);
+        self.visualized_range = self.visualized_range.start..end;
+
+        (visible_elements, second_page)
+    }
+
+    /**
+     * Draw the elements of a page, one below the other.
+     * Elements split across pages only show the lines of this page.
+     *
+     * @param page_x: the horizontal position of the page
+     * @param page_width: the width of the page
+     */
+    fn paint_page(
+        &mut self,
+        ctx: &mut PaintCtx,
+        page: &[PageSlice],
+        page_x: f64,
+        page_width: f64,
+        data: &EpubData,
+        env: &Env,
+    ) {
+        let selected = self.selected_ranges();
+        // the selected result of the search is opaque, the others are not
+        let search_color = env.get(style::HIGHLIGHT);
+        let selected_search_color = search_color.clone().with_alpha(1.);
+        let search_ranges = data.search_ranges(data.page_position.chapter());
+        let mut y = TEXT_Y_PADDING;
+        for slice in page {
+            let element = &self.text[slice.item];
+            let top = element.line_top(slice.lines.start);
+            let height = element.line_top(slice.lines.end) - top;
+            let visible = Rect::new(page_x, y, page_x + page_width, y + height);
+            // the first line shown is at the top of the visible part
+            let origin = Point::new(page_x + data.epub_settings.margin, y - top);
+
+            ctx.with_save(|ctx| {
+                ctx.clip(visible);
+                for highlight in data.highlights.iter().filter(|h| {
+                    h.chapter == data.page_position.chapter() && h.richtext_number == slice.item
+                }) {
+                    let range = highlight.range();
+                    let range = element.byte_offset(range.start)..element.byte_offset(range.end);
+                    for rect in element.rects_for_range(range) {
+                        ctx.fill(rect + origin.to_vec2(), &highlight.color.color());
+                    }
+                }
+                for (_, range) in selected.iter().filter(|(item, _)| *item == slice.item) {
+                    for rect in element.rects_for_range(range.clone()) {
+                        ctx.fill(rect + origin.to_vec2(), &SELECTION_COLOR);
+                    }
                 }
-                second_page.push(element.clone());
-                total_size += element.size().height + epub_settings.paragraph_spacing;
-                i += 1;
+                for (_, range) in search_ranges.iter().filter(|(item, range)| {
+                    *item == slice.item && self.search_selection != Some((*item, range.clone()))
+                }) {
+                    let range = element.byte_offset(range.start)..element.byte_offset(range.end);
+                    for rect in element.rects_for_range(range) {
+                        ctx.fill(rect + origin.to_vec2(), &search_color);
+                    }
+                }
+                if let Some((richtext, range)) = &self.search_selection {
+                    if *richtext == slice.item {
+                        let range =
+                            element.byte_offset(range.start)..element.byte_offset(range.end);
+                        for rect in element.rects_for_range(range) {
+                            ctx.fill(rect + origin.to_vec2(), &selected_search_color);
+                        }
+                    }
+                }
+                element.draw(ctx, origin);
+            });
+
+            // a marker in the margin, next to the first line of each note
+            for (i, highlight) in data.highlights.iter().enumerate().filter(|(_, h)| {
+                h.chapter == data.page_position.chapter()
+                    && h.richtext_number == slice.item
+                    && !h.note.trim().is_empty()
+            }) {
+                let range = highlight.range();
+                let range = element.byte_offset(range.start)..element.byte_offset(range.end);
+                let line = match element.rects_for_range(range).first() {
+                    Some(rect) => *rect + origin.to_vec2(),
+                    None => continue,
+                };
+                if line.y0 < visible.y0 || line.y0 >= visible.y1 {
+                    continue;
+                }
+                let center = Point::new(page_x + data.epub_settings.margin / 2., line.center().y);
+                ctx.fill(
+                    druid::kurbo::Circle::new(center, NOTE_MARKER_RADIUS),
+                    &highlight.color.color().with_alpha(1.),
+                );
+                self.note_markers.push((center, i));
             }
+            self.drawn_items.push((slice.item, origin, visible));
+            y += height + data.epub_settings.paragraph_spacing;
         }
+    }
+}
 
-        self.visualized_range = self.visualized_range.start..i;
-
-        (visible_elements, second_page)
+impl Drop for PageSplitter {
+    fn drop(&mut self) {
+        self.stop_pagination();
     }
 }
 
+/**
+ * Count the pages of the chapters of a book in a background thread, and send
+ * the pages of each chapter to the page as soon as they are counted.
+ * The book is opened again, so the thread does not lock the document shown,
+ * and the text is measured with the text backend of a bitmap, which is not
+ * bound to the UI thread.
+ *
+ * @param sink: the sink used to send the pages to the main thread
+ * @param widget: the page receiving the pages
+ * @param key: the book, settings and window size to paginate
+ * @param chapters: the chapters whose pages are not counted yet
+ * @param stop: set when the pagination is no longer needed
+ */
+fn start_pagination_in_thread(
+    sink: ExtEventSink,
+    widget: WidgetId,
+    key: PaginationKey,
+    chapters: Vec<usize>,
+    stop: Arc<AtomicBool>,
+) {
+    std::thread::spawn(move || {
+        let doc = match EpubDoc::new(&key.book) {
+            Ok(doc) => doc,
+            Err(_) => return,
+        };
+        let mut data = EpubData::new(doc);
+        data.epub_settings = key.settings.clone();
+
+        let mut device = match Device::new() {
+            Ok(device) => device,
+            Err(_) => return,
+        };
+        let mut bitmap = match device.bitmap_target(1, 1, 1.) {
+            Ok(bitmap) => bitmap,
+            Err(_) => return,
+        };
+        let mut piet = bitmap.render_context();
+        // the fonts which cannot be loaded are left out, like in the page
+        for font in data.embedded_fonts().iter() {
+            let _ = piet.text().load_font(&font.data);
+        }
+        // the colors of the theme do not change the size of the text
+        let mut env = Env::empty();
+        style::apply_theme(&mut env, &Theme::default());
+
+        for chapter in chapters {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let pages = PageSplitter::chapter_pages(&data, chapter, key.size, piet.text(), &env);
+            let submitted = sink.submit_command(
+                INTERNAL_COMMAND,
+                InternalUICommand::ChapterPaginated((key.clone(), chapter, pages)),
+                Target::Widget(widget),
+            );
+            if submitted.is_err() {
+                break;
+            }
+        }
+        let _ = piet.finish();
+    });
+}
+
 impl Widget<EpubData> for PageSplitter {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut EpubData, _: &Env) {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut EpubData, env: &Env) {
         match event {
             Event::Command(cmd) => {
+                if cmd.is(druid::commands::COPY) {
+                    self.copy_selection();
+                    ctx.set_handled();
+                }
                 if let Some(internal) = cmd.get(INTERNAL_COMMAND) {
                     match internal {
                         InternalUICommand::EpubNavigate(direction) => {
+                            self.close_popover(ctx);
                             if *direction {
                                 let can_get_next_page =
-                                    self.next_page(ctx.size().height - 50., &data.epub_settings);
+                                    self.next_page(
+                                        ctx.size().height - PAGE_BOTTOM_PADDING,
+                                        &data.epub_settings,
+                                    );
                                 if !can_get_next_page && data.next_chapter() {
-                                    self.visualized_range = 0..0;
+                                    self.go_to(0, 0);
                                 }
                             } else {
                                 let can_get_prev_page =
-                                    self.prev_page(ctx.size().height - 50., &data.epub_settings);
+                                    self.prev_page(
+                                        ctx.size().height - PAGE_BOTTOM_PADDING,
+                                        &data.epub_settings,
+                                    );
                                 if !can_get_prev_page && data.prev_chapter() {
-                                    let last_pos = data.get_current_chap().len() - 1;
-                                    self.visualized_range = last_pos..last_pos;
+                                    // the last page is found in the text of the previous chapter,
+                                    // which can be empty when it only contains notes
+                                    self.generate_text(&data.get_current_chap(), data);
+                                    self.wrap_label_size(
+                                        &ctx.size(),
+                                        ctx.text(),
+                                        data.epub_settings.margin,
+                                        env,
+                                    );
+                                    self.go_to_last_page(
+                                        ctx.size().height - PAGE_BOTTOM_PADDING,
+                                        &data.epub_settings,
+                                    );
                                 }
                             }
-                            data.set_position_in_page(self.visualized_range.start);
+                            data.set_position_in_page(
+                                self.visualized_range.start,
+                                self.start_offset,
+                            );
+                            ctx.request_update();
+                            ctx.request_layout();
+                            ctx.request_paint();
+                        }
+
+                        InternalUICommand::EpubGoToChapterEnd => {
+                            self.go_to_last_page(
+                                ctx.size().height - PAGE_BOTTOM_PADDING,
+                                &data.epub_settings,
+                            );
+                            data.set_position_in_page(
+                                self.visualized_range.start,
+                                self.start_offset,
+                            );
                             ctx.request_update();
                             ctx.request_layout();
                             ctx.request_paint();
                         }
 
+                        InternalUICommand::ChapterPaginated((key, chapter, pages)) => {
+                            if let Some(pagination) = self.paginations.get_mut(key) {
+                                pagination.set_chapter_pages(*chapter, *pages);
+                            }
+                            ctx.request_paint();
+                        }
+                        InternalUICommand::FocusPage => {
+                            ctx.request_focus();
+                        }
+
+                        InternalUICommand::EpubGoToSearchResult(forward) => {
+                            if let Some(pos) = data.select_adjacent_search_result(*forward) {
+                                ctx.submit_command(
+                                    INTERNAL_COMMAND.with(InternalUICommand::EpubGoToPos(pos)),
+                                );
+                            }
+                        }
+
                         InternalUICommand::EpubGoToPos(pos) => {
                             if data.page_position.chapter() != pos.chapter()
-                                || !self.visualized_range.contains(&pos.richtext_number())
+                                || !self.is_visible(
+                                    pos.richtext_number(),
+                                    pos.offset(),
+                                    ctx.size().height - PAGE_BOTTOM_PADDING,
+                                    &data.epub_settings,
+                                )
                             {
                                 data.change_position(pos.clone());
-                                self.visualized_range =
-                                    pos.richtext_number()..pos.richtext_number();
+                                self.go_to(pos.richtext_number(), pos.offset());
                             }
 
                             if let Some(range) = pos.range() {
-                                self.search_selection = Some((
-                                    pos.richtext_number(),
-                                    Selection::new(range.start, range.end),
-                                ));
+                                self.search_selection =
+                                    Some((pos.richtext_number(), range.clone()));
+                                data.select_search_result(pos);
                             }
                             ctx.request_update();
                             ctx.request_layout();
                             ctx.request_paint();
                         }
+
+                        InternalUICommand::EpubOpenLink(href)
+                        | InternalUICommand::EpubOpenNote(href) => {
+                            let is_noteref =
+                                matches!(internal, InternalUICommand::EpubOpenNote(_));
+                            if EXTERNAL_LINK_SCHEMES.iter().any(|s| href.starts_with(s)) {
+                                if let Err(e) = open::that(href) {
+                                    println!("Error opening link {}: {}", href, e);
+                                }
+                            } else if let Some(note) = data.get_note(href, is_noteref) {
+                                // notes are shown over the page instead of leaving it
+                                self.show_note(ctx, note, env);
+                            } else if let Some(pos) = data.resolve_link(href) {
+                                data.push_link_history();
+                                ctx.submit_command(
+                                    INTERNAL_COMMAND.with(InternalUICommand::EpubGoToPos(pos)),
+                                );
+                            }
+                            ctx.set_handled();
+                        }
+
+                        InternalUICommand::CopySelection => {
+                            self.copy_selection();
+                            self.close_popover(ctx);
+                            ctx.set_handled();
+                        }
+
+                        InternalUICommand::HighlightSelection(color) => {
+                            if self.highlight_selection(data, *color) {
+                                // highlights are saved with the other information of the book
+                                ctx.submit_command(
+                                    INTERNAL_COMMAND.with(InternalUICommand::UpdateBookInfo(
+                                        data.get_epub_path(),
+                                    )),
+                                );
+                            }
+                            self.close_popover(ctx);
+                            ctx.request_paint();
+                            ctx.set_handled();
+                        }
+
+                        InternalUICommand::AnnotateSelection => {
+                            // the note is attached to the first highlight of the selection
+                            let highlight = data.highlights.len();
+                            if self.highlight_selection(data, HighlightColor::Yellow) {
+                                self.open_note_editor(ctx, data, highlight, env);
+                            } else {
+                                self.close_popover(ctx);
+                            }
+                            ctx.request_paint();
+                            ctx.set_handled();
+                        }
+
+                        InternalUICommand::EpubGoBack => {
+                            if let Some(pos) = data.pop_link_history() {
+                                ctx.submit_command(
+                                    INTERNAL_COMMAND.with(InternalUICommand::EpubGoToPos(pos)),
+                                );
+                            }
+                            ctx.set_handled();
+                        }
                         _ => {}
                     }
                 }
             }
+            Event::MouseDown(mouse) => {
+                self.close_popover(ctx);
+                if mouse.button.is_left() {
+                    self.last_click_window_pos = mouse.window_pos;
+                    let note = self.note_markers.iter().find_map(|(center, highlight)| {
+                        (center.distance(mouse.pos) <= NOTE_MARKER_RADIUS * 2.).then(|| *highlight)
+                    });
+                    if let Some(highlight) = note {
+                        self.open_note_editor(ctx, data, highlight, env);
+                        ctx.set_handled();
+                    } else if let Some(command) = self.link_at(mouse.pos) {
+                        ctx.submit_command(command);
+                        ctx.set_handled();
+                    } else {
+                        // start selecting the text
+                        self.selection = self.text_position_at(mouse.pos).map(|pos| (pos, pos));
+                        ctx.set_active(true);
+                        ctx.request_focus();
+                        ctx.request_paint();
+                    }
+                }
+            }
+            Event::MouseUp(mouse) => {
+                if ctx.is_active() {
+                    ctx.set_active(false);
+                    if self.selected_ranges().is_empty() {
+                        self.selection = None;
+                    } else {
+                        self.last_click_window_pos = mouse.window_pos;
+                        self.show_selection_actions(ctx, env);
+                    }
+                }
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                if let (Some((anchor, _)), Some(pos)) =
+                    (self.selection, self.text_position_at(mouse.pos))
+                {
+                    self.selection = Some((anchor, pos));
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseMove(mouse) => {
+                if self.link_at(mouse.pos).is_some() {
+                    ctx.set_cursor(&druid::Cursor::Pointer);
+                } else {
+                    ctx.set_cursor(&druid::Cursor::Arrow);
+                }
+            }
+            Event::Timer(token) if Some(*token) == self.pagination_timer => {
+                self.pagination_timer = None;
+                self.stop_pagination();
+                if let Some(pagination) = self.paginations.current() {
+                    let stop = Arc::new(AtomicBool::new(false));
+                    start_pagination_in_thread(
+                        ctx.get_external_handle(),
+                        ctx.widget_id(),
+                        pagination.key().clone(),
+                        pagination.pending_chapters(),
+                        stop.clone(),
+                    );
+                    self.pagination_worker = Some((pagination.key().clone(), stop));
+                }
+            }
+            Event::KeyDown(key) if HotKey::new(SysMods::Cmd, "c").matches(key) => {
+                self.copy_selection();
+                ctx.set_handled();
+            }
+            // the other keys are performed by the controller of the page, the ones
+            // which could edit a text only when the page has the focus
+            Event::KeyDown(key) if is_text_editing_key(key) => {
+                ctx.submit_command(
+                    INTERNAL_COMMAND.with(InternalUICommand::PageKeyDown(key.clone())),
+                );
+                ctx.set_handled();
+            }
             // when the window is going to be closed, save the current position
             Event::WindowDisconnected => {
                 ctx.submit_command(
@@ -227,14 +1112,20 @@ impl Widget<EpubData> for PageSplitter {
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &EpubData, env: &Env) {
         match event {
             LifeCycle::WidgetAdded => {
-                self.generate_text(&data.get_current_chap(), data.epub_settings.font_size);
+                self.generate_text(&data.get_current_chap(), data);
                 self.wrap_label_size(&ctx.size(), ctx.text(), data.epub_settings.margin, env);
 
-                // If the position is not 0, then we need to go to the position
-                if data.page_position.richtext_number() != 0 {
-                    self.visualized_range =
-                        data.page_position.richtext_number()..data.page_position.richtext_number();
-                }
+                // go to the line where the reading was left
+                self.go_to(
+                    data.page_position.richtext_number(),
+                    data.page_position.offset(),
+                );
+                // the keys turn the pages as soon as the book is opened
+                ctx.submit_command(
+                    INTERNAL_COMMAND
+                        .with(InternalUICommand::FocusPage)
+                        .to(ctx.widget_id()),
+                );
             }
             _ => {}
         }
@@ -248,16 +1139,14 @@ impl Widget<EpubData> for PageSplitter {
                 .same(&old_data.edit_data.edited_chapter())
                 || !data.page_position.chapter()
                     .same(&old_data.page_position.chapter())
-                || !data.epub_settings.same(&old_data.epub_settings)
-                || data.edit_data.same(&old_data.edit_data);
+                || !data.epub_settings.same(&old_data.epub_settings);
 
             if should_update_chap {
+                // the selection refers to the elements of the previous text
+                self.selection = None;
                 self.generate_text(
-                    &crate::dom::generate_renderable_tree(
-                        data.edit_data.edited_chapter(),
-                        data.epub_settings.font_size,
-                    ),
-                    data.epub_settings.font_size,
+                    &data.render_current_text(data.edit_data.edited_chapter()),
+                    data,
                 );
                 self.wrap_label_size(&ctx.size(), ctx.text(), data.epub_settings.margin, env);
             }
@@ -271,25 +1160,47 @@ impl Widget<EpubData> for PageSplitter {
         data: &EpubData,
         env: &Env,
     ) -> Size {
+        self.load_embedded_fonts(ctx.text(), data);
         self.wrap_label_size(&bc.max(), ctx.text(), data.epub_settings.margin, env);
+        self.page_size = bc.max();
+
+        // changing the settings or the size of the window paginates the book again,
+        // except for the colors, which do not move the text
+        let key = PaginationKey {
+            book: data.get_epub_path(),
+            settings: EpubSettings {
+                theme: Theme::default(),
+                custom_themes: Vector::new(),
+                ..data.epub_settings.clone()
+            },
+            size: self.page_size,
+        };
+        let running =
+            matches!(&self.pagination_worker, Some((worker_key, _)) if worker_key.same(&key));
+        let pagination = self.paginations.select(key, data.chapter_count());
+        if !pagination.is_complete() && !running {
+            // the previous pagination is stopped, a new timer postpones the new one
+            self.stop_pagination();
+            self.pagination_timer = Some(ctx.request_timer(PAGINATION_DELAY));
+        }
         bc.max()
     }
 
-    fn paint(&mut self, ctx: &mut PaintCtx, data: &EpubData, _: &Env) {
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &EpubData, env: &Env) {
         let size = ctx.size();
-        let mut y = 0.0;
+        let background = env.get(style::BACKGROUND);
 
-        // draw a white background
+        // draw the background of the theme
         if data.epub_settings.visualization_mode == VisualizationMode::TwoPage {
             let rect = Rect::ZERO.with_size(size);
-            ctx.fill(rect, &Color::WHITE);
+            ctx.fill(rect, &background);
         } else {
             // draw only the part of the page that is visible
             let rect = Rect::from_origin_size(
                 Point::new(size.width * 0.25, 0.),
                 Size::new(size.width / 2., size.height),
             );
-            ctx.fill(rect, &Color::WHITE);
+            ctx.fill(rect, &background);
         }
 
         let x = if !(data.epub_settings.visualization_mode == VisualizationMode::TwoPage) {
@@ -298,71 +1209,20 @@ impl Widget<EpubData> for PageSplitter {
             0.0
         };
 
-        let (page_1, page_2) = self.get_visible_elements(size.height - 50., &data.epub_settings);
-
-        for (i, label) in page_1.iter().enumerate() {
-            if let Some((richtext, selection)) = &self.search_selection {
-                let i = i + self.visualized_range.start;
-                if *richtext == i {
-                    let label = &self.text[i];
-                    label
-                        .rects_for_range(selection.range())
-                        .iter()
-                        .for_each(|rect| {
-                            ctx.fill(
-                                *rect
-                                    + druid::Vec2::new(
-                                        x + data.epub_settings.margin,
-                                        TEXT_Y_PADDING + y,
-                                    ),
-                                &Color::YELLOW,
-                            );
-                        });
-                }
-            }
-            label.draw(
-                ctx,
-                Point::new(x + data.epub_settings.margin, y + TEXT_Y_PADDING),
-            );
-            y += label.size().height + data.epub_settings.paragraph_spacing;
-        }
+        let (page_1, page_2) =
+            self.get_visible_elements(size.height - PAGE_BOTTOM_PADDING, &data.epub_settings);
+        self.drawn_items.clear();
+        self.note_markers.clear();
 
+        self.paint_page(ctx, &page_1, x, size.width / 2., data, env);
         if data.epub_settings.visualization_mode == VisualizationMode::TwoPage {
-            y = 0.0;
-            for (i, label) in page_2.iter().enumerate() {
-                if let Some((richtext, selection)) = &self.search_selection {
-                    let i = i + self.visualized_range.start + page_1.len();
-                    if *richtext == i {
-                        let label = &self.text[i];
-                        label
-                            .rects_for_range(selection.range())
-                            .iter()
-                            .for_each(|rect| {
-                                ctx.fill(
-                                    *rect
-                                        + druid::Vec2::new(
-                                            size.width / 2. + data.epub_settings.margin,
-                                            TEXT_Y_PADDING + y,
-                                        ),
-                                    &Color::YELLOW,
-                                );
-                            });
-                    }
-                }
-                label.draw(
-                    ctx,
-                    Point::new(
-                        size.width / 2. + data.epub_settings.margin,
-                        y + TEXT_Y_PADDING,
-                    ),
-                );
-                y += label.size().height + data.epub_settings.paragraph_spacing;
-            }
+            self.paint_page(ctx, &page_2, size.width / 2., size.width / 2., data, env);
         }
 
-        // draw a frame for the page
+        // draw a frame for the page, in the color of the text of the theme
         // if two side, draw two frames with a shadow in the middle
         // if one side, draw one frame with a shadow in the left
+        let frame_color = env.get(style::TEXT);
         let stops = (
             (Color::BLACK.with_alpha(0.)),
             (Color::BLACK.with_alpha(0.1)),
@@ -391,7 +1251,7 @@ impl Widget<EpubData> for PageSplitter {
                 Point::new(size.width / 2., 0.),
                 Size::new(size.width / 2., size.height),
             );
-            ctx.stroke(rect, &Color::BLACK, 1.0);
+            ctx.stroke(rect, &frame_color, 1.0);
         } else {
             let rect = Rect::from_origin_size(
                 Point::new(size.width * 0.25, 0.),
@@ -403,13 +1263,15 @@ impl Widget<EpubData> for PageSplitter {
         // create a rectangular frame for the page
         let rect =
             Rect::from_origin_size(Point::new(x, 0.), Size::new(size.width / 2., size.height));
-        ctx.stroke(rect, &Color::BLACK, 1.0);
+        ctx.stroke(rect, &frame_color, 1.0);
     }
 }
 
 pub struct TextContainer {
     label_text_lines: WidgetPod<EpubData, PageSplitter>,
     navigation_buttons: Vec<WidgetPod<EpubData, Box<dyn Widget<EpubData>>>>,
+    // shown only after following an internal link
+    back_button: WidgetPod<EpubData, Box<dyn Widget<EpubData>>>,
 }
 impl TextContainer {
     pub fn new() -> Self {
@@ -421,9 +1283,7 @@ impl TextContainer {
                             INTERNAL_COMMAND.with(InternalUICommand::EpubNavigate(false)),
                         );
                     })
-                    .with_color(crate::core::style::get_color_unchecked(
-                        crate::core::style::PRIMARY_LIGHT,
-                    ))
+                    .with_color(style::CHROME_LIGHT)
                     .boxed(),
             ),
             WidgetPod::new(
@@ -433,15 +1293,22 @@ impl TextContainer {
                             INTERNAL_COMMAND.with(InternalUICommand::EpubNavigate(true)),
                         );
                     })
-                    .with_color(crate::core::style::get_color_unchecked(
-                        crate::core::style::PRIMARY_LIGHT,
-                    ))
+                    .with_color(style::CHROME_LIGHT)
                     .boxed(),
             ),
         ];
+        let back_button = WidgetPod::new(
+            RoundButton::new(ARROW_BACK)
+                .with_click_handler(|ctx, _, _| {
+                    ctx.submit_command(INTERNAL_COMMAND.with(InternalUICommand::EpubGoBack));
+                })
+                .with_color(style::CHROME_LIGHT)
+                .boxed(),
+        );
         Self {
             label_text_lines: WidgetPod::new(PageSplitter::new()),
             navigation_buttons,
+            back_button,
         }
     }
 }
@@ -452,6 +1319,9 @@ impl Widget<EpubData> for TextContainer {
         for nav_button in self.navigation_buttons.iter_mut() {
             nav_button.event(ctx, event, data, env);
         }
+        if !data.link_history.is_empty() || event.should_propagate_to_hidden() {
+            self.back_button.event(ctx, event, data, env);
+        }
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &EpubData, env: &Env) {
@@ -459,6 +1329,7 @@ impl Widget<EpubData> for TextContainer {
         for nav_button in self.navigation_buttons.iter_mut() {
             nav_button.lifecycle(ctx, event, data, env);
         }
+        self.back_button.lifecycle(ctx, event, data, env);
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &EpubData, data: &EpubData, env: &Env) {
@@ -466,6 +1337,7 @@ impl Widget<EpubData> for TextContainer {
         for nav_button in self.navigation_buttons.iter_mut() {
             nav_button.update(ctx, data, env);
         }
+        self.back_button.update(ctx, data, env);
     }
 
     fn layout(
@@ -490,6 +1362,8 @@ impl Widget<EpubData> for TextContainer {
             nav_button.set_origin(ctx, data, env, Point::new(x, size.height - 100.));
             x = size.width - 50.;
         }
+        self.back_button.layout(ctx, bc, data, env);
+        self.back_button.set_origin(ctx, data, env, Point::new(10., TEXT_Y_PADDING));
         size
     }
 
@@ -500,35 +1374,28 @@ impl Widget<EpubData> for TextContainer {
         for nav_button in self.navigation_buttons.iter_mut() {
             nav_button.paint(ctx, data, env);
         }
-        let label_text = self.label_text_lines.widget_mut();
-        let number_of_labels = label_text.text.len() as isize - 1;
-        let range = &label_text.visualized_range;
-        let text = range.start.to_string()
-            + "-"
-            + &range.end.to_string()
-            + "/"
-            + &number_of_labels.to_string();
-        let layout = ctx.text().new_text_layout(text).build().unwrap();
-
-        let mut origin = Point::new(
-            size.width / 2. - PAGE_LABEL_DISTANCE_FROM_CENTER,
-            size.height - PAGE_LABEL_Y_PADDING,
-        );
-        if data.epub_settings.visualization_mode == VisualizationMode::SinglePage {
-            ctx.draw_text(&layout, origin);
-        } else {
-            origin.x = size.width / 2. - size.width / 4. - PAGE_LABEL_DISTANCE_FROM_CENTER;
-            ctx.draw_text(&layout, origin);
-
-            let range = &label_text.visualized_range;
-            let text = range.start.to_string()
-                + "-"
-                + &range.end.to_string()
-                + "/"
-                + &number_of_labels.to_string();
-            let layout = ctx.text().new_text_layout(text).build().unwrap();
+        if !data.link_history.is_empty() {
+            self.back_button.paint(ctx, data, env);
+        }
+        let labels = self.label_text_lines.widget().page_labels(data);
 
-            origin.x = size.width / 2. + size.width / 4. - PAGE_LABEL_DISTANCE_FROM_CENTER;
+        // each label is centered under its page
+        let centers = if data.epub_settings.visualization_mode == VisualizationMode::SinglePage {
+            vec![size.width / 2.]
+        } else {
+            vec![size.width / 4., size.width * 0.75]
+        };
+        for (label, center) in labels.into_iter().zip(centers) {
+            let layout = ctx
+                .text()
+                .new_text_layout(label)
+                .text_color(env.get(style::TEXT))
+                .build()
+                .unwrap();
+            let origin = Point::new(
+                center - layout.size().width / 2.,
+                size.height - PAGE_LABEL_Y_PADDING,
+            );
             ctx.draw_text(&layout, origin);
         }
     }
@@ -0,0 +1,177 @@
+use druid::{
+    widget::{Controller, Either, Label, LineBreaking, List, TextBox},
+    Env, Event, EventCtx, Widget, WidgetExt,
+};
+
+use crate::{
+    core::{
+        constants::commands::{InternalUICommand, INTERNAL_COMMAND},
+        style,
+    },
+    data::{epub::settings::EpubSettings, HomePageData, LibraryResult},
+    PageType,
+};
+
+// results shown for the text of each book
+const HITS_PER_BOOK: usize = 10;
+
+/**
+ * Search box of the home page, looking for a text in all the books of the library.
+ * The search is started with Enter.
+ */
+pub fn library_search_input() -> impl Widget<HomePageData> {
+    TextBox::new()
+        .with_placeholder("Search the library")
+        .lens(HomePageData::search_input)
+        .expand_width()
+        .controller(LibrarySearchController)
+}
+
+/**
+ * Results of the search in the library, grouped by book.
+ * Clicking a book opens it where it was left, clicking a result opens it at the result.
+ */
+pub fn library_search_results() -> impl Widget<HomePageData> {
+    List::new(|| {
+        Either::new(
+            |result: &LibraryResult, _| result.is_book(),
+            Label::dynamic(|result: &LibraryResult, _| result.label.to_string())
+                .with_text_size(18.)
+                .with_text_color(style::CHROME_TEXT)
+                .padding((5., 10., 5., 5.)),
+            Label::dynamic(|result: &LibraryResult, _| result.label.to_string())
+                .with_text_size(14.)
+                .with_text_color(style::CHROME_TEXT)
+                .with_line_break_mode(LineBreaking::WordWrap)
+                .padding(5.),
+        )
+        .expand_width()
+        .on_click(|ctx, result: &mut LibraryResult, _| {
+            ctx.submit_command(
+                INTERNAL_COMMAND.with(InternalUICommand::OpenLibraryResult(result.clone())),
+            );
+        })
+    })
+    .lens(HomePageData::search_results)
+}
+
+/**
+ * Start the search in the library and open the results.
+ */
+struct LibrarySearchController;
+
+impl<W: Widget<HomePageData>> Controller<HomePageData, W> for LibrarySearchController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut HomePageData,
+        env: &Env,
+    ) {
+        match event {
+            Event::KeyUp(key) if key.code == druid::Code::Enter => {
+                data.search_results.clear();
+                data.search_id += 1;
+                if !data.search_input.trim().is_empty() {
+                    data.search_results
+                        .push_back(LibraryResult::message("Searching..."));
+                    let books = data
+                        .recents
+                        .iter()
+                        .map(|r| (r.path.clone(), r.epub_settings.clone()))
+                        .collect();
+                    start_library_search_in_thread(
+                        ctx.get_external_handle(),
+                        books,
+                        data.search_input.clone(),
+                        data.search_id,
+                        data.data_dir.clone(),
+                    );
+                }
+            }
+            Event::Command(cmd) => {
+                if let Some(internal) = cmd.get(INTERNAL_COMMAND) {
+                    match internal {
+                        InternalUICommand::LibrarySearchResults((search_id, results)) => {
+                            // the results of an older search are discarded
+                            if *search_id == data.search_id {
+                                // the results are shown before the message of the search
+                                let message = data.search_results.pop_back();
+                                data.search_results.append(results.clone());
+                                data.search_results.extend(message);
+                            }
+                            ctx.set_handled();
+                        }
+                        InternalUICommand::LibrarySearchCompleted(search_id) => {
+                            if *search_id == data.search_id {
+                                data.search_results.pop_back();
+                                if data.search_results.is_empty() {
+                                    data.search_results
+                                        .push_back(LibraryResult::message("No results"));
+                                }
+                            }
+                            ctx.set_handled();
+                        }
+                        InternalUICommand::OpenLibraryResult(result) => {
+                            if let Some(recent) = data.get_recent(&result.book_path) {
+                                ctx.submit_command(druid::Command::new(
+                                    INTERNAL_COMMAND,
+                                    InternalUICommand::OpenRecent(recent),
+                                    druid::Target::Auto,
+                                ));
+                                ctx.submit_command(
+                                    INTERNAL_COMMAND
+                                        .with(InternalUICommand::UINavigate(PageType::Reader)),
+                                );
+                                if let Some(position) = &result.position {
+                                    ctx.submit_command(INTERNAL_COMMAND.with(
+                                        InternalUICommand::EpubGoToPos(position.as_ref().clone()),
+                                    ));
+                                }
+                            }
+                            ctx.set_handled();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+/**
+ * Search the books in a background thread, sending the results of each book
+ * as soon as it is searched.
+ *
+ * @param search_id: the number of the search, sent with its results
+ */
+fn start_library_search_in_thread(
+    sink: druid::ExtEventSink,
+    books: Vec<(String, EpubSettings)>,
+    query: String,
+    search_id: usize,
+    data_dir: std::path::PathBuf,
+) {
+    std::thread::spawn(move || {
+        crate::search::search_library(&books, &query, HITS_PER_BOOK, &data_dir, |book| {
+            sink.submit_command(
+                INTERNAL_COMMAND,
+                InternalUICommand::LibrarySearchResults((
+                    search_id,
+                    LibraryResult::from_matches(vec![book]),
+                )),
+                druid::Target::Global,
+            )
+            .expect("command failed to submit");
+        });
+        sink.submit_command(
+            INTERNAL_COMMAND,
+            InternalUICommand::LibrarySearchCompleted(search_id),
+            druid::Target::Global,
+        )
+        .expect("command failed to submit");
+    });
+}
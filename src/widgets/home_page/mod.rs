@@ -1 +1,2 @@
+pub mod library_search;
 pub mod recent_item;
\ No newline at end of file
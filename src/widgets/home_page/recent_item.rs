@@ -1,6 +1,6 @@
 use druid::{
-    widget::Flex, BoxConstraints, Color, Data, EventCtx, Point, RenderContext, Size, TextLayout,
-    Widget, WidgetExt, WidgetPod,
+    widget::Flex, BoxConstraints, Data, EventCtx, Point, RenderContext, Size, TextLayout, Widget,
+    WidgetExt, WidgetPod,
 };
 use epub::doc::EpubDoc;
 
@@ -162,15 +162,15 @@ impl Widget<Recent> for RecentWidget {
 
                 self.title_label.set_text(title);
                 self.title_label.set_text_size(18.);
-                self.title_label.set_text_color(Color::WHITE);
+                self.title_label.set_text_color(style::CHROME_TEXT);
 
                 self.creator_label.set_text(creator);
                 self.creator_label.set_text_size(14.);
-                self.creator_label.set_text_color(Color::WHITE);
+                self.creator_label.set_text_color(style::CHROME_TEXT);
 
                 self.publisher_label.set_text(publisher);
                 self.publisher_label.set_text_size(14.);
-                self.publisher_label.set_text_color(Color::WHITE);
+                self.publisher_label.set_text_color(style::CHROME_TEXT);
 
                 self.position_in_book_label
                     .set_text(if let Some(pos) = &data.reached_position {
@@ -179,7 +179,8 @@ impl Widget<Recent> for RecentWidget {
                         "Never opened".to_string()
                     });
                 self.position_in_book_label.set_text_size(14.);
-                self.position_in_book_label.set_text_color(Color::WHITE);
+                self.position_in_book_label
+                    .set_text_color(style::CHROME_TEXT);
             }
             _ => {}
         }
@@ -264,12 +265,9 @@ impl Widget<Recent> for RecentWidget {
         druid::Size::new(bc.max().width, 200.)
     }
 
-    fn paint(&mut self, ctx: &mut druid::PaintCtx, data: &Recent, _env: &druid::Env) {
+    fn paint(&mut self, ctx: &mut druid::PaintCtx, data: &Recent, env: &druid::Env) {
         let size = ctx.size();
-        ctx.fill(
-            size.to_rect(),
-            &style::get_color_unchecked(style::PRIMARY_DARK),
-        );
+        ctx.fill(size.to_rect(), &env.get(style::CHROME));
         const LABEL_PADDING: f64 = 5.;
 
         let mut y = 15.;
@@ -282,10 +280,10 @@ impl Widget<Recent> for RecentWidget {
         y += self.publisher_label.size().height + LABEL_PADDING;
 
         self.position_in_book_label.draw(ctx, Point::new(150., y));
-        self.image.paint(ctx, data, _env);
+        self.image.paint(ctx, data, env);
 
         if ctx.is_hot() {
-            self.remove_button.paint(ctx, data, _env);
+            self.remove_button.paint(ctx, data, env);
         }
     }
 }
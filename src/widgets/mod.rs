@@ -1,5 +1,6 @@
 pub mod epub_page;
 pub mod home_page;
+pub mod settings_page;
 
 mod common;
 mod popup;
@@ -13,4 +14,6 @@ pub use home_page::recent_item;
 
 pub use popup::edit::EditWidget;
 pub use popup::edit::PromptOption;
+pub use popup::keymap::build_keymap_ui;
+pub use popup::note::build_note_ui;
 pub use popup::ocr::build_ocr_ui;
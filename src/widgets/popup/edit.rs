@@ -262,7 +262,7 @@ impl Widget<EditData> for EditWidget {
     fn paint(&mut self, ctx: &mut PaintCtx, data: &EditData, env: &Env) {
         let size = ctx.size();
         let rect = size.to_rect();
-        ctx.fill(rect, &style::get_color_unchecked(style::PRIMARY_LIGHT));
+        ctx.fill(rect, &env.get(style::CHROME_LIGHT));
         self.toolbar.paint(ctx, data, env);
         self.text.paint(ctx, data, env);
     }
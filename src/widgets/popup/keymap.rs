@@ -0,0 +1,57 @@
+use druid::{
+    widget::{Button, Flex, Label, List, Scroll, TextBox},
+    Color, Widget, WidgetExt,
+};
+
+use crate::data::{KeyBinding, Keymap};
+
+const ACTION_LABEL_WIDTH: f64 = 180.;
+
+/**
+ * Popup to change the shortcuts of the reader.
+ * Each action has a list of shortcuts separated by commas, like "Ctrl+F, F3";
+ * the keymap is saved with the configuration as soon as it changes.
+ */
+pub fn build_keymap_ui() -> impl Widget<Keymap> {
+    let bindings = List::new(|| {
+        Flex::row()
+            .with_child(
+                Label::dynamic(|binding: &KeyBinding, _| binding.action.label().to_string())
+                    .fix_width(ACTION_LABEL_WIDTH),
+            )
+            .with_flex_child(
+                TextBox::new().lens(KeyBinding::shortcuts).expand_width(),
+                1.,
+            )
+            .with_child(
+                Label::dynamic(|binding: &KeyBinding, _| {
+                    if binding.is_valid() {
+                        String::new()
+                    } else {
+                        "Unknown key".to_string()
+                    }
+                })
+                .with_text_color(Color::RED),
+            )
+            .padding((0., 2.))
+    })
+    .lens(Keymap::bindings);
+
+    let buttons = Flex::row()
+        .with_flex_spacer(1.)
+        .with_child(
+            Button::new("Reset to defaults").on_click(|_, data: &mut Keymap, _| {
+                *data = Keymap::default();
+            }),
+        )
+        .with_default_spacer()
+        .with_child(Button::new("Done").on_click(|ctx, _, _| {
+            ctx.submit_command(druid::commands::CLOSE_WINDOW.to(ctx.window_id()));
+        }));
+
+    Flex::column()
+        .with_flex_child(Scroll::new(bindings).vertical().expand(), 1.)
+        .with_default_spacer()
+        .with_child(buttons)
+        .padding(10.)
+}
@@ -1,4 +1,6 @@
 pub(crate) mod edit;
+pub(crate) mod keymap;
+pub(crate) mod note;
 pub(crate) mod ocr;
 
 
@@ -0,0 +1,77 @@
+use druid::{
+    widget::{Button, Controller, Flex, Label, LineBreaking, TextBox},
+    Env, Event, EventCtx, LensExt, Widget, WidgetExt,
+};
+
+use crate::{
+    core::constants::commands::{InternalUICommand, INTERNAL_COMMAND},
+    data::{epub::EpubData, Highlight},
+};
+
+/**
+ * Popup to write the note attached to a highlight.
+ * It shows the highlighted passage above the text of the note.
+ *
+ * @param highlight: the index of the highlight in the book
+ * @param passage: the highlighted text
+ */
+pub fn build_note_ui(highlight: usize, passage: String) -> impl Widget<EpubData> {
+    let buttons = Flex::row()
+        .with_flex_spacer(1.)
+        .with_child(
+            Button::new("Delete").on_click(move |ctx, data: &mut EpubData, _| {
+                if let Some(highlight) = data.highlights.get_mut(highlight) {
+                    highlight.note.clear();
+                }
+                ctx.submit_command(druid::commands::CLOSE_WINDOW.to(ctx.window_id()));
+            }),
+        )
+        .with_default_spacer()
+        .with_child(Button::new("Done").on_click(|ctx, _, _| {
+            ctx.submit_command(druid::commands::CLOSE_WINDOW.to(ctx.window_id()));
+        }));
+
+    Flex::column()
+        .with_child(
+            Label::new(format!("\u{201C}{}\u{201D}", passage))
+                .with_line_break_mode(LineBreaking::WordWrap)
+                .expand_width(),
+        )
+        .with_default_spacer()
+        .with_flex_child(
+            TextBox::multiline()
+                .with_placeholder("Write a note")
+                .lens(EpubData::highlights.index(highlight).then(Highlight::note))
+                .expand(),
+            1.,
+        )
+        .with_default_spacer()
+        .with_child(buttons)
+        .padding(10.)
+        .controller(NoteController)
+}
+
+/**
+ * Save the notes of the book when the popup is closed.
+ */
+struct NoteController;
+
+impl<W: Widget<EpubData>> Controller<EpubData, W> for NoteController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut EpubData,
+        env: &Env,
+    ) {
+        if let Event::WindowDisconnected = event {
+            ctx.submit_command(
+                INTERNAL_COMMAND
+                    .with(InternalUICommand::UpdateBookInfo(data.get_epub_path()))
+                    .to(druid::Target::Global),
+            );
+        }
+        child.event(ctx, event, data, env)
+    }
+}
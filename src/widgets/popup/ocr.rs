@@ -30,7 +30,7 @@ pub fn build_ocr_ui() -> impl Widget<OcrData> {
         .with_tab("OCR Search", find_by_photo())
         .with_tab("Reverse OCR", find_by_virtual())
         .with_tab_index(0)
-        .background(style::get_color_unchecked(style::PRIMARY_DARK));
+        .background(style::CHROME);
 
     main_tabs.controller(OcrController)
 }
@@ -0,0 +1,237 @@
+use druid::{
+    text::ParseFormatter,
+    widget::{Button, CrossAxisAlignment, Flex, Label, Radio, Scroll, Slider, Switch, TextBox},
+    Color, Lens, Widget, WidgetExt,
+};
+
+use crate::{
+    core::{
+        constants::commands::{InternalUICommand, INTERNAL_COMMAND},
+        style,
+    },
+    css::TextAlign,
+    data::{
+        config::{AppConfig, SettingRange, SettingsRanges},
+        epub::{settings::EpubSettings, theme::Theme},
+    },
+    widgets::RoundButton,
+    PageType,
+};
+
+const SETTINGS_WIDTH: f64 = 500.;
+const RANGE_LABEL_WIDTH: f64 = 150.;
+const RANGE_INPUT_WIDTH: f64 = 80.;
+const SECTION_SPACING: f64 = 20.;
+
+/**
+ * The page of the settings of the application, opened from the home page.
+ * The configuration is saved as soon as it changes.
+ *
+ * @param ranges: the ranges of the sliders of the settings of the new books
+ */
+pub fn settings_page(ranges: &SettingsRanges) -> impl Widget<AppConfig> {
+    let back = RoundButton::new(druid_material_icons::normal::navigation::ARROW_BACK)
+        .with_click_handler(|ctx, _, _env| {
+            ctx.submit_command(
+                INTERNAL_COMMAND.with(InternalUICommand::UINavigate(PageType::Home)),
+            );
+        });
+    let header = Flex::row()
+        .with_child(back)
+        .with_default_spacer()
+        .with_child(
+            Label::new("Settings")
+                .with_text_size(26.0)
+                .with_text_color(style::CHROME_TEXT),
+        )
+        .expand_width();
+
+    let content = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(section_title("New books"))
+        .with_child(default_settings(ranges).lens(AppConfig::default_settings))
+        .with_spacer(SECTION_SPACING)
+        .with_child(section_title("Ranges of the settings"))
+        .with_child(ranges_settings().lens(AppConfig::ranges))
+        .with_spacer(SECTION_SPACING)
+        .with_child(section_title("Theme of the home page"))
+        .with_child(theme_presets().lens(AppConfig::theme))
+        .with_spacer(SECTION_SPACING)
+        .with_child(section_title("Keyboard shortcuts"))
+        .with_child(Button::new("Edit the shortcuts").on_click(|ctx, _, _| {
+            ctx.submit_command(INTERNAL_COMMAND.with(InternalUICommand::OpenKeymapEditor));
+        }))
+        .with_spacer(SECTION_SPACING)
+        .with_child(section_title("Data directory"))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("The data directory of the user")
+                .lens(AppConfig::data_dir)
+                .expand_width(),
+        )
+        .with_child(
+            Label::new("Where the library and the search indexes are kept, used after a restart")
+                .with_text_size(12.0),
+        )
+        .fix_width(SETTINGS_WIDTH)
+        .padding(10.);
+
+    let layout = Flex::column()
+        .with_child(header.padding(5.0))
+        .with_flex_child(Scroll::new(content).vertical(), 1.);
+    druid::widget::Container::new(layout).background(style::CHROME)
+}
+
+fn section_title<T: druid::Data>(title: &str) -> impl Widget<T> {
+    Label::new(title).with_text_size(18.0).padding((0., 5.))
+}
+
+/**
+ * The settings given to the books opened for the first time.
+ */
+fn default_settings(ranges: &SettingsRanges) -> impl Widget<EpubSettings> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(slider_setting(
+            "Font size",
+            ranges.font_size,
+            EpubSettings::font_size,
+        ))
+        .with_child(slider_setting(
+            "Text margin",
+            ranges.margin,
+            EpubSettings::margin,
+        ))
+        .with_child(slider_setting(
+            "Paragraph spacing",
+            ranges.paragraph_spacing,
+            EpubSettings::paragraph_spacing,
+        ))
+        .with_child(slider_setting(
+            "Line height",
+            ranges.line_height,
+            EpubSettings::line_height,
+        ))
+        .with_spacer(5.)
+        .with_child(Label::new("Alignment"))
+        .with_child(
+            Flex::row()
+                .with_child(Radio::new("Book", None))
+                .with_child(Radio::new("Left", Some(TextAlign::Start)))
+                .with_child(Radio::new("Justified", Some(TextAlign::Justified)))
+                .lens(EpubSettings::text_align),
+        )
+        .with_spacer(5.)
+        .with_child(switch_setting(
+            "Publisher styles",
+            EpubSettings::publisher_styles,
+        ))
+        .with_child(switch_setting("Hyphenation", EpubSettings::hyphenation))
+        .with_spacer(5.)
+        .with_child(theme_presets().lens(EpubSettings::theme))
+}
+
+fn slider_setting(
+    label: &'static str,
+    range: SettingRange,
+    lens: impl Lens<EpubSettings, f64> + 'static,
+) -> impl Widget<EpubSettings> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::new(move |value: &f64, _env: &_| {
+            format!("{}: {number:.prec$}", label, prec = 2, number = value)
+        }))
+        .with_child(
+            Slider::new()
+                .with_range(range.min, range.max)
+                .expand_width(),
+        )
+        .lens(lens)
+}
+
+fn switch_setting(
+    label: &'static str,
+    lens: impl Lens<EpubSettings, bool> + 'static,
+) -> impl Widget<EpubSettings> {
+    Flex::row()
+        .with_child(Label::new(label))
+        .with_flex_spacer(1.)
+        .with_child(Switch::new().lens(lens))
+        .expand_width()
+}
+
+/**
+ * The minimum and the maximum of each setting of the books, shown by the sliders.
+ */
+fn ranges_settings() -> impl Widget<SettingsRanges> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_spacer(RANGE_LABEL_WIDTH)
+                .with_child(Label::new("Minimum").fix_width(RANGE_INPUT_WIDTH))
+                .with_default_spacer()
+                .with_child(Label::new("Maximum").fix_width(RANGE_INPUT_WIDTH)),
+        )
+        .with_child(range_setting("Font size", SettingsRanges::font_size))
+        .with_child(range_setting("Text margin", SettingsRanges::margin))
+        .with_child(range_setting(
+            "Paragraph spacing",
+            SettingsRanges::paragraph_spacing,
+        ))
+        .with_child(range_setting("Line height", SettingsRanges::line_height))
+        .with_child(range_setting(
+            "First line indent",
+            SettingsRanges::first_line_indent,
+        ))
+        .with_child(
+            Label::dynamic(|ranges: &SettingsRanges, _| {
+                if ranges.is_valid() {
+                    String::new()
+                } else {
+                    "Invalid range, the default one is used".to_string()
+                }
+            })
+            .with_text_color(Color::RED),
+        )
+}
+
+fn range_setting(
+    label: &'static str,
+    lens: impl Lens<SettingsRanges, SettingRange> + 'static,
+) -> impl Widget<SettingsRanges> {
+    Flex::row()
+        .with_child(Label::new(label).fix_width(RANGE_LABEL_WIDTH))
+        .with_child(number_input().lens(SettingRange::min))
+        .with_default_spacer()
+        .with_child(number_input().lens(SettingRange::max))
+        .padding((0., 2.))
+        .lens(lens)
+}
+
+fn number_input() -> impl Widget<f64> {
+    TextBox::new()
+        .with_formatter(ParseFormatter::new())
+        .update_data_while_editing(true)
+        .fix_width(RANGE_INPUT_WIDTH)
+}
+
+fn theme_presets() -> impl Widget<Theme> {
+    let mut presets = Flex::row();
+    for preset in Theme::presets() {
+        presets.add_flex_child(
+            Button::new(preset.name.clone())
+                .on_click(move |_, theme: &mut Theme, _| {
+                    *theme = preset.clone();
+                })
+                .expand_width(),
+            1.,
+        );
+    }
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::dynamic(|theme: &Theme, _| {
+            format!("Theme: {}", theme.name)
+        }))
+        .with_child(presets.expand_width())
+}